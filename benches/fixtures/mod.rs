@@ -0,0 +1,93 @@
+//! Generator of realistic server responses for the benchmarks
+//!
+//! The responses are generated instead of checked in, so that their size can be varied. The
+//! songs have the tags and attributes a tagged library typically has, with values that vary
+//! between songs like in a real library.
+
+use std::fmt::Write;
+
+/// Song `i` of the library, in the format of `playlistinfo` and `listallinfo`
+fn song(out: &mut String, i: usize, in_queue: bool) {
+    let artist = i / 120;
+    let album = i / 12;
+    let track = i % 12 + 1;
+
+    writeln!(
+        out,
+        "file: Artist {artist}/Album {album}/{track:02} Song {i}.flac\n\
+         Last-Modified: 2021-{month:02}-{day:02}T12:{min:02}:00Z\n\
+         Format: 44100:16:2\n\
+         Artist: Artist {artist}\n\
+         AlbumArtist: Artist {artist}\n\
+         ArtistSort: Artist {artist}\n\
+         Title: Song number {i}\n\
+         Album: Album {album}\n\
+         Track: {track}\n\
+         Date: {year}\n\
+         Genre: Genre {genre}\n\
+         Disc: 1\n\
+         MUSICBRAINZ_ARTISTID: 0383dadf-2a4e-4d10-a46a-e9e041da8eb3\n\
+         MUSICBRAINZ_ALBUMID: 9f41ef3e-6a4e-4cf4-b3b5-{album:012}\n\
+         MUSICBRAINZ_TRACKID: 2c8e4a38-f2a4-4c33-8f39-{i:012}\n\
+         Time: {secs}\n\
+         duration: {secs}.{millis:03}",
+        month = i % 12 + 1,
+        day = i % 28 + 1,
+        min = i % 60,
+        year = 1960 + i % 60,
+        genre = i % 20,
+        secs = 120 + i % 300,
+        millis = i % 1000,
+    )
+    .unwrap();
+
+    if in_queue {
+        writeln!(out, "Pos: {}\nId: {}", i, i + 1).unwrap();
+    }
+}
+
+/// Response of `playlistinfo` for a queue of `songs` songs
+pub fn playlistinfo(songs: usize) -> String {
+    let mut out = String::new();
+
+    for i in 0..songs {
+        song(&mut out, i, true);
+    }
+
+    out.push_str("OK\n");
+    out
+}
+
+/// Response of a recursive `listallinfo` of a library of `songs` songs
+pub fn listallinfo(songs: usize) -> String {
+    let mut out = String::new();
+
+    for i in 0..songs {
+        if i % 120 == 0 {
+            writeln!(out, "directory: Artist {}", i / 120).unwrap();
+        }
+        if i % 12 == 0 {
+            writeln!(
+                out,
+                "directory: Artist {}/Album {}\nLast-Modified: 2021-01-01T00:00:00Z",
+                i / 120,
+                i / 12
+            )
+            .unwrap();
+        }
+
+        song(&mut out, i, false);
+    }
+
+    out.push_str("OK\n");
+    out
+}
+
+/// Response of `status` while playing
+pub fn status() -> String {
+    "volume: 80\nrepeat: 0\nrandom: 1\nsingle: 0\nconsume: 0\npartition: default\n\
+     playlist: 1234\nplaylistlength: 10000\nmixrampdb: 0.000000\nstate: play\nsong: 42\n\
+     songid: 43\ntime: 61:245\nelapsed: 61.317\nbitrate: 903\nduration: 245.280\n\
+     audio: 44100:16:2\nnextsong: 43\nnextsongid: 44\nOK\n"
+        .to_string()
+}
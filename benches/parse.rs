@@ -0,0 +1,48 @@
+use async_mpd::{KeyValueResponse, MixedResponseResponse, ResponseHandler, Status, Tracks};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures_lite::future::block_on;
+
+mod fixtures;
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+
+fn playlistinfo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playlistinfo");
+    group.sample_size(10);
+
+    for songs in SIZES {
+        let resp = fixtures::playlistinfo(songs);
+        group.throughput(Throughput::Bytes(resp.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(songs), &resp, |b, resp| {
+            b.iter(|| block_on(Tracks::handle(&mut resp.as_bytes())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn listallinfo(c: &mut Criterion) {
+    let mut group = c.benchmark_group("listallinfo");
+    group.sample_size(10);
+
+    for songs in SIZES {
+        let resp = fixtures::listallinfo(songs);
+        group.throughput(Throughput::Bytes(resp.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(songs), &resp, |b, resp| {
+            b.iter(|| block_on(MixedResponseResponse::handle(&mut resp.as_bytes())).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn status(c: &mut Criterion) {
+    let resp = fixtures::status();
+
+    c.bench_function("status", |b| {
+        b.iter(|| block_on(KeyValueResponse::<Status>::handle(&mut resp.as_bytes())).unwrap())
+    });
+}
+
+criterion_group!(benches, playlistinfo, listallinfo, status);
+criterion_main!(benches);
@@ -2,6 +2,8 @@
 // use tokio as runtime;
 use async_std as runtime;
 
+use async_mpd::Primary;
+
 #[runtime::main]
 async fn main() -> Result<(), async_mpd::Error> {
     femme::with_level(log::LevelFilter::Debug);
@@ -17,14 +19,14 @@ async fn main() -> Result<(), async_mpd::Error> {
     for track in queue {
         println!(
             "{:3}: {} - {}",
-            track.id.unwrap_or(0),
-            track.artist.unwrap_or_else(|| "<NoArtist>".to_string()),
-            track.title.unwrap_or_else(|| "<NoTitle>".to_string()),
+            track.id.map_or(0, |id| id.0),
+            track.artist.primary().unwrap_or("<NoArtist>"),
+            track.title.as_deref().unwrap_or("<NoTitle>"),
         );
     }
 
-    // Play track nr 2 in the queue
-    mpd.playid(2).await?;
+    // Play the track with id 2 in the queue
+    mpd.playid(async_mpd::SongId(2)).await?;
 
     // Get and print the current server status
     println!("{:?}", mpd.status().await?);
@@ -42,6 +42,11 @@ async fn main() -> Result<(), Error> {
         match resp {
             WrappedResponse::Status(s) => println!("{:?}", s),
             WrappedResponse::Stats(s) => println!("{:?}", s),
+            WrappedResponse::Raw(pairs) => {
+                for (k, v) in pairs {
+                    println!("{}: {}", k, v);
+                }
+            }
             _ => (),
         };
 
@@ -50,10 +55,11 @@ async fn main() -> Result<(), Error> {
     }
 }
 
-async fn enum_dispatcher<C: MpdCmd + Copy>(
-    mpd: &mut MpdClient,
-    cmd: C,
-) -> Result<WrappedResponse, async_mpd::Error> {
+async fn enum_dispatcher<C>(mpd: &mut MpdClient, cmd: C) -> Result<WrappedResponse, Error>
+where
+    C: MpdCmd + Copy,
+    <C::Handler as ResponseHandler>::Response: Into<WrappedResponse>,
+{
     let mut tries = 0;
 
     let ret = loop {
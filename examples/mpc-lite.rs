@@ -1,4 +1,4 @@
-use async_mpd::{Error, Filter, MpdClient, Tag, ToFilterExpr};
+use async_mpd::prelude::*;
 use structopt::StructOpt;
 
 // To use tokio you would do:
@@ -84,7 +84,7 @@ async fn main() -> Result<(), Error> {
             client.play().await?;
         }
         Command::Playid { id } => {
-            client.playid(id).await?;
+            client.playid(SongId(id)).await?;
         }
         Command::Pause => {
             client.pause().await?;
@@ -0,0 +1,14 @@
+#![no_main]
+
+use async_mpd::{Ack, Error};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|line: &str| {
+    let _ = line.parse::<Ack>();
+
+    let error = Error::ServerError {
+        msg: line.to_string(),
+    };
+    let _ = error.kind();
+    let _ = error.is_retryable();
+});
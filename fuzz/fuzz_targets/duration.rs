@@ -0,0 +1,14 @@
+#![no_main]
+
+use async_mpd::RespMap;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|value: &str| {
+    let mut map = RespMap::new();
+    map.insert("duration", value);
+
+    if let Some(duration) = map.as_duration("duration") {
+        // Parsed durations are never negative, and fit in a `Duration`
+        assert!(duration.as_secs_f64() >= 0.0);
+    }
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use async_mpd::{BinaryResponse, MixedResponseResponse, ResponseHandler, Tracks};
+use futures_lite::future::block_on;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = block_on(MixedResponseResponse::handle(&mut &data[..]));
+    let _ = block_on(Tracks::handle(&mut &data[..]));
+    let _ = block_on(BinaryResponse::handle(&mut &data[..]));
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use async_mpd::{Output, RespMap, Stats, Status, Track};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    let map = || RespMap::from_string(input.to_string());
+
+    let _ = Status::from(map());
+    let _ = Stats::from(map());
+    let _ = Track::from(map());
+    let _ = Output::from(map());
+});
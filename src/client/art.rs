@@ -0,0 +1,192 @@
+use async_lock::OnceCell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{Error, MpdClient, Primary, Subsystem, Track};
+
+/// Key of a cover in an [`ArtCache`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ArtKey {
+    /// Cover of an album, shared by all of its songs
+    Album {
+        /// Album artist, or the artist if there is no album artist
+        artist: Option<String>,
+        album: String,
+    },
+    /// Cover of a song without an album tag
+    Uri(String),
+}
+
+impl ArtKey {
+    /// Key of the cover of `track`
+    pub fn of(track: &Track) -> Self {
+        match &track.album {
+            Some(album) => ArtKey::Album {
+                artist: track
+                    .album_artist
+                    .as_deref()
+                    .or_else(|| track.artist.primary())
+                    .map(String::from),
+                album: album.clone(),
+            },
+            None => ArtKey::Uri(track.file.clone()),
+        }
+    }
+}
+
+/// A cached cover, None if the album has no cover
+type Cover = Option<Arc<Vec<u8>>>;
+
+#[derive(Debug, Default)]
+struct ArtCacheState {
+    covers: HashMap<ArtKey, Arc<OnceCell<Cover>>>,
+    /// Database update time the covers were fetched at
+    db_update: Option<i32>,
+}
+
+/// Cache of album covers
+///
+/// Covers are fetched with `albumart` the first time they are asked for, and kept until the
+/// database changes, which is detected from the database update time in `stats`. Feed the
+/// idle events to [`on_event`](ArtCache::on_event) to drop stale covers.
+///
+/// The cache can be cloned and shared between tasks. Concurrent requests for the same cover
+/// wait for a single fetch.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{ArtCache, Error, MpdClient};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let covers = ArtCache::new();
+///
+///     for track in mpd.queue().await? {
+///         if let Some(cover) = covers.get(&mut mpd, &track).await? {
+///             println!("{}: {} bytes", track.file, cover.len());
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ArtCache {
+    state: Arc<Mutex<ArtCacheState>>,
+}
+
+impl ArtCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cover of the album of `track`, None if there is none
+    pub async fn get(&self, client: &mut MpdClient, track: &Track) -> Result<Cover, Error> {
+        self.fetch(client, ArtKey::of(track), &track.file).await
+    }
+
+    /// Cover for the song `uri`, None if there is none
+    pub async fn get_uri(&self, client: &mut MpdClient, uri: &str) -> Result<Cover, Error> {
+        self.fetch(client, ArtKey::Uri(uri.to_string()), uri).await
+    }
+
+    /// Drop the cover with the key `key`
+    pub fn invalidate(&self, key: &ArtKey) {
+        self.state().covers.remove(key);
+    }
+
+    /// Drop all covers
+    pub fn clear(&self) {
+        self.state().covers.clear();
+    }
+
+    /// Drop all covers if the database changed since they were fetched. Returns true if
+    /// dropped
+    pub async fn refresh(&self, client: &mut MpdClient) -> Result<bool, Error> {
+        let db_update = client.stats().await?.db_update;
+        let mut state = self.state();
+
+        if state.db_update == Some(db_update) {
+            return Ok(false);
+        }
+
+        state.covers.clear();
+        state.db_update = Some(db_update);
+        Ok(true)
+    }
+
+    /// Handle an idle event, dropping the covers when the database changed or an update
+    /// finished. Returns true if dropped
+    pub async fn on_event(
+        &self,
+        client: &mut MpdClient,
+        subsystem: Subsystem,
+    ) -> Result<bool, Error> {
+        match subsystem {
+            Subsystem::Database | Subsystem::Update => self.refresh(client).await,
+            _ => Ok(false),
+        }
+    }
+
+    /// Number of cached covers, including albums without a cover
+    pub fn len(&self) -> usize {
+        self.state()
+            .covers
+            .values()
+            .filter(|cover| cover.get().is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    async fn fetch(&self, client: &mut MpdClient, key: ArtKey, uri: &str) -> Result<Cover, Error> {
+        let cell = self
+            .state()
+            .covers
+            .entry(key)
+            .or_insert_with(|| Arc::new(OnceCell::new()))
+            .clone();
+
+        // A failed fetch leaves the cell empty, to be tried again by the next request
+        let cover = cell
+            .get_or_try_init(|| async { Ok::<_, Error>(client.albumart(uri).await?.map(Arc::new)) })
+            .await?;
+
+        Ok(cover.clone())
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, ArtCacheState> {
+        // The state is left consistent even if a panic happened while it was locked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArtKey;
+    use crate::Track;
+
+    #[test]
+    fn art_keys() {
+        let track = Track::builder("a/1.flac")
+            .artist("Artist")
+            .album("Album")
+            .build();
+
+        assert_eq!(
+            ArtKey::of(&track),
+            ArtKey::Album {
+                artist: Some("Artist".into()),
+                album: "Album".into(),
+            }
+        );
+
+        let single = Track::builder("b.flac").artist("Artist").build();
+        assert_eq!(ArtKey::of(&single), ArtKey::Uri("b.flac".into()));
+    }
+}
@@ -0,0 +1,209 @@
+//! Sockets and timers of the runtime selected with the `backend-*` features
+//!
+//! `backend-tokio` takes precedence when both backends are enabled.
+
+#[cfg(not(any(feature = "backend-async-net", feature = "backend-tokio")))]
+compile_error!("the client needs a socket backend, enable `backend-async-net` or `backend-tokio`");
+
+#[cfg(all(feature = "backend-async-net", not(feature = "backend-tokio")))]
+mod imp {
+    use std::time::{Duration, Instant};
+
+    #[cfg(unix)]
+    pub(crate) use async_net::unix::UnixStream;
+    pub use async_net::AsyncToSocketAddrs as ToSocketAddrs;
+    pub(crate) use async_net::TcpStream;
+
+    pub(crate) async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> std::io::Result<TcpStream> {
+        TcpStream::connect(addr).await
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &std::path::Path) -> std::io::Result<UnixStream> {
+        UnixStream::connect(path).await
+    }
+
+    pub(crate) fn sock_ref(stream: &TcpStream) -> socket2::SockRef<'_> {
+        socket2::SockRef::from(stream)
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        async_io::Timer::after(duration).await;
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant) {
+        async_io::Timer::at(deadline).await;
+    }
+}
+
+#[cfg(feature = "backend-tokio")]
+mod imp {
+    use futures_lite::io::{AsyncRead, AsyncWrite};
+    use std::io;
+    use std::net::{Shutdown, SocketAddr};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, Instant};
+    use tokio::io::ReadBuf;
+
+    pub use tokio::net::ToSocketAddrs;
+
+    /// Tokio stream with the `futures` io traits
+    pub(crate) struct Compat<S>(S);
+
+    pub(crate) type TcpStream = Compat<tokio::net::TcpStream>;
+    #[cfg(unix)]
+    pub(crate) type UnixStream = Compat<tokio::net::UnixStream>;
+
+    pub(crate) async fn connect_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> {
+        tokio::net::TcpStream::connect(addr).await.map(Compat)
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &std::path::Path) -> io::Result<UnixStream> {
+        tokio::net::UnixStream::connect(path).await.map(Compat)
+    }
+
+    pub(crate) fn sock_ref(stream: &TcpStream) -> socket2::SockRef<'_> {
+        socket2::SockRef::from(&stream.0)
+    }
+
+    pub(crate) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant) {
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+
+    impl TcpStream {
+        pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+            self.0.peer_addr()
+        }
+    }
+
+    impl<S> Compat<S> {
+        /// Tokio streams can only be shut down asynchronously, and are closed when dropped
+        pub(crate) fn shutdown(&self, _: Shutdown) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<S: tokio::io::AsyncRead + Unpin> AsyncRead for Compat<S> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let mut buf = ReadBuf::new(buf);
+            match Pin::new(&mut self.0).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+                Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    impl<S: tokio::io::AsyncWrite + Unpin> AsyncWrite for Compat<S> {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.0).poll_shutdown(cx)
+        }
+    }
+}
+
+pub use imp::ToSocketAddrs;
+pub(crate) use imp::{connect_tcp, sleep, sleep_until, sock_ref, TcpStream};
+#[cfg(unix)]
+pub(crate) use imp::{connect_unix, UnixStream};
+
+#[cfg(test)]
+mod test {
+    use crate::client::mock::{run, MockServer};
+    use crate::client::transport::SocketOptions;
+    use crate::{ConnectionState, MpdClient, ServerAddress};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[test]
+    fn connect_and_sleep() {
+        let server = MockServer::new(&[(&["stop"], "OK\n")]);
+
+        run(async {
+            let mut client = server.client().await;
+            super::sleep(Duration::from_millis(1)).await;
+            client.stop().await.unwrap();
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn failover() {
+        // Nothing listens on the first address anymore
+        let closed = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let server = MockServer::new(&[]);
+        let addr = server.addr;
+
+        let mut addrs = vec![ServerAddress::Tcp(closed)];
+        #[cfg(unix)]
+        addrs.push(ServerAddress::Unix("/nonexistent/mpd/socket".into()));
+        addrs.push(ServerAddress::Tcp(addr));
+
+        run(async {
+            let mut client = MpdClient::builder().failover(&addrs).build();
+            client.connect_failover().await.unwrap();
+
+            match client.connection_state() {
+                ConnectionState::Connected { server, .. } => {
+                    assert_eq!(server, ServerAddress::Tcp(addr))
+                }
+                state => panic!("{:?}", state),
+            }
+        });
+        server.finish();
+
+        // None of the servers is there anymore
+        run(async {
+            let mut client = MpdClient::builder().failover(&addrs).build();
+            assert!(client.connect_failover().await.is_err());
+            assert!(!client.is_connected());
+        });
+    }
+
+    #[test]
+    fn socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let options = SocketOptions {
+            keepalive: Some(Duration::from_secs(60)),
+            recv_buffer_size: Some(64 * 1024),
+            ..SocketOptions::default()
+        };
+
+        run(async {
+            let stream = super::connect_tcp(addr).await.unwrap();
+            options.apply(&stream).unwrap();
+
+            let socket = super::sock_ref(&stream);
+            assert!(socket.tcp_nodelay().unwrap());
+            assert!(socket.keepalive().unwrap());
+            assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+        });
+    }
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{
+    client::{
+        mpdclient::EventHandler,
+        tap::{WireTap, WireTapFn},
+        throttle::TokenBucket,
+        transport::SocketOptions,
+    },
+    ConnectionEvent, MpdClient, RateLimit, ReconnectPolicy, SecretString, ServerAddress, Tag,
+    WireDirection,
+};
+
+/// Builder for [`MpdClient`]
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, MpdClient, Tag};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::builder()
+///         .tag_profile(&[Tag::Artist, Tag::Album, Tag::Title])
+///         .build();
+///
+///     mpd.connect("localhost:6600").await?;
+///
+///     // Only the artist, album and title tags are included
+///     let queue = mpd.queue().await?;
+///     println!("{} songs", queue.len());
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct MpdClientBuilder {
+    tag_profile: Option<Vec<Tag>>,
+    event_handler: Option<EventHandler>,
+    password: Option<SecretString>,
+    partition: Option<String>,
+    reconnect_policy: Option<ReconnectPolicy>,
+    socket_options: SocketOptions,
+    wire_tap: Option<WireTapFn>,
+    listallinfo_limit: Option<u32>,
+    slow_command_threshold: Option<Duration>,
+    check_versions: bool,
+    failover: Vec<ServerAddress>,
+    rate_limit: Option<RateLimit>,
+    #[cfg(feature = "tls")]
+    tls_domain: Option<String>,
+}
+
+impl MpdClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tags the server should include in song responses
+    ///
+    /// The profile is applied with `tagtypes` after every connect and reconnect. Leaving out
+    /// unused tags makes listing large numbers of songs a lot faster. Ignored by servers older
+    /// than Mpd 0.21.
+    pub fn tag_profile(mut self, tags: &[Tag]) -> Self {
+        self.tag_profile = Some(tags.to_vec());
+        self
+    }
+
+    /// Password to authenticate with after every connect and reconnect
+    pub fn password<S: Into<SecretString>>(mut self, password: S) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Partition to switch to after every connect and reconnect. Requires Mpd 0.22
+    ///
+    /// Keeps the commands of the client in its partition, also after the connection was lost.
+    pub fn partition(mut self, name: &str) -> Self {
+        self.partition = Some(name.to_string());
+        self
+    }
+
+    /// Reconnect according to `policy` when the connection is lost while waiting for changes
+    /// with [`MpdClient::idle_stream`]
+    pub fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = Some(policy);
+        self
+    }
+
+    /// Set `TCP_NODELAY` on TCP connections, sending commands without delay. On by default, as
+    /// the commands are small and every one of them waits for a response
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.socket_options.nodelay = nodelay;
+        self
+    }
+
+    /// Enable TCP keepalive, probing the connection after it has been idle for `time`
+    ///
+    /// Makes the OS notice a lost connection while the client waits in `idle`. Off by default.
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.socket_options.keepalive = Some(time);
+        self
+    }
+
+    /// Size of the receive buffer of TCP sockets, the OS default if not set
+    pub fn recv_buffer_size(mut self, bytes: usize) -> Self {
+        self.socket_options.recv_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Capacity of the buffer responses are read through, 8 KiB by default
+    pub fn read_buffer_size(mut self, bytes: usize) -> Self {
+        self.socket_options.read_buffer_size = bytes;
+        self
+    }
+
+    /// Connect with TLS, verifying the server certificate against `domain`
+    ///
+    /// Mpd doesn't support TLS itself, but can be put behind a TLS terminating proxy such as
+    /// stunnel or haproxy. The certificate is checked against the Mozilla root certificates.
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, domain: &str) -> Self {
+        self.tls_domain = Some(domain.to_string());
+        self
+    }
+
+    /// Call `handler` when the client connects, reconnects, or loses the connection
+    ///
+    /// The handler is called from within the client, and should return quickly.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::{ConnectionEvent, MpdClient};
+    ///
+    /// let mpd = MpdClient::builder()
+    ///     .on_event(|event| match event {
+    ///         ConnectionEvent::Disconnected { reason } => println!("Disconnected: {}", reason),
+    ///         _ => println!("Connected"),
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn on_event<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ConnectionEvent) + Send + Sync + 'static,
+    {
+        self.event_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Call `tap` with every line sent to and received from the server, for debugging
+    ///
+    /// Passwords are replaced with `***`, lines longer than 1 KiB are cut, and binary data, such
+    /// as album art, is replaced with a note of its size. The tap is called from within the
+    /// client, and should return quickly.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::{MpdClient, WireDirection};
+    ///
+    /// let mpd = MpdClient::builder()
+    ///     .wire_tap(|direction, line| match direction {
+    ///         WireDirection::Sent => eprintln!("> {}", line),
+    ///         WireDirection::Received => eprintln!("< {}", line),
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn wire_tap<F>(mut self, tap: F) -> Self
+    where
+        F: Fn(WireDirection, &str) + Send + Sync + 'static,
+    {
+        self.wire_tap = Some(Arc::new(tap));
+        self
+    }
+
+    /// Refuse to list the whole database with [`MpdClient::listallinfo`] when it has more than
+    /// `songs` songs, returning [`Error::LibraryTooLarge`] instead
+    ///
+    /// Guards against freezing an application with an accidental listing of a huge library.
+    ///
+    /// [`Error::LibraryTooLarge`]: crate::Error::LibraryTooLarge
+    pub fn listallinfo_limit(mut self, songs: u32) -> Self {
+        self.listallinfo_limit = Some(songs);
+        self
+    }
+
+    /// Log a warning for every command taking longer than `threshold`, response included
+    ///
+    /// The warning carries the name of the command, its duration in milliseconds and the size of
+    /// the response in bytes, as the `cmd`, `duration_ms` and `bytes` key-values of the log
+    /// record. Waiting in `idle` is not logged.
+    pub fn slow_command_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_command_threshold = Some(threshold);
+        self
+    }
+
+    /// Check the protocol version required by commands, failing with [`Error::Unsupported`]
+    /// instead of sending commands the server doesn't support. Off by default
+    ///
+    /// The requirements are those of [`MpdCmd::min_version`], which are only known for the
+    /// commands of this crate, and commands defined with a version.
+    ///
+    /// [`Error::Unsupported`]: crate::Error::Unsupported
+    /// [`MpdCmd::min_version`]: crate::cmd::MpdCmd::min_version
+    pub fn check_versions(mut self, check: bool) -> Self {
+        self.check_versions = check;
+        self
+    }
+
+    /// Servers to connect to with [`MpdClient::connect_failover`], in order of preference
+    ///
+    /// Every connect and reconnect tries the addresses in order, until one of them accepts the
+    /// connection. Useful on a laptop that uses the server at home over a unix socket, and
+    /// another one over TCP elsewhere. Addresses of streams are skipped.
+    pub fn failover(mut self, addrs: &[ServerAddress]) -> Self {
+        self.failover = addrs.to_vec();
+        self
+    }
+
+    /// Limit the rate commands are sent at, so that a runaway loop can't flood the server
+    ///
+    /// Commands over the limit wait until they are allowed, instead of failing.
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    pub fn build(self) -> MpdClient {
+        let mut client = MpdClient::new();
+        client.tag_profile = self.tag_profile;
+        client.event_handler = self.event_handler;
+        client.password = self.password;
+        client.partition = self.partition;
+        client.reconnect_policy = self.reconnect_policy;
+        client.socket_options = self.socket_options;
+        client.wire_tap = self.wire_tap.map(WireTap::new);
+        client.listallinfo_limit = self.listallinfo_limit;
+        client.slow_command_threshold = self.slow_command_threshold;
+        client.check_versions = self.check_versions;
+        client.failover = self.failover;
+        client.throttle = self.rate_limit.map(TokenBucket::new);
+        #[cfg(feature = "tls")]
+        {
+            client.tls_domain = self.tls_domain;
+        }
+        client
+    }
+}
+
+impl fmt::Debug for MpdClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpdClientBuilder")
+            .field("tag_profile", &self.tag_profile)
+            .field("event_handler", &self.event_handler.is_some())
+            .field("password", &self.password)
+            .field("partition", &self.partition)
+            .field("reconnect_policy", &self.reconnect_policy)
+            .field("socket_options", &self.socket_options)
+            .field("wire_tap", &self.wire_tap.is_some())
+            .field("listallinfo_limit", &self.listallinfo_limit)
+            .field("slow_command_threshold", &self.slow_command_threshold)
+            .field("check_versions", &self.check_versions)
+            .field("failover", &self.failover)
+            .field("rate_limit", &self.rate_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debug_without_password() {
+        let builder = MpdClientBuilder::new().password("hunter2");
+        assert!(!format!("{:?}", builder).contains("hunter2"));
+    }
+}
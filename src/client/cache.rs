@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::{cmd, Album, Error, MpdClient, Primary, Subsystem, Track};
+
+/// Local copy of the music database
+///
+/// Loaded once with `listallinfo`, and reloaded only when the database has changed, which is
+/// detected from the database update time in `stats`. Feed the idle events to
+/// [`on_event`](LibraryCache::on_event) to keep it fresh.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, LibraryCache, MpdClient};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let mut library = LibraryCache::load(&mut mpd).await?;
+///     println!("{} songs by {} artists", library.len(), library.artists().len());
+///
+///     loop {
+///         let subsystem = mpd.idle().await?;
+///         if library.on_event(&mut mpd, subsystem).await? {
+///             println!("Library updated, {} songs", library.len());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LibraryCache {
+    /// Tracks by uri
+    tracks: BTreeMap<String, Track>,
+    /// Database update time the cache was loaded at
+    db_update: Option<i32>,
+}
+
+impl LibraryCache {
+    /// Load the whole database
+    pub async fn load(client: &mut MpdClient) -> Result<Self, Error> {
+        let mut cache = Self::default();
+        cache.refresh(client).await?;
+        Ok(cache)
+    }
+
+    /// Reload the database if it changed since it was loaded. Returns true if reloaded
+    pub async fn refresh(&mut self, client: &mut MpdClient) -> Result<bool, Error> {
+        let db_update = client.stats().await?.db_update;
+
+        if self.db_update == Some(db_update) {
+            return Ok(false);
+        }
+
+        // Loading the whole database is the point of the cache, whatever the limit of the client
+        let listing = client.exec(cmd::ListallInfo(None)).await?;
+
+        self.tracks = listing
+            .files
+            .into_iter()
+            .map(|t| (t.file.clone(), t))
+            .collect();
+        self.db_update = Some(db_update);
+        Ok(true)
+    }
+
+    /// Handle an idle event, refreshing the cache when the database changed or an update
+    /// finished. Returns true if reloaded
+    pub async fn on_event(
+        &mut self,
+        client: &mut MpdClient,
+        subsystem: Subsystem,
+    ) -> Result<bool, Error> {
+        match subsystem {
+            Subsystem::Database | Subsystem::Update => self.refresh(client).await,
+            _ => Ok(false),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// The song with uri `file`
+    pub fn get(&self, file: &str) -> Option<&Track> {
+        self.tracks.get(file)
+    }
+
+    /// All songs, ordered by uri
+    pub fn iter(&self) -> impl Iterator<Item = &Track> {
+        self.tracks.values()
+    }
+
+    /// Songs matching `predicate`
+    pub fn filter<F>(&self, mut predicate: F) -> Vec<&Track>
+    where
+        F: FnMut(&Track) -> bool,
+    {
+        self.iter().filter(|t| predicate(t)).collect()
+    }
+
+    /// All artists, sorted
+    pub fn artists(&self) -> Vec<&str> {
+        let mut artists: Vec<&str> = self
+            .iter()
+            .flat_map(|t| t.artist.iter().map(String::as_str))
+            .collect();
+
+        artists.sort_unstable();
+        artists.dedup();
+        artists
+    }
+
+    /// Albums with songs by `artist`
+    pub fn albums_by_artist(&self, artist: &str) -> Vec<Album> {
+        let tracks = self
+            .iter()
+            .filter(|t| t.artist.iter().any(|a| a == artist))
+            .cloned()
+            .collect();
+
+        Album::group(tracks)
+    }
+
+    /// All albums
+    pub fn albums(&self) -> Vec<Album> {
+        Album::group(self.iter().cloned().collect())
+    }
+
+    /// Songs with `needle` in the title or the primary artist, ignoring case
+    pub fn search(&self, needle: &str) -> Vec<&Track> {
+        let needle = needle.to_lowercase();
+        let matches = |s: Option<&str>| s.is_some_and(|s| s.to_lowercase().contains(&needle));
+
+        self.filter(|t| matches(t.title.as_deref()) || matches(t.artist.primary()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn queries() {
+        let cache = LibraryCache {
+            tracks: vec![
+                Track::builder("a.flac").artist("B").title("Song").build(),
+                Track::builder("b.flac").artist("A").artist("B").build(),
+            ]
+            .into_iter()
+            .map(|t| (t.file.clone(), t))
+            .collect(),
+            db_update: Some(1),
+        };
+
+        assert_eq!(cache.artists(), vec!["A", "B"]);
+        assert_eq!(cache.search("song")[0].file, "a.flac");
+        assert_eq!(cache.search("a").len(), 1);
+        assert_eq!(cache.albums_by_artist("A").len(), 1);
+    }
+}
@@ -1,225 +1,1703 @@
 //! MPD commands
+//!
+//! Commands with optional arguments are created with `new`, taking the required arguments, and
+//! the optional ones are set with a method each. Their fields are public as well.
+//!
+//! ```
+//! use async_mpd::cmd::{MpdCmd, Search};
+//! use async_mpd::{SortKey, Tag};
+//!
+//! let search = Search::new(Some("(Artist == 'Miles Davis')"))
+//!     .sort(SortKey::Tag(Tag::Date))
+//!     .window(0..20);
+//!
+//! assert_eq!(
+//!     search.to_cmdline(),
+//!     "search \"(Artist == 'Miles Davis')\" \"sort\" \"Date\" \"window\" \"0:20\"\n"
+//! );
+//! ```
+
+use std::time::Duration;
 
 use crate::{
     client::resp::{
-        handlers::{MixedResponseResponse, OkResponse, RespMapResponse, ResponseHandler, Tracks},
+        handlers::{
+            BinaryResponse, CommandListResponse, KeyValueResponse, ListResponse,
+            MixedResponseResponse, OkResponse, RawResponse, ResponseHandler, SingleLineResp,
+            Tracks,
+        },
         respmap_handlers::ListallResponse,
     },
-    DatabaseVersion,
+    protocol::quote,
+    ConsumeMode, DatabaseVersion, Error, Fingerprint, Mount, Neighbor, Output, Position,
+    ReplayGainMode, SecretString, SingleMode, SongId, SongPos, Sort, Sticker, StickerMatch,
+    StoredPlaylist, Tag, Version, Volume, Window,
 };
 
-#[derive(Copy, Clone)]
-pub struct Stats;
-#[derive(Copy, Clone)]
-pub struct Status;
-
-#[derive(Copy, Clone)]
-pub struct Setvol(pub u32);
-#[derive(Copy, Clone)]
-pub struct Repeat(pub bool);
-#[derive(Copy, Clone)]
-pub struct Random(pub bool);
-#[derive(Copy, Clone)]
-pub struct Consume(pub bool);
-
-#[derive(Copy, Clone)]
-pub struct PlayId(pub u32);
-#[derive(Copy, Clone)]
-pub struct QueueClear;
-#[derive(Copy, Clone)]
-pub struct QueueAdd<'a>(pub &'a str);
-
-#[derive(Copy, Clone)]
-pub struct Search<'a>(pub Option<&'a str>);
-#[derive(Copy, Clone)]
-pub struct PlaylistInfo;
-
-#[derive(Copy, Clone)]
-pub struct Stop;
-#[derive(Copy, Clone)]
-pub struct PlayPause(pub bool);
-#[derive(Copy, Clone)]
-pub struct Next;
-#[derive(Copy, Clone)]
-pub struct Prev;
-
-#[derive(Copy, Clone)]
-pub struct Rescan<'a>(pub Option<&'a str>);
-#[derive(Copy, Clone)]
-pub struct Update<'a>(pub Option<&'a str>);
-
-#[derive(Copy, Clone)]
-pub struct Idle;
-#[derive(Copy, Clone)]
-pub struct NoIdle;
-
-#[derive(Copy, Clone)]
-pub struct Listall<'a>(pub Option<&'a str>);
-#[derive(Copy, Clone)]
-pub struct ListallInfo<'a>(pub Option<&'a str>);
-
+/// A Mpd command and the handler of its response
+///
+/// Commands not known by this crate can be used by implementing this trait, directly or with
+/// the [`mpd_cmd!`](crate::mpd_cmd) macro, together with one of the exported response handlers.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{cmd::MpdCmd, Error, ListResponse, MpdClient, RespMap};
+///
+/// /// Url scheme supported by the server, as returned by the `urlhandlers` command
+/// struct UrlHandler(String);
+///
+/// impl From<RespMap> for UrlHandler {
+///     fn from(mut map: RespMap) -> Self {
+///         UrlHandler(map.get_def("handler"))
+///     }
+/// }
+///
+/// struct UrlHandlers;
+///
+/// impl MpdCmd for UrlHandlers {
+///     const CMD: &'static str = "urlhandlers";
+///     type Handler = ListResponse<UrlHandler>;
+/// }
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     for handler in mpd.exec(UrlHandlers).await? {
+///         println!("{}", handler.0);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
 pub trait MpdCmd {
     /// The Command name
     const CMD: &'static str;
     /// The Response handler for this command
     type Handler: ResponseHandler;
+    /// Oldest protocol version supporting the command, 0.0.0 for commands supported by all
+    /// versions
+    const MIN_VERSION: Version = Version::new(0, 0, 0);
     /// Optionally returns the commands argument as a String
     fn argument(&self) -> Option<String> {
         None
     }
-    /// Creates the MPD command line for this command
+    /// Returns all arguments of the command. Commands with more than one argument implement
+    /// this instead of `argument`
+    fn arguments(&self) -> Vec<String> {
+        self.argument().into_iter().collect()
+    }
+    /// Creates the MPD command line for this command, exactly as it is sent
+    ///
+    /// Arguments are quoted with [`quote`]. Mpd reads commands a line at a time, so arguments
+    /// can't contain newlines. Rendering a command doesn't need a connection, which makes it
+    /// useful for showing what a tool would do in a dry run, and for testing how commands are
+    /// built.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::cmd::{MpdCmd, Update};
+    ///
+    /// assert_eq!(
+    ///     Update(Some(r#"Jazz/"Kind of Blue""#)).to_cmdline(),
+    ///     "update \"Jazz/\\\"Kind of Blue\\\"\"\n"
+    /// );
+    /// ```
     fn to_cmdline(&self) -> String {
-        if let Some(arg) = self.argument() {
-            format!("{} \"{}\"\n", Self::CMD, arg)
-        } else {
-            format!("{}\n", Self::CMD)
+        let mut line = Self::CMD.to_string();
+
+        for arg in self.arguments() {
+            line.push(' ');
+            line.push_str(&quote(&arg));
         }
+
+        line.push('\n');
+        line
+    }
+    /// The command line as included in errors and logs. Commands with secret arguments
+    /// override this to leave them out
+    fn redacted_cmdline(&self) -> String {
+        self.to_cmdline()
+    }
+    /// Oldest protocol version supporting the command with its arguments. Commands with
+    /// arguments requiring later versions than [`MIN_VERSION`](MpdCmd::MIN_VERSION) override
+    /// this
+    ///
+    /// Checked before the command is sent when enabled with
+    /// [`MpdClientBuilder::check_versions`](crate::MpdClientBuilder::check_versions).
+    fn min_version(&self) -> Version {
+        Self::MIN_VERSION
     }
 }
 
-impl<'a> MpdCmd for ListallInfo<'a> {
-    const CMD: &'static str = "listallinfo";
-    type Handler = MixedResponseResponse;
+/// The later of two versions, usable in constants
+const fn later(a: Version, b: Version) -> Version {
+    let a_first = a.major < b.major
+        || (a.major == b.major && (a.minor < b.minor || (a.minor == b.minor && a.patch < b.patch)));
 
-    fn argument(&self) -> Option<String> {
-        self.0.map(ToString::to_string)
+    if a_first {
+        b
+    } else {
+        a
+    }
+}
+
+/// Fail if an argument of `cmdline` contains a line break
+///
+/// Mpd reads a command per line, so a line break in an argument, such as in a file name or a
+/// search typed by a user, would end the command and start another one. Line breaks between the
+/// commands of a command list are outside the quoted arguments, and allowed.
+pub(crate) fn check_line_breaks(cmdline: &str) -> Result<(), Error> {
+    let line_break = || Error::InvalidArgument {
+        msg: "argument contains a line break".into(),
+    };
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for c in cmdline.chars() {
+        match c {
+            '\r' => return Err(line_break()),
+            '\n' if quoted => return Err(line_break()),
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Conversion of a command argument into its protocol representation
+pub trait CmdArg {
+    /// Returns the argument as a String, or None if it should be left out
+    fn to_arg(&self) -> Option<String>;
+}
+
+impl CmdArg for bool {
+    fn to_arg(&self) -> Option<String> {
+        Some((*self as u32).to_string())
+    }
+}
+
+impl CmdArg for u32 {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for &str {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for String {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.clone())
+    }
+}
+
+impl CmdArg for SongId {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for SongPos {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for Position {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for ConsumeMode {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for SingleMode {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl CmdArg for Tag {
+    fn to_arg(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+}
+
+impl<T: CmdArg> CmdArg for Option<T> {
+    fn to_arg(&self) -> Option<String> {
+        self.as_ref().and_then(CmdArg::to_arg)
+    }
+}
+
+/// Define one or more Mpd commands
+///
+/// Each definition creates a struct, without or with a single argument, and implements
+/// [`MpdCmd`](crate::cmd::MpdCmd) for it. The argument type must implement
+/// [`CmdArg`](crate::cmd::CmdArg). Commands added in later protocol versions are marked with
+/// `since(major, minor, patch)` after the handler.
+///
+/// # Example
+/// ```
+/// use async_mpd::{mpd_cmd, RawResponse};
+///
+/// mpd_cmd! {
+///     /// List the available decoder plugins
+///     #[derive(Copy, Clone)]
+///     pub struct Decoders => "decoders", RawResponse;
+///
+///     /// Read the comments of a song
+///     #[derive(Copy, Clone)]
+///     pub struct ReadComments<'a>(pub &'a str) => "readcomments", RawResponse;
+///
+///     /// Decode a song and read its picture
+///     #[derive(Copy, Clone)]
+///     pub struct ReadPicture<'a>(pub &'a str) => "readpicture", RawResponse, since(0, 22, 0);
+/// }
+/// ```
+#[macro_export]
+macro_rules! mpd_cmd {
+    () => {};
+
+    ($(#[$meta:meta])* $vis:vis struct $name:ident => $cmd:literal, $handler:ty
+        $(, since($major:literal, $minor:literal, $patch:literal))?; $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::cmd::MpdCmd for $name {
+            const CMD: &'static str = $cmd;
+            type Handler = $handler;
+            $(const MIN_VERSION: $crate::Version = $crate::Version::new($major, $minor, $patch);)?
+        }
+
+        $crate::mpd_cmd!($($rest)*);
+    };
+
+    ($(#[$meta:meta])* $vis:vis struct $name:ident $(<$lt:lifetime>)? (pub $arg:ty) => $cmd:literal, $handler:ty
+        $(, since($major:literal, $minor:literal, $patch:literal))?; $($rest:tt)*) => {
+        $(#[$meta])*
+        $vis struct $name $(<$lt>)? (pub $arg);
+
+        impl $(<$lt>)? $crate::cmd::MpdCmd for $name $(<$lt>)? {
+            const CMD: &'static str = $cmd;
+            type Handler = $handler;
+            $(const MIN_VERSION: $crate::Version = $crate::Version::new($major, $minor, $patch);)?
+
+            fn argument(&self) -> Option<String> {
+                $crate::cmd::CmdArg::to_arg(&self.0)
+            }
+        }
+
+        $crate::mpd_cmd!($($rest)*);
+    };
+}
+
+/// Commands executed together in a command list, without commands of other clients in between
+///
+/// Implemented for tuples of two to five commands, with the responses returned as a tuple as
+/// well, and for a `Vec` of commands of the same type. The server stops at the first command
+/// that fails, and the error of that command is returned.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{cmd, Error, MpdClient};
+///
+/// # async fn example(mpd: &mut MpdClient) -> Result<(), Error> {
+/// let (status, stats) = mpd.exec(cmd::CommandList((cmd::Status, cmd::Stats))).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct CommandList<T>(pub T);
+
+impl<T> CommandList<T>
+where
+    Self: MpdCmd,
+{
+    /// The lines of the command list, exactly as they are sent, from `command_list_ok_begin`
+    /// to `command_list_end`
+    ///
+    /// Secret arguments, such as passwords, are included. Use
+    /// [`redacted_cmdline`](MpdCmd::redacted_cmdline) for lines shown to users.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::cmd::{CommandList, QueueAdd, QueueClear};
+    ///
+    /// let list = CommandList((QueueClear, QueueAdd("Jazz/So What.flac")));
+    /// assert_eq!(
+    ///     list.render(),
+    ///     "command_list_ok_begin\nclear\nadd \"Jazz/So What.flac\"\ncommand_list_end\n"
+    /// );
+    /// ```
+    pub fn render(&self) -> String {
+        self.to_cmdline()
+    }
+}
+
+macro_rules! command_list {
+    ($($cmd:ident $idx:tt),+) => {
+        impl<$($cmd),+> MpdCmd for CommandList<($($cmd,)+)>
+        where
+            $($cmd: MpdCmd, <$cmd::Handler as ResponseHandler>::Response: Send,)+
+        {
+            const CMD: &'static str = "command_list_ok_begin";
+            type Handler = CommandListResponse<($($cmd::Handler,)+)>;
+            const MIN_VERSION: Version = {
+                let version = Version::new(0, 0, 0);
+                $(let version = later(version, $cmd::MIN_VERSION);)+
+                version
+            };
+
+            fn to_cmdline(&self) -> String {
+                let mut lines = String::from("command_list_ok_begin\n");
+                $(lines.push_str(&self.0.$idx.to_cmdline());)+
+                lines.push_str("command_list_end\n");
+                lines
+            }
+
+            fn redacted_cmdline(&self) -> String {
+                let mut lines = String::from("command_list_ok_begin\n");
+                $(lines.push_str(&self.0.$idx.redacted_cmdline());)+
+                lines.push_str("command_list_end\n");
+                lines
+            }
+
+            fn min_version(&self) -> Version {
+                let version = Version::new(0, 0, 0);
+                $(let version = later(version, self.0.$idx.min_version());)+
+                version
+            }
+        }
+    };
+}
+
+command_list!(A 0, B 1);
+command_list!(A 0, B 1, C 2);
+command_list!(A 0, B 1, C 2, D 3);
+command_list!(A 0, B 1, C 2, D 3, E 4);
+
+impl<C> MpdCmd for CommandList<Vec<C>>
+where
+    C: MpdCmd,
+    <C::Handler as ResponseHandler>::Response: Send,
+{
+    const CMD: &'static str = "command_list_ok_begin";
+    type Handler = CommandListResponse<Vec<C::Handler>>;
+    const MIN_VERSION: Version = C::MIN_VERSION;
+
+    fn to_cmdline(&self) -> String {
+        let mut lines = String::from("command_list_ok_begin\n");
+        self.0
+            .iter()
+            .for_each(|cmd| lines.push_str(&cmd.to_cmdline()));
+        lines.push_str("command_list_end\n");
+        lines
+    }
+
+    fn redacted_cmdline(&self) -> String {
+        let mut lines = String::from("command_list_ok_begin\n");
+        self.0
+            .iter()
+            .for_each(|cmd| lines.push_str(&cmd.redacted_cmdline()));
+        lines.push_str("command_list_end\n");
+        lines
     }
+
+    fn min_version(&self) -> Version {
+        self.0.iter().fold(C::MIN_VERSION, |version, cmd| {
+            later(version, cmd.min_version())
+        })
+    }
+}
+
+mpd_cmd! {
+    #[derive(Copy, Clone)]
+    pub struct Ping => "ping", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct Stats => "stats", KeyValueResponse<crate::Stats>;
+    #[derive(Copy, Clone)]
+    pub struct Status => "status", KeyValueResponse<crate::Status>;
+    /// The current song, as a list of at most one song
+    #[derive(Copy, Clone)]
+    pub struct CurrentSong => "currentsong", Tracks;
+
+    /// Clear the current player error
+    #[derive(Copy, Clone)]
+    pub struct ClearError => "clearerror", OkResponse;
+
+    #[derive(Copy, Clone)]
+    pub struct Setvol(pub u32) => "setvol", OkResponse;
+    /// The volume. Since Mpd 0.23
+    #[derive(Copy, Clone)]
+    pub struct GetVol => "getvol", SingleLineResp<Volume>, since(0, 23, 0);
+    /// The replay gain mode
+    #[derive(Copy, Clone)]
+    pub struct ReplayGainStatus => "replay_gain_status", SingleLineResp<ReplayGainMode>;
+    #[derive(Copy, Clone)]
+    pub struct Repeat(pub bool) => "repeat", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct Random(pub bool) => "random", OkResponse;
+
+    /// Start playback, at the given position or at the current song
+    #[derive(Copy, Clone)]
+    pub struct Play(pub Option<SongPos>) => "play", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct PlayId(pub SongId) => "playid", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct QueueClear => "clear", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct QueueAdd<'a>(pub &'a str) => "add", OkResponse;
+
+    #[derive(Copy, Clone)]
+    pub struct PlaylistInfo => "playlistinfo", Tracks;
+    /// The song with the given id in the queue
+    #[derive(Copy, Clone)]
+    pub struct PlaylistId(pub SongId) => "playlistid", Tracks;
+    /// Songs in the queue changed since a queue version
+    #[derive(Copy, Clone)]
+    pub struct PlChanges(pub u32) => "plchanges", Tracks;
+    /// Remove the song with the given id from the queue
+    #[derive(Copy, Clone)]
+    pub struct DeleteId(pub SongId) => "deleteid", OkResponse;
+    /// Songs in a stored playlist
+    #[derive(Copy, Clone)]
+    pub struct ListPlaylistInfo<'a>(pub &'a str) => "listplaylistinfo", Tracks;
+    /// Songs in the queue exactly matching a filter
+    #[derive(Copy, Clone)]
+    pub struct PlaylistFind<'a>(pub &'a str) => "playlistfind", Tracks, since(0, 21, 0);
+    /// Add the songs exactly matching a filter to the queue
+    #[derive(Copy, Clone)]
+    pub struct FindAdd<'a>(pub &'a str) => "findadd", OkResponse, since(0, 21, 0);
+    /// Delete a stored playlist
+    #[derive(Copy, Clone)]
+    pub struct Rm<'a>(pub &'a str) => "rm", OkResponse;
+
+    #[derive(Copy, Clone)]
+    pub struct Stop => "stop", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct PlayPause(pub bool) => "pause", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct Next => "next", OkResponse;
+    #[derive(Copy, Clone)]
+    pub struct Prev => "prev", OkResponse;
+
+    #[derive(Copy, Clone)]
+    pub struct Rescan<'a>(pub Option<&'a str>) => "rescan", SingleLineResp<DatabaseVersion>;
+    #[derive(Copy, Clone)]
+    pub struct Update<'a>(pub Option<&'a str>) => "update", SingleLineResp<DatabaseVersion>;
+
+    #[derive(Copy, Clone)]
+    pub struct Idle => "idle", KeyValueResponse<crate::Subsystem>;
+    #[derive(Copy, Clone)]
+    pub struct NoIdle => "noidle", OkResponse;
+    /// Close the connection. The server closes it without responding
+    #[derive(Copy, Clone)]
+    pub struct Close => "close", OkResponse;
+    /// Maximum size of the chunks of binary responses. Requires Mpd 0.22.4
+    #[derive(Copy, Clone)]
+    pub struct BinaryLimit(pub u32) => "binarylimit", OkResponse, since(0, 22, 4);
+
+    /// Chromaprint fingerprint of a song. Requires Mpd 0.22
+    #[derive(Copy, Clone)]
+    pub struct GetFingerprint<'a>(pub &'a str) => "getfingerprint", KeyValueResponse<Fingerprint>,
+        since(0, 22, 0);
+
+    /// Stored playlists
+    #[derive(Copy, Clone)]
+    pub struct ListPlaylists => "listplaylists", ListResponse<StoredPlaylist>;
+
+    /// Audio outputs
+    #[derive(Copy, Clone)]
+    pub struct Outputs => "outputs", ListResponse<Output>;
+
+    /// Storage mounted in the music directory. Requires Mpd 0.19
+    #[derive(Copy, Clone)]
+    pub struct ListMounts => "listmounts", ListResponse<Mount>, since(0, 19, 0);
+    /// Storage found on the network by the neighbor plugins. Requires Mpd 0.19
+    #[derive(Copy, Clone)]
+    pub struct ListNeighbors => "listneighbors", ListResponse<Neighbor>, since(0, 19, 0);
+
+    /// Switch the connection to another partition. Requires Mpd 0.22
+    #[derive(Copy, Clone)]
+    pub struct Partition<'a>(pub &'a str) => "partition", OkResponse, since(0, 22, 0);
+
+    #[derive(Copy, Clone)]
+    pub struct Listall<'a>(pub Option<&'a str>) => "listall", KeyValueResponse<ListallResponse>;
+    #[derive(Copy, Clone)]
+    pub struct ListallInfo<'a>(pub Option<&'a str>) => "listallinfo", MixedResponseResponse;
+    /// Contents of a directory, without recursing into subdirectories
+    #[derive(Copy, Clone)]
+    pub struct Lsinfo<'a>(pub Option<&'a str>) => "lsinfo", MixedResponseResponse;
 }
 
-impl<'a> MpdCmd for QueueAdd<'a> {
-    const CMD: &'static str = "add";
+/// Set the consume mode. The oneshot mode requires Mpd 0.24
+#[derive(Copy, Clone)]
+pub struct Consume(pub ConsumeMode);
+
+impl MpdCmd for Consume {
+    const CMD: &'static str = "consume";
     type Handler = OkResponse;
 
     fn argument(&self) -> Option<String> {
-        Some(self.0.to_string())
+        self.0.to_arg()
+    }
+
+    fn min_version(&self) -> Version {
+        match self.0 {
+            ConsumeMode::Oneshot => Version::new(0, 24, 0),
+            _ => Self::MIN_VERSION,
+        }
     }
 }
 
-impl<'a> MpdCmd for Listall<'a> {
-    const CMD: &'static str = "listall";
-    type Handler = RespMapResponse<ListallResponse>;
+/// Set the single mode. The oneshot mode requires Mpd 0.21
+#[derive(Copy, Clone)]
+pub struct Single(pub SingleMode);
+
+impl MpdCmd for Single {
+    const CMD: &'static str = "single";
+    type Handler = OkResponse;
 
     fn argument(&self) -> Option<String> {
-        self.0.map(ToString::to_string)
+        self.0.to_arg()
+    }
+
+    fn min_version(&self) -> Version {
+        match self.0 {
+            SingleMode::Oneshot => Version::new(0, 21, 0),
+            _ => Self::MIN_VERSION,
+        }
     }
 }
 
-impl<'a> MpdCmd for Update<'a> {
-    const CMD: &'static str = "update";
-    type Handler = RespMapResponse<DatabaseVersion>;
+/// Search for songs matching a filter, ignoring case
+#[derive(Copy, Clone)]
+pub struct Search<'a> {
+    pub filter: Option<&'a str>,
+    pub sort: Option<Sort>,
+    pub window: Option<Window>,
+}
 
-    fn argument(&self) -> Option<String> {
-        self.0.map(ToString::to_string)
+/// Find songs exactly matching a filter
+#[derive(Copy, Clone)]
+pub struct Find<'a> {
+    pub filter: Option<&'a str>,
+    pub sort: Option<Sort>,
+    pub window: Option<Window>,
+}
+
+impl<'a> Search<'a> {
+    pub fn new(filter: Option<&'a str>) -> Self {
+        Self {
+            filter,
+            sort: None,
+            window: None,
+        }
+    }
+
+    /// Sort by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn window<W: Into<Window>>(mut self, window: W) -> Self {
+        self.window = Some(window.into());
+        self
     }
 }
 
-impl<'a> MpdCmd for Rescan<'a> {
-    const CMD: &'static str = "rescan";
-    type Handler = RespMapResponse<DatabaseVersion>;
+impl<'a> Find<'a> {
+    pub fn new(filter: Option<&'a str>) -> Self {
+        Self {
+            filter,
+            sort: None,
+            window: None,
+        }
+    }
 
-    fn argument(&self) -> Option<String> {
-        self.0.map(ToString::to_string)
+    /// Sort by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn window<W: Into<Window>>(mut self, window: W) -> Self {
+        self.window = Some(window.into());
+        self
     }
 }
 
-impl<'a> MpdCmd for Search<'a> {
+impl MpdCmd for Search<'_> {
     const CMD: &'static str = "search";
     type Handler = Tracks;
-    fn argument(&self) -> Option<String> {
-        self.0.map(ToString::to_string)
+    const MIN_VERSION: Version = FILTER_VERSION;
+
+    fn arguments(&self) -> Vec<String> {
+        query_args(self.filter, self.sort, self.window)
     }
 }
 
-impl MpdCmd for PlaylistInfo {
-    const CMD: &'static str = "playlistinfo";
+impl MpdCmd for Find<'_> {
+    const CMD: &'static str = "find";
     type Handler = Tracks;
+    const MIN_VERSION: Version = FILTER_VERSION;
+
+    fn arguments(&self) -> Vec<String> {
+        query_args(self.filter, self.sort, self.window)
+    }
+}
+
+/// Add the songs matching a filter, ignoring case, to the queue. Sort and window require Mpd
+/// 0.22
+#[derive(Copy, Clone)]
+pub struct SearchAdd<'a> {
+    pub filter: &'a str,
+    pub sort: Option<Sort>,
+    pub window: Option<Window>,
 }
 
-impl MpdCmd for Repeat {
-    const CMD: &'static str = "repeat";
+impl<'a> SearchAdd<'a> {
+    pub fn new(filter: &'a str) -> Self {
+        Self {
+            filter,
+            sort: None,
+            window: None,
+        }
+    }
+
+    /// Sort by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn window<W: Into<Window>>(mut self, window: W) -> Self {
+        self.window = Some(window.into());
+        self
+    }
+}
+
+impl MpdCmd for SearchAdd<'_> {
+    const CMD: &'static str = "searchadd";
     type Handler = OkResponse;
-    fn argument(&self) -> Option<String> {
-        Some((self.0 as u32).to_string())
+    const MIN_VERSION: Version = FILTER_VERSION;
+
+    fn arguments(&self) -> Vec<String> {
+        query_args(Some(self.filter), self.sort, self.window)
+    }
+
+    fn min_version(&self) -> Version {
+        add_version(self.sort, self.window)
+    }
+}
+
+/// Add the songs matching a filter, ignoring case, to a stored playlist, creating the playlist
+/// if it doesn't exist. Sort and window require Mpd 0.22
+#[derive(Copy, Clone)]
+pub struct SearchAddPl<'a> {
+    /// Name of the stored playlist
+    pub name: &'a str,
+    pub filter: &'a str,
+    pub sort: Option<Sort>,
+    pub window: Option<Window>,
+}
+
+impl<'a> SearchAddPl<'a> {
+    pub fn new(name: &'a str, filter: &'a str) -> Self {
+        Self {
+            name,
+            filter,
+            sort: None,
+            window: None,
+        }
+    }
+
+    /// Sort by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    pub fn window<W: Into<Window>>(mut self, window: W) -> Self {
+        self.window = Some(window.into());
+        self
     }
 }
 
-impl MpdCmd for Random {
-    const CMD: &'static str = "random";
+impl MpdCmd for SearchAddPl<'_> {
+    const CMD: &'static str = "searchaddpl";
     type Handler = OkResponse;
-    fn argument(&self) -> Option<String> {
-        Some((self.0 as u32).to_string())
+    const MIN_VERSION: Version = FILTER_VERSION;
+
+    fn min_version(&self) -> Version {
+        add_version(self.sort, self.window)
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![self.name.to_string()];
+        args.extend(query_args(Some(self.filter), self.sort, self.window));
+        args
     }
 }
 
-impl MpdCmd for Consume {
-    const CMD: &'static str = "consume";
+/// Protocol version introducing filter expressions, as created by [`Filter`](crate::Filter)
+const FILTER_VERSION: Version = Version::new(0, 21, 0);
+
+/// Protocol version of `searchadd` and `searchaddpl`, which support sort and window since
+/// Mpd 0.22
+fn add_version(sort: Option<Sort>, window: Option<Window>) -> Version {
+    if sort.is_some() || window.is_some() {
+        Version::new(0, 22, 0)
+    } else {
+        FILTER_VERSION
+    }
+}
+
+/// Arguments of the commands taking a filter and optional sort and window
+fn query_args(filter: Option<&str>, sort: Option<Sort>, window: Option<Window>) -> Vec<String> {
+    let mut args: Vec<String> = filter.to_arg().into_iter().collect();
+
+    if let Some(sort) = sort {
+        args.push("sort".into());
+        args.push(sort.to_string());
+    }
+
+    if let Some(window) = window {
+        args.push("window".into());
+        args.push(window.to_string());
+    }
+
+    args
+}
+
+/// Add a song to the queue, at the end or at the given position. Responds with the id of the
+/// new song
+#[derive(Copy, Clone)]
+pub struct AddId<'a> {
+    pub uri: &'a str,
+    pub pos: Option<Position>,
+}
+
+impl<'a> AddId<'a> {
+    pub fn new(uri: &'a str) -> Self {
+        Self { uri, pos: None }
+    }
+
+    /// Insert the song at `pos` instead of at the end
+    pub fn pos<P: Into<Position>>(mut self, pos: P) -> Self {
+        self.pos = Some(pos.into());
+        self
+    }
+}
+
+impl MpdCmd for AddId<'_> {
+    const CMD: &'static str = "addid";
+    type Handler = SingleLineResp<SongId>;
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![self.uri.to_string()];
+        args.extend(self.pos.to_arg());
+        args
+    }
+
+    fn min_version(&self) -> Version {
+        match self.pos {
+            Some(Position::AfterCurrent(_) | Position::BeforeCurrent(_)) => Version::new(0, 23, 0),
+            _ => Self::MIN_VERSION,
+        }
+    }
+}
+
+/// Add the songs of a stored playlist to the queue, all of them at the end by default
+#[derive(Copy, Clone)]
+pub struct Load<'a> {
+    /// Name of the stored playlist
+    pub name: &'a str,
+    /// Songs of the playlist to add
+    pub range: Option<Window>,
+    /// Where to insert the songs. Requires Mpd 0.23.1
+    pub pos: Option<Position>,
+}
+
+impl<'a> Load<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            range: None,
+            pos: None,
+        }
+    }
+
+    /// Add only the songs of the playlist in `range`
+    pub fn range<W: Into<Window>>(mut self, range: W) -> Self {
+        self.range = Some(range.into());
+        self
+    }
+
+    /// Insert the songs at `pos` instead of at the end. Requires Mpd 0.23.1
+    pub fn pos<P: Into<Position>>(mut self, pos: P) -> Self {
+        self.pos = Some(pos.into());
+        self
+    }
+}
+
+impl MpdCmd for Load<'_> {
+    const CMD: &'static str = "load";
     type Handler = OkResponse;
-    fn argument(&self) -> Option<String> {
-        Some((self.0 as u32).to_string())
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![self.name.to_string()];
+
+        // The position comes after the range, which is open ended when only a position is set
+        match (self.range, self.pos) {
+            (Some(range), _) => args.push(range.to_string()),
+            (None, Some(_)) => args.push("0:".into()),
+            (None, None) => (),
+        }
+        args.extend(self.pos.to_arg());
+        args
+    }
+
+    fn min_version(&self) -> Version {
+        match self.pos {
+            Some(_) => Version::new(0, 23, 1),
+            None => Self::MIN_VERSION,
+        }
+    }
+}
+
+/// Wait until one of the subsystems changes, or any subsystem if empty
+#[derive(Copy, Clone)]
+pub struct IdleFor<'a>(pub &'a [crate::Subsystem]);
+
+impl MpdCmd for IdleFor<'_> {
+    const CMD: &'static str = "idle";
+    type Handler = ListResponse<crate::Subsystem>;
+
+    fn arguments(&self) -> Vec<String> {
+        self.0.iter().map(ToString::to_string).collect()
+    }
+}
+
+/// Chunk of the cover of the album of a song, starting at `offset`. Requires Mpd 0.21
+#[derive(Copy, Clone)]
+pub struct AlbumArt<'a> {
+    pub uri: &'a str,
+    /// Offset in bytes into the cover
+    pub offset: usize,
+}
+
+impl MpdCmd for AlbumArt<'_> {
+    const CMD: &'static str = "albumart";
+    const MIN_VERSION: Version = Version::new(0, 21, 0);
+    type Handler = BinaryResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![self.uri.to_string(), self.offset.to_string()]
     }
 }
 
-impl MpdCmd for PlayPause {
-    const CMD: &'static str = "pause";
+/// Set a runtime attribute of an output. Requires Mpd 0.21
+#[derive(Copy, Clone)]
+pub struct OutputSet<'a> {
+    /// Id of the output
+    pub id: u32,
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+impl MpdCmd for OutputSet<'_> {
+    const CMD: &'static str = "outputset";
+    const MIN_VERSION: Version = Version::new(0, 21, 0);
     type Handler = OkResponse;
-    fn argument(&self) -> Option<String> {
-        Some((self.0 as u32).to_string())
+
+    fn arguments(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.name.to_string(),
+            self.value.to_string(),
+        ]
     }
 }
 
-impl MpdCmd for Next {
-    const CMD: &'static str = "next";
+/// Add a song to a stored playlist, creating the playlist if it doesn't exist
+#[derive(Copy, Clone)]
+pub struct PlaylistAdd<'a> {
+    /// Name of the stored playlist
+    pub name: &'a str,
+    pub uri: &'a str,
+}
+
+impl MpdCmd for PlaylistAdd<'_> {
+    const CMD: &'static str = "playlistadd";
     type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![self.name.to_string(), self.uri.to_string()]
+    }
+}
+
+/// Seek to a time in the song with the given id, and play it
+#[derive(Copy, Clone)]
+pub struct SeekId {
+    pub id: SongId,
+    pub time: Duration,
 }
-impl MpdCmd for Prev {
-    const CMD: &'static str = "prev";
+
+impl MpdCmd for SeekId {
+    const CMD: &'static str = "seekid";
     type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            format!("{:.3}", self.time.as_secs_f64()),
+        ]
+    }
 }
 
-impl MpdCmd for QueueClear {
-    const CMD: &'static str = "clear";
+/// Seek to a time in the song at the given position, and play it
+#[derive(Copy, Clone)]
+pub struct Seek {
+    pub pos: SongPos,
+    pub time: Duration,
+}
+
+impl MpdCmd for Seek {
+    const CMD: &'static str = "seek";
     type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![
+            self.pos.to_string(),
+            format!("{:.3}", self.time.as_secs_f64()),
+        ]
+    }
+}
+
+/// Move the song with the given id to a new position in the queue
+#[derive(Copy, Clone)]
+pub struct MoveId {
+    pub id: SongId,
+    pub to: SongPos,
 }
 
-impl MpdCmd for NoIdle {
-    const CMD: &'static str = "noidle";
+impl MpdCmd for MoveId {
+    const CMD: &'static str = "moveid";
     type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.to.to_string()]
+    }
 }
 
-impl MpdCmd for Idle {
-    const CMD: &'static str = "idle";
-    type Handler = RespMapResponse<crate::Subsystem>;
+/// List the unique values of a tag, optionally for songs matching a filter
+///
+/// The response contains the values keyed by the tag name, preceded by the value of the
+/// `group` tag when grouping.
+#[derive(Copy, Clone)]
+pub struct List<'a> {
+    pub tag: Tag,
+    pub filter: Option<&'a str>,
+    pub group: Option<Tag>,
 }
 
-impl MpdCmd for Stats {
-    const CMD: &'static str = "stats";
-    type Handler = RespMapResponse<crate::Stats>;
+impl MpdCmd for List<'_> {
+    const CMD: &'static str = "list";
+    type Handler = RawResponse;
+
+    fn min_version(&self) -> Version {
+        match self.filter {
+            Some(_) => FILTER_VERSION,
+            None => Self::MIN_VERSION,
+        }
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![self.tag.to_string()];
+        args.extend(self.filter.to_arg());
+
+        if let Some(group) = self.group {
+            args.push("group".into());
+            args.push(group.to_string());
+        }
+
+        args
+    }
+}
+
+/// Search a stored playlist for songs matching a filter. Since Mpd 0.24
+#[derive(Copy, Clone)]
+pub struct SearchPlaylist<'a> {
+    /// Name of the stored playlist
+    pub name: &'a str,
+    pub filter: &'a str,
+    pub window: Option<Window>,
 }
 
-impl MpdCmd for Status {
-    const CMD: &'static str = "status";
-    type Handler = RespMapResponse<crate::Status>;
+impl MpdCmd for SearchPlaylist<'_> {
+    const CMD: &'static str = "searchplaylist";
+    const MIN_VERSION: Version = Version::new(0, 24, 0);
+    type Handler = Tracks;
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![self.name.into(), self.filter.into()];
+
+        if let Some(window) = self.window {
+            args.push("window".into());
+            args.push(window.to_string());
+        }
+
+        args
+    }
 }
 
-impl MpdCmd for Setvol {
-    const CMD: &'static str = "setvol";
+/// Authenticate with a password
+///
+/// The password is left out of [`redacted_cmdline`](MpdCmd::redacted_cmdline).
+#[derive(Copy, Clone)]
+pub struct Password<'a>(pub &'a SecretString);
+
+impl MpdCmd for Password<'_> {
+    const CMD: &'static str = "password";
     type Handler = OkResponse;
 
-    fn argument(&self) -> Option<String> {
-        Some(self.0.to_string())
+    fn arguments(&self) -> Vec<String> {
+        vec![self.0.expose().to_string()]
+    }
+
+    fn redacted_cmdline(&self) -> String {
+        format!("{} \"***\"\n", Self::CMD)
     }
 }
 
-impl MpdCmd for Stop {
-    const CMD: &'static str = "stop";
+/// Change the set of tags included in song responses on this connection. Since Mpd 0.21
+#[derive(Copy, Clone)]
+pub enum TagTypes<'a> {
+    /// Include no tags
+    Clear,
+    /// Include all tags
+    All,
+    /// Include these tags, in addition to the ones already included
+    Enable(&'a [Tag]),
+    /// Leave out these tags
+    Disable(&'a [Tag]),
+}
+
+impl MpdCmd for TagTypes<'_> {
+    const CMD: &'static str = "tagtypes";
+    const MIN_VERSION: Version = Version::new(0, 21, 0);
     type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        let (op, tags): (&str, &[Tag]) = match self {
+            TagTypes::Clear => ("clear", &[]),
+            TagTypes::All => ("all", &[]),
+            TagTypes::Enable(tags) => ("enable", tags),
+            TagTypes::Disable(tags) => ("disable", tags),
+        };
+
+        std::iter::once(op.to_string())
+            .chain(tags.iter().map(Tag::to_string))
+            .collect()
+    }
 }
 
-impl MpdCmd for PlayId {
-    const CMD: &'static str = "playid";
+/// Sticker type of song stickers
+const STICKER_SONG: &str = "song";
+
+/// Get the value of a song sticker
+#[derive(Copy, Clone)]
+pub struct StickerGet<'a> {
+    pub uri: &'a str,
+    pub name: &'a str,
+}
+
+/// Set the value of a song sticker
+#[derive(Copy, Clone)]
+pub struct StickerSet<'a> {
+    pub uri: &'a str,
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+/// Delete a song sticker, or all stickers of the song if `name` is None
+#[derive(Copy, Clone)]
+pub struct StickerDelete<'a> {
+    pub uri: &'a str,
+    pub name: Option<&'a str>,
+}
+
+/// List the stickers of a song
+#[derive(Copy, Clone)]
+pub struct StickerList<'a>(pub &'a str);
+
+/// Comparison of sticker values in [`StickerFind`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StickerOp {
+    /// String comparison, `=`
+    Equals,
+    /// String comparison, `<`
+    Less,
+    /// String comparison, `>`
+    Greater,
+    /// Integer comparison, `eq`. Since Mpd 0.24
+    IntEquals,
+    /// Integer comparison, `lt`. Since Mpd 0.24
+    IntLess,
+    /// Integer comparison, `gt`. Since Mpd 0.24
+    IntGreater,
+    /// Since Mpd 0.24
+    Contains,
+    /// Since Mpd 0.24
+    StartsWith,
+}
+
+impl StickerOp {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StickerOp::Equals => "=",
+            StickerOp::Less => "<",
+            StickerOp::Greater => ">",
+            StickerOp::IntEquals => "eq",
+            StickerOp::IntLess => "lt",
+            StickerOp::IntGreater => "gt",
+            StickerOp::Contains => "contains",
+            StickerOp::StartsWith => "starts_with",
+        }
+    }
+}
+
+/// Find songs below `uri` with a sticker called `name`
+///
+/// The value, sort and window options are supported since Mpd 0.24, except for the `=`, `<`
+/// and `>` comparisons.
+///
+/// # Example
+/// ```
+/// use async_mpd::cmd::{MpdCmd, StickerFind, StickerOp};
+///
+/// // All songs rated 4 or higher
+/// let cmd = StickerFind::new("", "rating")
+///     .value(StickerOp::IntGreater, "3")
+///     .sort("value_int")
+///     .window(0..100);
+///
+/// assert_eq!(
+///     cmd.to_cmdline(),
+///     "sticker \"find\" \"song\" \"\" \"rating\" \"gt\" \"3\" \"sort\" \"value_int\" \"window\" \"0:100\"\n"
+/// );
+/// ```
+#[derive(Copy, Clone)]
+pub struct StickerFind<'a> {
+    pub uri: &'a str,
+    pub name: &'a str,
+    pub value: Option<(StickerOp, &'a str)>,
+    /// `uri`, `value` or `value_int`
+    pub sort: Option<&'a str>,
+    pub window: Option<Window>,
+}
+
+impl<'a> StickerFind<'a> {
+    pub fn new(uri: &'a str, name: &'a str) -> Self {
+        Self {
+            uri,
+            name,
+            value: None,
+            sort: None,
+            window: None,
+        }
+    }
+
+    /// Only match stickers with a value comparing to `value`
+    pub fn value(mut self, op: StickerOp, value: &'a str) -> Self {
+        self.value = Some((op, value));
+        self
+    }
+
+    pub fn sort(mut self, sort: &'a str) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn window<W: Into<Window>>(mut self, window: W) -> Self {
+        self.window = Some(window.into());
+        self
+    }
+}
+
+impl MpdCmd for StickerGet<'_> {
+    const CMD: &'static str = "sticker";
+    type Handler = KeyValueResponse<Sticker>;
+
+    fn arguments(&self) -> Vec<String> {
+        vec![
+            "get".into(),
+            STICKER_SONG.into(),
+            self.uri.into(),
+            self.name.into(),
+        ]
+    }
+}
+
+impl MpdCmd for StickerSet<'_> {
+    const CMD: &'static str = "sticker";
     type Handler = OkResponse;
 
-    fn argument(&self) -> Option<String> {
-        Some(self.0.to_string())
+    fn arguments(&self) -> Vec<String> {
+        vec![
+            "set".into(),
+            STICKER_SONG.into(),
+            self.uri.into(),
+            self.name.into(),
+            self.value.into(),
+        ]
+    }
+}
+
+impl MpdCmd for StickerDelete<'_> {
+    const CMD: &'static str = "sticker";
+    type Handler = OkResponse;
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec!["delete".into(), STICKER_SONG.into(), self.uri.into()];
+        args.extend(self.name.to_arg());
+        args
+    }
+}
+
+impl MpdCmd for StickerList<'_> {
+    const CMD: &'static str = "sticker";
+    type Handler = ListResponse<Sticker>;
+
+    fn arguments(&self) -> Vec<String> {
+        vec!["list".into(), STICKER_SONG.into(), self.0.into()]
+    }
+}
+
+impl MpdCmd for StickerFind<'_> {
+    const CMD: &'static str = "sticker";
+    type Handler = ListResponse<StickerMatch>;
+
+    fn min_version(&self) -> Version {
+        let string_op = matches!(
+            self.value,
+            None | Some((StickerOp::Equals | StickerOp::Less | StickerOp::Greater, _))
+        );
+
+        if string_op && self.sort.is_none() && self.window.is_none() {
+            Self::MIN_VERSION
+        } else {
+            Version::new(0, 24, 0)
+        }
+    }
+
+    fn arguments(&self) -> Vec<String> {
+        let mut args = vec![
+            "find".into(),
+            STICKER_SONG.into(),
+            self.uri.into(),
+            self.name.into(),
+        ];
+
+        if let Some((op, value)) = self.value {
+            args.push(op.as_str().into());
+            args.push(value.into());
+        }
+
+        if let Some(sort) = self.sort {
+            args.push("sort".into());
+            args.push(sort.into());
+        }
+
+        if let Some(window) = self.window {
+            args.push("window".into());
+            args.push(window.to_string());
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Filter, ToFilterExpr, WrappedResponse};
+
+    /// Reads the quoted string at the start of `s` like Mpd does, returning it and the rest of `s`
+    fn unquote(s: &str) -> Result<(String, &str), String> {
+        let mut chars = s.char_indices();
+        let mut value = String::new();
+
+        if chars.next().map(|(_, c)| c) != Some('"') {
+            return Err(format!("expected quote: {}", s));
+        }
+
+        while let Some((_, c)) = chars.next() {
+            match c {
+                '"' => return Ok((value, chars.as_str())),
+                '\\' => value.push(chars.next().ok_or("escape at end of line")?.1),
+                c => value.push(c),
+            }
+        }
+
+        Err(format!("missing closing quote: {}", s))
+    }
+
+    /// Splits a command line into the command and its arguments, following the grammar of Mpd
+    fn tokenize(line: &str) -> Result<Vec<String>, String> {
+        let mut rest = line.strip_suffix('\n').ok_or("missing newline")?;
+        let mut tokens = Vec::new();
+
+        if rest.contains('\n') {
+            return Err("newline within line".into());
+        }
+
+        loop {
+            rest = rest.trim_start_matches(' ');
+
+            if rest.is_empty() {
+                return Ok(tokens);
+            }
+
+            if rest.starts_with('"') {
+                let (token, after) = unquote(rest)?;
+                if !after.is_empty() && !after.starts_with(' ') {
+                    return Err(format!("garbage after closing quote: {}", after));
+                }
+                tokens.push(token);
+                rest = after;
+            } else {
+                let end = rest.find(' ').unwrap_or(rest.len());
+                tokens.push(rest[..end].to_string());
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    #[test]
+    fn cmdline() {
+        assert_eq!(Stats.to_cmdline(), "stats\n");
+        assert_eq!(Repeat(true).to_cmdline(), "repeat \"1\"\n");
+        assert_eq!(PlayId(SongId(4)).to_cmdline(), "playid \"4\"\n");
+        assert_eq!(Update(None).to_cmdline(), "update\n");
+        assert_eq!(Update(Some("music")).to_cmdline(), "update \"music\"\n");
+        assert_eq!(
+            Update(Some(r#"a "b" \c"#)).to_cmdline(),
+            r#"update "a \"b\" \\c""#.to_string() + "\n"
+        );
+        assert_eq!(
+            Search::new(Some("(Artist == 'abba')"))
+                .sort(Sort::by(Tag::Title).descending())
+                .window(0..10)
+                .to_cmdline(),
+            "search \"(Artist == 'abba')\" \"sort\" \"-Title\" \"window\" \"0:10\"\n"
+        );
+        assert_eq!(
+            Find::new(None)
+                .sort(crate::SortKey::LastModified)
+                .to_cmdline(),
+            "find \"sort\" \"Last-Modified\"\n"
+        );
+        let password = SecretString::from("hunter2");
+        assert_eq!(Password(&password).to_cmdline(), "password \"hunter2\"\n");
+        assert_eq!(Password(&password).redacted_cmdline(), "password \"***\"\n");
+        assert_eq!(
+            TagTypes::Enable(&[Tag::Artist, Tag::Title]).to_cmdline(),
+            "tagtypes \"enable\" \"Artist\" \"Title\"\n"
+        );
+        assert_eq!(
+            AddId::new("a.flac")
+                .pos(Position::AfterCurrent(0))
+                .to_cmdline(),
+            "addid \"a.flac\" \"+0\"\n"
+        );
+        assert_eq!(Load::new("best").to_cmdline(), "load \"best\"\n");
+        assert_eq!(
+            Load::new("best").range(2..4).to_cmdline(),
+            "load \"best\" \"2:4\"\n"
+        );
+        assert_eq!(
+            Load::new("best").pos(SongPos(1)).to_cmdline(),
+            "load \"best\" \"0:\" \"1\"\n"
+        );
+        assert_eq!(
+            CommandList((Password(&password), Status, CurrentSong)).redacted_cmdline(),
+            "command_list_ok_begin\npassword \"***\"\nstatus\ncurrentsong\ncommand_list_end\n"
+        );
+        assert_eq!(
+            SeekId {
+                id: SongId(3),
+                time: Duration::from_millis(90500)
+            }
+            .to_cmdline(),
+            "seekid \"3\" \"90.500\"\n"
+        );
+        assert_eq!(
+            CommandList(vec![QueueAdd("a.flac"), QueueAdd("b.flac")]).to_cmdline(),
+            "command_list_ok_begin\nadd \"a.flac\"\nadd \"b.flac\"\ncommand_list_end\n"
+        );
+        assert_eq!(
+            SearchAddPl::new("best", "(Genre == 'Jazz')")
+                .window(0..20)
+                .to_cmdline(),
+            "searchaddpl \"best\" \"(Genre == 'Jazz')\" \"window\" \"0:20\"\n"
+        );
+
+        let password = SecretString::from("hunter2");
+        let list = CommandList((Password(&password), Status));
+        assert_eq!(
+            list.render(),
+            "command_list_ok_begin\npassword \"hunter2\"\nstatus\ncommand_list_end\n"
+        );
+        assert!(!list.redacted_cmdline().contains("hunter2"));
+    }
+
+    #[test]
+    fn min_versions() {
+        let v = Version::new;
+
+        assert_eq!(Status.min_version(), v(0, 0, 0));
+        assert_eq!(BinaryLimit(8192).min_version(), v(0, 22, 4));
+        assert_eq!(Find::new(Some("(Album == 'A')")).min_version(), v(0, 21, 0));
+
+        let add = SearchAdd::new("(Genre == 'Jazz')");
+        assert_eq!(add.min_version(), v(0, 21, 0));
+        assert_eq!(add.window(0..10).min_version(), v(0, 22, 0));
+        assert_eq!(Load::new("best").min_version(), v(0, 0, 0));
+        assert_eq!(Load::new("best").pos(SongPos(0)).min_version(), v(0, 23, 1));
+
+        let add_id = AddId::new("a.flac");
+        assert_eq!(add_id.pos(SongPos(0)).min_version(), v(0, 0, 0));
+        assert_eq!(
+            add_id.pos(Position::AfterCurrent(0)).min_version(),
+            v(0, 23, 0)
+        );
+        assert_eq!(
+            add_id.pos(Position::BeforeCurrent(1)).min_version(),
+            v(0, 23, 0)
+        );
+
+        assert_eq!(Consume(ConsumeMode::On).min_version(), v(0, 0, 0));
+        assert_eq!(Consume(ConsumeMode::Oneshot).min_version(), v(0, 24, 0));
+        assert_eq!(Single(SingleMode::On).min_version(), v(0, 0, 0));
+        assert_eq!(Single(SingleMode::Oneshot).min_version(), v(0, 21, 0));
+
+        let find = StickerFind::new("", "rating");
+        assert_eq!(find.value(StickerOp::Equals, "5").min_version(), v(0, 0, 0));
+        assert_eq!(
+            find.value(StickerOp::IntGreater, "3").min_version(),
+            v(0, 24, 0)
+        );
+
+        assert_eq!(
+            <CommandList<(Status, GetVol, ListMounts)>>::MIN_VERSION,
+            v(0, 23, 0)
+        );
+        assert_eq!(CommandList((Status, add)).min_version(), v(0, 21, 0));
+        assert_eq!(
+            CommandList(vec![Partition("a"), Partition("b")]).min_version(),
+            v(0, 22, 0)
+        );
+    }
+
+    #[test]
+    fn line_breaks() {
+        assert!(check_line_breaks(&CommandList((Status, QueueAdd("a.flac"))).to_cmdline()).is_ok());
+        assert!(check_line_breaks(&QueueAdd("a.flac\nclear").to_cmdline()).is_err());
+        assert!(check_line_breaks(&QueueAdd("a\\\"b\rc").to_cmdline()).is_err());
+    }
+
+    #[test]
+    fn tokenize_quoted() {
+        assert_eq!(
+            tokenize("find \"(Artist == \\\"a\\\\\\\"b\\\")\"\n").unwrap(),
+            vec!["find", r#"(Artist == "a\"b")"#]
+        );
+        assert!(tokenize("update \"music\n").is_err());
+        assert!(tokenize("update \"a\"b\n").is_err());
+    }
+
+    /// Compiles only if the response of the command can be wrapped
+    fn wrapped<F: std::future::Future<Output = Result<WrappedResponse, Error>>>(_: F) {}
+
+    #[test]
+    fn exec_wrapped_all() {
+        use crate::{cmd, MpdClient, Subsystem};
+
+        let mut client = MpdClient::new();
+        let secret = SecretString::from("secret");
+
+        wrapped(client.exec_wrapped(cmd::Ping));
+        wrapped(client.exec_wrapped(cmd::Stats));
+        wrapped(client.exec_wrapped(cmd::Status));
+        wrapped(client.exec_wrapped(cmd::CurrentSong));
+        wrapped(client.exec_wrapped(cmd::ClearError));
+        wrapped(client.exec_wrapped(cmd::Setvol(50)));
+        wrapped(client.exec_wrapped(cmd::GetVol));
+        wrapped(client.exec_wrapped(cmd::ReplayGainStatus));
+        wrapped(client.exec_wrapped(cmd::Repeat(true)));
+        wrapped(client.exec_wrapped(cmd::Random(true)));
+        wrapped(client.exec_wrapped(cmd::Consume(ConsumeMode::On)));
+        wrapped(client.exec_wrapped(cmd::Single(SingleMode::On)));
+        wrapped(client.exec_wrapped(cmd::Play(None)));
+        wrapped(client.exec_wrapped(cmd::PlayId(SongId(1))));
+        wrapped(client.exec_wrapped(cmd::QueueClear));
+        wrapped(client.exec_wrapped(cmd::QueueAdd("a.flac")));
+        wrapped(client.exec_wrapped(cmd::PlaylistInfo));
+        wrapped(client.exec_wrapped(cmd::PlaylistId(SongId(1))));
+        wrapped(client.exec_wrapped(cmd::PlChanges(0)));
+        wrapped(client.exec_wrapped(cmd::DeleteId(SongId(1))));
+        wrapped(client.exec_wrapped(cmd::ListPlaylistInfo("p")));
+        wrapped(client.exec_wrapped(cmd::PlaylistFind("(file == 'a.flac')")));
+        wrapped(client.exec_wrapped(cmd::FindAdd("(file == 'a.flac')")));
+        wrapped(client.exec_wrapped(cmd::Rm("p")));
+        wrapped(client.exec_wrapped(cmd::Stop));
+        wrapped(client.exec_wrapped(cmd::PlayPause(true)));
+        wrapped(client.exec_wrapped(cmd::Next));
+        wrapped(client.exec_wrapped(cmd::Prev));
+        wrapped(client.exec_wrapped(cmd::Rescan(None)));
+        wrapped(client.exec_wrapped(cmd::Update(None)));
+        wrapped(client.exec_wrapped(cmd::Idle));
+        wrapped(client.exec_wrapped(cmd::NoIdle));
+        wrapped(client.exec_wrapped(cmd::Close));
+        wrapped(client.exec_wrapped(cmd::BinaryLimit(8192)));
+        wrapped(client.exec_wrapped(cmd::GetFingerprint("a.flac")));
+        wrapped(client.exec_wrapped(cmd::ListPlaylists));
+        wrapped(client.exec_wrapped(cmd::Outputs));
+        wrapped(client.exec_wrapped(cmd::ListMounts));
+        wrapped(client.exec_wrapped(cmd::ListNeighbors));
+        wrapped(client.exec_wrapped(cmd::Partition("default")));
+        wrapped(client.exec_wrapped(cmd::Listall(None)));
+        wrapped(client.exec_wrapped(cmd::ListallInfo(None)));
+        wrapped(client.exec_wrapped(cmd::Lsinfo(None)));
+        wrapped(client.exec_wrapped(cmd::Search::new(None)));
+        wrapped(client.exec_wrapped(cmd::Find::new(None)));
+        wrapped(client.exec_wrapped(cmd::SearchAdd::new("(any == 'a')")));
+        wrapped(client.exec_wrapped(cmd::SearchAddPl::new("p", "(any == 'a')")));
+        wrapped(client.exec_wrapped(cmd::AddId::new("a.flac")));
+        wrapped(client.exec_wrapped(cmd::Load::new("p")));
+        wrapped(client.exec_wrapped(cmd::IdleFor(&[Subsystem::Player])));
+        wrapped(client.exec_wrapped(cmd::AlbumArt {
+            uri: "a.flac",
+            offset: 0,
+        }));
+        wrapped(client.exec_wrapped(cmd::OutputSet {
+            id: 0,
+            name: "dop",
+            value: "1",
+        }));
+        wrapped(client.exec_wrapped(cmd::PlaylistAdd {
+            name: "p",
+            uri: "a.flac",
+        }));
+        wrapped(client.exec_wrapped(cmd::SeekId {
+            id: SongId(1),
+            time: Duration::ZERO,
+        }));
+        wrapped(client.exec_wrapped(cmd::Seek {
+            pos: SongPos(0),
+            time: Duration::ZERO,
+        }));
+        wrapped(client.exec_wrapped(cmd::MoveId {
+            id: SongId(1),
+            to: SongPos(0),
+        }));
+        wrapped(client.exec_wrapped(cmd::List {
+            tag: Tag::Album,
+            filter: None,
+            group: None,
+        }));
+        wrapped(client.exec_wrapped(cmd::SearchPlaylist {
+            name: "p",
+            filter: "(any == 'a')",
+            window: None,
+        }));
+        wrapped(client.exec_wrapped(cmd::Password(&secret)));
+        wrapped(client.exec_wrapped(cmd::TagTypes::Clear));
+        wrapped(client.exec_wrapped(cmd::StickerGet {
+            uri: "a.flac",
+            name: "rating",
+        }));
+        wrapped(client.exec_wrapped(cmd::StickerSet {
+            uri: "a.flac",
+            name: "rating",
+            value: "5",
+        }));
+        wrapped(client.exec_wrapped(cmd::StickerDelete {
+            uri: "a.flac",
+            name: None,
+        }));
+        wrapped(client.exec_wrapped(cmd::StickerList("a.flac")));
+        wrapped(client.exec_wrapped(cmd::StickerFind::new("", "rating")));
+
+        // Every command of this module is in the list above
+        let source = include_str!("cmd.rs");
+        let start = source.find("fn exec_wrapped_all()").unwrap();
+        let test = &source[start..source[start..].find("\n    }\n").unwrap() + start];
+
+        for line in source.lines().map(str::trim) {
+            let name = match (
+                line.strip_prefix("impl MpdCmd for "),
+                line.strip_prefix("pub struct "),
+            ) {
+                (Some(rest), _) => rest,
+                (_, Some(rest)) if rest.contains(" => ") => rest,
+                _ => continue,
+            };
+            let name: String = name.chars().take_while(|c| c.is_alphanumeric()).collect();
+            let used = test
+                .match_indices(&format!("cmd::{}", name))
+                .any(|(i, m)| !test[i + m.len()..].starts_with(|c: char| c.is_alphanumeric()));
+            assert!(used, "cmd::{} is missing from exec_wrapped_all", name);
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn arguments_roundtrip(arg in "[^\n]*", other in "[^\n]*") {
+            let line = Update(Some(&arg)).to_cmdline();
+            proptest::prop_assert_eq!(tokenize(&line), Ok(vec!["update".to_string(), arg.clone()]));
+
+            let line = PlaylistAdd { name: &arg, uri: &other }.to_cmdline();
+            proptest::prop_assert_eq!(tokenize(&line), Ok(vec!["playlistadd".to_string(), arg, other]));
+        }
+
+        #[test]
+        fn line_breaks_rejected(before in "[^\r\n]*", after in "[^\r\n]*", brk in "\r|\n|\r\n") {
+            proptest::prop_assert!(check_line_breaks(&Update(Some(&before)).to_cmdline()).is_ok());
+
+            let arg = format!("{}{}{}", before, brk, after);
+            proptest::prop_assert!(check_line_breaks(&Update(Some(&arg)).to_cmdline()).is_err());
+
+            let filter = Filter::with(Tag::Any.contains(&arg)).to_query();
+            let list = CommandList((Status, Search::new(filter.as_deref())));
+            proptest::prop_assert!(check_line_breaks(&list.to_cmdline()).is_err());
+        }
+
+        #[test]
+        fn filter_values_roundtrip(value in "[^\n]*") {
+            let filter = Filter::with(Tag::Artist.equals(&value));
+            let line = Search::new(filter.to_query().as_deref()).to_cmdline();
+            let tokens = tokenize(&line).unwrap();
+
+            let expr = tokens[1].strip_prefix("((Artist == ").unwrap();
+            let (parsed, rest) = unquote(expr).unwrap();
+            proptest::prop_assert_eq!(parsed, value);
+            proptest::prop_assert_eq!(rest, "))");
+        }
     }
 }
@@ -0,0 +1,140 @@
+use std::env;
+use std::path::PathBuf;
+
+use crate::{
+    client::url::{ConnectionUrl, UrlAddress, DEFAULT_PORT},
+    Error, MpdClient, SecretString,
+};
+
+/// Resolve the `MPD_HOST` and `MPD_PORT` variables, the same way as mpc
+///
+/// `MPD_HOST` is a host name, or a socket path, optionally prefixed with `password@`. A socket
+/// path starting with `~` is relative to the home directory.
+fn resolve(
+    host: Option<String>,
+    port: Option<String>,
+    home: Option<PathBuf>,
+) -> Result<ConnectionUrl, Error> {
+    let host = host
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "localhost".into());
+
+    // A leading `@` is an abstract socket, not a password separator
+    let (password, host) = match host.find('@') {
+        Some(at) if at > 0 => (
+            Some(SecretString::from(&host[..at])),
+            host[at + 1..].to_string(),
+        ),
+        _ => (None, host),
+    };
+
+    let address = if host.starts_with('@') {
+        return Err(Error::InvalidArgument {
+            msg: format!("abstract sockets are not supported: {}", host),
+        });
+    } else if let Some(path) = host.strip_prefix("~/") {
+        let home = home.ok_or_else(|| Error::InvalidArgument {
+            msg: "no home directory for MPD_HOST".into(),
+        })?;
+        unix_address(home.join(path))?
+    } else if host.starts_with('/') {
+        unix_address(PathBuf::from(host))?
+    } else {
+        let port = match port.filter(|p| !p.is_empty()) {
+            Some(port) => port.parse::<u16>().map_err(|_| Error::InvalidArgument {
+                msg: format!("invalid MPD_PORT: {}", port),
+            })?,
+            None => DEFAULT_PORT,
+        };
+
+        if host.contains(':') {
+            UrlAddress::Tcp(format!("[{}]:{}", host, port))
+        } else {
+            UrlAddress::Tcp(format!("{}:{}", host, port))
+        }
+    };
+
+    Ok(ConnectionUrl {
+        address,
+        password,
+        tls_domain: None,
+    })
+}
+
+#[cfg(unix)]
+fn unix_address(path: PathBuf) -> Result<UrlAddress, Error> {
+    Ok(UrlAddress::Unix(path))
+}
+
+#[cfg(not(unix))]
+fn unix_address(path: PathBuf) -> Result<UrlAddress, Error> {
+    Err(Error::InvalidArgument {
+        msg: format!("unix sockets are not supported: {}", path.display()),
+    })
+}
+
+impl MpdClient {
+    /// Connect to the server given by the `MPD_HOST` and `MPD_PORT` environment variables
+    ///
+    /// The variables are interpreted like mpc does: `MPD_HOST` is a host name or socket path,
+    /// optionally prefixed with `password@`, and defaults to `localhost`. `MPD_PORT` defaults
+    /// to 6600.
+    pub async fn connect_env(&mut self) -> Result<String, Error> {
+        let home = env::var_os("HOME").map(PathBuf::from);
+        let url = resolve(env::var("MPD_HOST").ok(), env::var("MPD_PORT").ok(), home)?;
+        self.connect_to(url).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ErrorKind;
+
+    fn resolve_str(host: Option<&str>, port: Option<&str>) -> ConnectionUrl {
+        resolve(
+            host.map(Into::into),
+            port.map(Into::into),
+            Some("/home/user".into()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn resolve_env() {
+        assert_eq!(
+            resolve_str(None, None).address,
+            UrlAddress::Tcp("localhost:6600".into())
+        );
+        assert_eq!(
+            resolve_str(Some("music.lan"), Some("6601")).address,
+            UrlAddress::Tcp("music.lan:6601".into())
+        );
+        assert_eq!(
+            resolve_str(Some("::1"), None).address,
+            UrlAddress::Tcp("[::1]:6600".into())
+        );
+
+        let url = resolve_str(Some("secret@music.lan"), None);
+        assert_eq!(url.password, Some("secret".into()));
+        assert_eq!(url.address, UrlAddress::Tcp("music.lan:6600".into()));
+
+        #[cfg(unix)]
+        {
+            assert_eq!(
+                resolve_str(Some("/run/mpd/socket"), None).address,
+                UrlAddress::Unix("/run/mpd/socket".into())
+            );
+            assert_eq!(
+                resolve_str(Some("pw@~/.mpd/socket"), None).address,
+                UrlAddress::Unix("/home/user/.mpd/socket".into())
+            );
+        }
+
+        assert!(resolve(Some("@abstract".into()), None, None).is_err());
+
+        let err = resolve(None, Some("port".into()), None).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Usage);
+        assert_eq!(err.to_string(), "Invalid argument: invalid MPD_PORT: port");
+    }
+}
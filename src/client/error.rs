@@ -1,5 +1,8 @@
 use std::io;
 use std::num::ParseIntError;
+use std::str::FromStr;
+
+use crate::Version;
 
 /// Error
 #[derive(thiserror::Error, Debug)]
@@ -8,6 +11,10 @@ pub enum Error {
     #[error("Invalid command or arguments")]
     CommandError { msg: String },
 
+    /// An argument was rejected by the client, before sending anything to the server
+    #[error("Invalid argument: {msg}")]
+    InvalidArgument { msg: String },
+
     /// The server closed the connection
     #[error("The server closed the connection")]
     Disconnected,
@@ -16,15 +23,297 @@ pub enum Error {
     #[error(transparent)]
     IOError(#[from] io::Error),
 
-    /// TODO
-    #[error("Server error")]
+    /// The server responded with an error
+    #[error("Server error: {msg}")]
     ServerError { msg: String },
 
     /// Generic unexpected response error
-    #[error("invalid value error")]
+    #[error("Invalid value: {msg}")]
     ValueError { msg: String },
 
     /// Conversion error
     #[error(transparent)]
     ParseInteError(#[from] ParseIntError),
+
+    /// The response doesn't have the expected value, or it is invalid
+    #[error("Unexpected response: {line}")]
+    ResponseError {
+        /// The line with the invalid value, or the first line of the response if the value is
+        /// missing
+        line: String,
+    },
+
+    /// A command failed. Lost connections are reported as is, without the command
+    #[error("Command `{cmdline}` failed: {source}")]
+    Command {
+        /// Name of the command
+        cmd: &'static str,
+        /// The command line sent to the server, with secrets left out
+        cmdline: String,
+        source: Box<Error>,
+    },
+
+    /// The command isn't supported by the protocol version of the server, checked before it
+    /// is sent when enabled with [`MpdClientBuilder::check_versions`]
+    ///
+    /// [`MpdClientBuilder::check_versions`]: crate::MpdClientBuilder::check_versions
+    #[error("`{cmd}` requires Mpd {required}, the server is {server}")]
+    Unsupported {
+        /// Name of the command
+        cmd: &'static str,
+        required: Version,
+        server: Version,
+    },
+
+    /// A recursive `listallinfo` of the whole database was refused, as the database has more
+    /// songs than the limit set with [`MpdClientBuilder::listallinfo_limit`]
+    ///
+    /// [`MpdClientBuilder::listallinfo_limit`]: crate::MpdClientBuilder::listallinfo_limit
+    #[error("The database has {songs} songs, more than the limit of {limit} for listallinfo; use walk() to list it a directory at a time")]
+    LibraryTooLarge { songs: u32, limit: u32 },
+}
+
+impl Error {
+    /// Attach the failed command to the error
+    pub(crate) fn in_command(self, cmd: &'static str, cmdline: &str) -> Self {
+        match self {
+            Error::Disconnected | Error::IOError(_) | Error::Command { .. } => self,
+            source => Error::Command {
+                cmd,
+                cmdline: cmdline.trim_end().to_string(),
+                source: Box::new(source),
+            },
+        }
+    }
+
+    /// The error without the command it happened in
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Command { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// The error without the command it happened in, taken out of the command
+    pub(crate) fn into_root(self) -> Error {
+        match self {
+            Error::Command { source, .. } => source.into_root(),
+            other => other,
+        }
+    }
+
+    /// The ACK error response of the server, if the server responded with an error
+    pub fn ack(&self) -> Option<Ack> {
+        match self.root() {
+            Error::ServerError { msg } => msg.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match self.root() {
+            Error::Disconnected | Error::IOError(_) => ErrorKind::Connection,
+            Error::CommandError { .. }
+            | Error::InvalidArgument { .. }
+            | Error::LibraryTooLarge { .. }
+            | Error::Unsupported { .. } => ErrorKind::Usage,
+            Error::ServerError { .. } => match self.ack().map(|ack| ack.code) {
+                Some(
+                    AckCode::NotList
+                    | AckCode::Arg
+                    | AckCode::Password
+                    | AckCode::Permission
+                    | AckCode::Unknown,
+                ) => ErrorKind::Usage,
+                Some(_) => ErrorKind::Server,
+                None => ErrorKind::Protocol,
+            },
+            Error::ValueError { .. }
+            | Error::ParseInteError(_)
+            | Error::ResponseError { .. }
+            | Error::Command { .. } => ErrorKind::Protocol,
+        }
+    }
+
+    /// The command may succeed if tried again, possibly after reconnecting
+    ///
+    /// Lost connections are retryable, as are server errors caused by the state of the server,
+    /// such as an update already running.
+    pub fn is_retryable(&self) -> bool {
+        match self.kind() {
+            ErrorKind::Connection => true,
+            ErrorKind::Server => matches!(
+                self.ack().map(|ack| ack.code),
+                Some(AckCode::System | AckCode::UpdateAlready | AckCode::PlayerSync)
+            ),
+            ErrorKind::Protocol | ErrorKind::Usage => false,
+        }
+    }
+}
+
+/// Category of an [`Error`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The connection was lost or couldn't be made
+    Connection,
+    /// The response of the server couldn't be understood
+    Protocol,
+    /// The server couldn't carry out a valid command, e.g. because a song doesn't exist
+    Server,
+    /// The command was rejected as invalid or not permitted
+    Usage,
+}
+
+/// Error code of an ACK response
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AckCode {
+    /// A command list was expected
+    NotList,
+    /// Invalid argument
+    Arg,
+    /// Wrong password
+    Password,
+    /// Not permitted without authenticating
+    Permission,
+    /// Unknown command
+    Unknown,
+    /// The song, playlist, sticker or other object doesn't exist
+    NoExist,
+    /// The playlist is full
+    PlaylistMax,
+    /// System error, such as a failing disk
+    System,
+    /// A stored playlist couldn't be loaded
+    PlaylistLoad,
+    /// A database update is already running
+    UpdateAlready,
+    /// The player isn't in the state required by the command
+    PlayerSync,
+    /// The object already exists
+    Exist,
+    /// A code not known by this crate
+    Other(u32),
+}
+
+impl From<u32> for AckCode {
+    fn from(code: u32) -> Self {
+        match code {
+            1 => AckCode::NotList,
+            2 => AckCode::Arg,
+            3 => AckCode::Password,
+            4 => AckCode::Permission,
+            5 => AckCode::Unknown,
+            50 => AckCode::NoExist,
+            51 => AckCode::PlaylistMax,
+            52 => AckCode::System,
+            53 => AckCode::PlaylistLoad,
+            54 => AckCode::UpdateAlready,
+            55 => AckCode::PlayerSync,
+            56 => AckCode::Exist,
+            other => AckCode::Other(other),
+        }
+    }
+}
+
+/// ACK error response, such as `ACK [50@0] {play} song doesn't exist: "10"`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ack {
+    pub code: AckCode,
+    /// Position of the failed command in a command list, 0 otherwise
+    pub list_index: u32,
+    /// Name of the failed command, empty if the server didn't get that far
+    pub command: String,
+    pub message: String,
+}
+
+impl FromStr for Ack {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::ValueError {
+            msg: format!("invalid ACK: {}", s),
+        };
+
+        let rest = s.strip_prefix("ACK [").ok_or_else(invalid)?;
+        let (code, rest) = rest.split_once('@').ok_or_else(invalid)?;
+        let (list_index, rest) = rest.split_once("] {").ok_or_else(invalid)?;
+        let (command, message) = rest.split_once('}').ok_or_else(invalid)?;
+
+        Ok(Ack {
+            code: code.parse::<u32>()?.into(),
+            list_index: list_index.parse()?,
+            command: command.to_string(),
+            message: message.trim_start().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server_error(msg: &str) -> Error {
+        Error::ServerError { msg: msg.into() }.in_command("play", "play \"10\"")
+    }
+
+    #[test]
+    fn parse_ack() {
+        let ack: Ack = r#"ACK [50@0] {play} song doesn't exist: "10""#.parse().unwrap();
+        assert_eq!(ack.code, AckCode::NoExist);
+        assert_eq!(ack.list_index, 0);
+        assert_eq!(ack.command, "play");
+        assert_eq!(ack.message, r#"song doesn't exist: "10""#);
+
+        let ack: Ack = "ACK [5@0] {} unknown command \"foo\"".parse().unwrap();
+        assert_eq!(ack.code, AckCode::Unknown);
+        assert_eq!(ack.command, "");
+
+        assert!("OK".parse::<Ack>().is_err());
+    }
+
+    #[test]
+    fn kinds() {
+        let no_exist = server_error("ACK [50@0] {play} song doesn't exist");
+        assert_eq!(no_exist.kind(), ErrorKind::Server);
+        assert!(!no_exist.is_retryable());
+
+        let updating = server_error("ACK [54@0] {update} already updating");
+        assert!(updating.is_retryable());
+
+        let unknown = server_error("ACK [5@0] {} unknown command");
+        assert_eq!(unknown.kind(), ErrorKind::Usage);
+
+        assert_eq!(Error::Disconnected.kind(), ErrorKind::Connection);
+        assert!(Error::Disconnected.is_retryable());
+        assert_eq!(server_error("garbage").kind(), ErrorKind::Protocol);
+
+        let invalid = Error::InvalidArgument {
+            msg: "empty filter".into(),
+        };
+        assert_eq!(invalid.kind(), ErrorKind::Usage);
+        assert_eq!(invalid.to_string(), "Invalid argument: empty filter");
+
+        let unexpected = Error::ResponseError { line: "OK".into() };
+        assert_eq!(unexpected.kind(), ErrorKind::Protocol);
+        assert!(!unexpected.is_retryable());
+
+        let too_large = Error::LibraryTooLarge {
+            songs: 300_000,
+            limit: 50_000,
+        };
+        assert_eq!(too_large.kind(), ErrorKind::Usage);
+        assert!(too_large.to_string().contains("walk()"));
+
+        let unsupported = Error::Unsupported {
+            cmd: "getvol",
+            required: Version::new(0, 23, 0),
+            server: Version::new(0, 21, 11),
+        };
+        assert_eq!(unsupported.kind(), ErrorKind::Usage);
+        assert_eq!(
+            unsupported.to_string(),
+            "`getvol` requires Mpd 0.23.0, the server is 0.21.11"
+        );
+    }
 }
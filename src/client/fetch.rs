@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::MpdClient;
+
+/// Keys starting the records counted as items: songs, directories and playlists
+const ITEM_KEYS: [&[u8]; 3] = [b"file: ", b"directory: ", b"playlist: "];
+
+/// Longest key in [`ITEM_KEYS`]
+const MAX_KEY: usize = 11;
+
+/// Handle to the progress of the responses read by a client, from [`MpdClient::fetch_progress`]
+///
+/// The handle can be cloned and polled from another task while the client is busy with a long
+/// fetch, such as `listallinfo`, `walk` or `queue_changes`, to show a progress bar.
+#[derive(Clone, Debug, Default)]
+pub struct FetchProgress {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    items: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl FetchProgress {
+    /// Songs, directories and playlists read since the last reset
+    pub fn items(&self) -> u64 {
+        self.counters.items.load(Ordering::Relaxed)
+    }
+
+    /// Bytes read since the last reset
+    pub fn bytes(&self) -> u64 {
+        self.counters.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Start counting from zero, e.g. before starting a fetch
+    pub fn reset(&self) {
+        self.counters.items.store(0, Ordering::Relaxed);
+        self.counters.bytes.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Counts the items and bytes of received data for a [`FetchProgress`]
+pub(crate) struct ProgressCounter {
+    handle: FetchProgress,
+    /// Start of the current line, at most `MAX_KEY` bytes
+    head: Vec<u8>,
+}
+
+impl ProgressCounter {
+    fn new(handle: FetchProgress) -> Self {
+        Self {
+            handle,
+            head: Vec::with_capacity(MAX_KEY),
+        }
+    }
+
+    pub(crate) fn received(&mut self, data: &[u8]) {
+        let mut items = 0;
+
+        for &b in data {
+            if b == b'\n' {
+                if ITEM_KEYS.iter().any(|key| self.head.starts_with(key)) {
+                    items += 1;
+                }
+                self.head.clear();
+            } else if self.head.len() < MAX_KEY {
+                self.head.push(b);
+            }
+        }
+
+        let counters = &self.handle.counters;
+        counters.items.fetch_add(items, Ordering::Relaxed);
+        counters
+            .bytes
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+    }
+}
+
+impl MpdClient {
+    /// Handle to the progress of the responses read by the client
+    ///
+    /// Counting starts with the first call, and every call returns a handle to the same
+    /// counters. The counts include the responses of all commands, call
+    /// [`FetchProgress::reset`] before a fetch to count only its items.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Error, MpdClient};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mut mpd = MpdClient::new();
+    ///     mpd.connect("localhost:6600").await?;
+    ///
+    ///     let progress = mpd.fetch_progress();
+    ///     let total = mpd.stats().await?.songs;
+    ///     progress.reset();
+    ///
+    ///     // Poll `progress.items()` against `total` from a UI task meanwhile
+    ///     let listing = mpd.listallinfo(None).await?;
+    ///     println!("{} of {} songs", listing.files.len(), total);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fetch_progress(&mut self) -> FetchProgress {
+        self.progress
+            .get_or_insert_with(|| ProgressCounter::new(FetchProgress::default()))
+            .handle
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FetchProgress, ProgressCounter};
+
+    #[test]
+    fn count_items() {
+        let progress = FetchProgress::default();
+        let mut counter = ProgressCounter::new(progress.clone());
+
+        let resp =
+            b"directory: music\nLast-Modified: 2021\nfile: music/a.flac\nTitle: file: no\nfi";
+        counter.received(resp);
+        counter.received(b"le: music/b.flac\nplaylist: p.m3u\nOK\n");
+
+        assert_eq!(progress.items(), 4);
+        assert_eq!(progress.bytes(), resp.len() as u64 + 36);
+
+        progress.reset();
+        assert_eq!((progress.items(), progress.bytes()), (0, 0));
+    }
+}
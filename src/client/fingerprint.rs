@@ -0,0 +1,102 @@
+use futures_lite::{future, stream, Stream};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+
+use crate::{Error, Fingerprint, MpdClient};
+
+type Fetch<'a> = Pin<
+    Box<dyn Future<Output = (&'a mut MpdClient, String, Result<Fingerprint, Error>)> + Send + 'a>,
+>;
+
+struct FingerprintState<'a, I> {
+    idle: Vec<&'a mut MpdClient>,
+    fetching: Vec<Fetch<'a>>,
+    uris: I,
+}
+
+/// Stream of the fingerprints of the songs `uris`, fetched over several connections
+///
+/// Each connection fetches one fingerprint at a time, so the number of connections bounds the
+/// number of fingerprints calculated concurrently by the server. The fingerprints are returned
+/// in the order they are ready, with failures reported for the song they happened for.
+/// Requires Mpd 0.22, built with chromaprint.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{fingerprints, Error, MpdClient};
+/// use futures_lite::StreamExt;
+/// use std::collections::HashMap;
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut clients = vec![MpdClient::new(), MpdClient::new()];
+///     for mpd in &mut clients {
+///         mpd.connect("localhost:6600").await?;
+///     }
+///
+///     let uris = clients[0].listall(None).await?.files;
+///     let mut songs = Box::pin(fingerprints(&mut clients, uris));
+///     let mut by_fingerprint = HashMap::<_, Vec<String>>::new();
+///
+///     while let Some((uri, fingerprint)) = songs.next().await {
+///         if let Ok(fingerprint) = fingerprint {
+///             by_fingerprint.entry(fingerprint).or_default().push(uri);
+///         }
+///     }
+///
+///     for uris in by_fingerprint.values().filter(|uris| uris.len() > 1) {
+///         println!("Duplicates: {:?}", uris);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub fn fingerprints<'a, I>(
+    clients: &'a mut [MpdClient],
+    uris: I,
+) -> impl Stream<Item = (String, Result<Fingerprint, Error>)> + 'a
+where
+    I: IntoIterator<Item = String>,
+    I::IntoIter: Send + 'a,
+{
+    let state = FingerprintState {
+        idle: clients.iter_mut().collect(),
+        fetching: Vec::new(),
+        uris: uris.into_iter(),
+    };
+
+    stream::unfold(state, |mut state| async move {
+        while !state.idle.is_empty() {
+            let uri = match state.uris.next() {
+                Some(uri) => uri,
+                None => break,
+            };
+
+            let client = state.idle.pop()?;
+            state.fetching.push(Box::pin(async move {
+                let fingerprint = client.fingerprint(&uri).await;
+                (client, uri, fingerprint)
+            }));
+        }
+
+        if state.fetching.is_empty() {
+            return None;
+        }
+
+        let (client, uri, fingerprint) = future::poll_fn(|cx| {
+            for i in 0..state.fetching.len() {
+                if let Poll::Ready(done) = state.fetching[i].as_mut().poll(cx) {
+                    drop(state.fetching.swap_remove(i));
+                    return Poll::Ready(done);
+                }
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        state.idle.push(client);
+        Some(((uri, fingerprint), state))
+    })
+}
@@ -0,0 +1,77 @@
+use serde::Serialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::{cmd, Error, MpdClient, PlayerError, Version};
+
+/// Health of the server, as reported by [`MpdClient::healthcheck`]
+#[derive(Serialize, Clone, Debug)]
+pub struct Health {
+    /// Round trip time of the health check
+    pub rtt: Duration,
+    /// Protocol version of the server
+    pub version: Option<Version>,
+    /// Time the server has been running
+    pub uptime: Duration,
+    /// Time since the database was last updated, None if it never was
+    pub db_update_age: Option<Duration>,
+    /// Error reported by the player, such as a decoder error
+    pub error: Option<PlayerError>,
+}
+
+impl Health {
+    /// The player has no error
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Time since `db_update`, the unix time of the last database update
+fn db_update_age(db_update: i32, now: SystemTime) -> Option<Duration> {
+    if db_update <= 0 {
+        return None;
+    }
+
+    let updated = UNIX_EPOCH + Duration::from_secs(db_update as u64);
+    now.duration_since(updated).ok()
+}
+
+impl MpdClient {
+    /// Check the health of the server, for readiness probes and the like
+    ///
+    /// `ping`, `status` and `stats` are sent in one command list, so the check takes a single
+    /// round trip.
+    pub async fn healthcheck(&mut self) -> Result<Health, Error> {
+        let start = Instant::now();
+        let ((), status, stats) = self
+            .exec(cmd::CommandList((cmd::Ping, cmd::Status, cmd::Stats)))
+            .await?;
+        let rtt = start.elapsed();
+
+        Ok(Health {
+            rtt,
+            version: self.server_version(),
+            uptime: stats.uptime,
+            db_update_age: db_update_age(stats.db_update, SystemTime::now()),
+            error: status.error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::db_update_age;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn update_age() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_100);
+
+        assert_eq!(
+            db_update_age(1_700_000_000, now),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(db_update_age(0, now), None);
+        // Clock of the server ahead of ours
+        assert_eq!(db_update_age(1_700_000_200, now), None);
+    }
+}
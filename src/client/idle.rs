@@ -0,0 +1,320 @@
+use futures_lite::{stream, Stream};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::{Error, MpdClient, Progress, State, Subsystem};
+
+/// Coalescing of bursts of idle events
+///
+/// Mpd reports a change for every modification, so adding a large number of songs to the queue
+/// results in a burst of `Playlist` events. With a window configured for a subsystem, its events
+/// are held back until no new event for it has arrived for the duration of the window, and are
+/// then reported once.
+#[derive(Clone, Debug, Default)]
+pub struct Debounce {
+    windows: HashMap<Subsystem, Duration>,
+}
+
+impl Debounce {
+    /// No coalescing of events
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coalesce events from `subsystem` arriving within `window` of each other
+    pub fn window(mut self, subsystem: Subsystem, window: Duration) -> Self {
+        self.windows.insert(subsystem, window);
+        self
+    }
+
+    /// Coalesce events from all subsystems arriving within `window` of each other
+    pub fn all(window: Duration) -> Self {
+        use Subsystem::*;
+
+        [
+            Database,
+            Player,
+            Mixer,
+            Options,
+            Update,
+            StoredPlaylist,
+            Playlist,
+            Output,
+            Partitions,
+            Sticker,
+            Subscription,
+            Message,
+            Neighbor,
+            Mount,
+            Other,
+        ]
+        .iter()
+        .fold(Self::new(), |debounce, s| debounce.window(*s, window))
+    }
+}
+
+/// Event of [`MpdClient::idle_stream`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IdleEvent {
+    /// A subsystem changed
+    Changed(Subsystem),
+    /// The connection was lost and made again. Changes may have been missed, so any state
+    /// kept from the server should be fetched again
+    Reconnected,
+}
+
+/// An event, with the time it was received
+///
+/// The time is taken from the monotonic clock of this process, so events of clients connected
+/// to different servers can be ordered by it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timestamped<T> {
+    pub event: T,
+    /// When the event was received. For coalesced events, when the last of them was received
+    pub received_at: Instant,
+}
+
+struct IdleState<'a> {
+    client: &'a mut MpdClient,
+    debounce: Debounce,
+    /// Events ready to be emitted
+    ready: VecDeque<Timestamped<IdleEvent>>,
+    /// Events held back, when they are released and when the last of them was received
+    held: HashMap<Subsystem, (Instant, Instant)>,
+    done: bool,
+}
+
+impl IdleState<'_> {
+    async fn next_event(&mut self) -> Result<Timestamped<IdleEvent>, Error> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Ok(event);
+            }
+
+            let deadline = self.held.values().map(|(release, _)| *release).min();
+            let changed = match self.client.idle_until(&[], deadline).await {
+                Ok(changed) => changed,
+                Err(e @ (Error::Disconnected | Error::IOError(_))) => {
+                    if self.client.reconnect_policy.is_none() {
+                        return Err(e);
+                    }
+
+                    self.client.reconnect_with_policy().await?;
+
+                    // Held back events are superseded by the reconnect
+                    self.held.clear();
+                    self.ready.push_back(Timestamped {
+                        event: IdleEvent::Reconnected,
+                        received_at: Instant::now(),
+                    });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let now = Instant::now();
+
+            for subsystem in changed {
+                let event = IdleEvent::Changed(subsystem);
+
+                match self.debounce.windows.get(&subsystem) {
+                    Some(window) => {
+                        self.held.insert(subsystem, (now + *window, now));
+                    }
+                    None if !self.ready.iter().any(|ready| ready.event == event) => {
+                        self.ready.push_back(Timestamped {
+                            event,
+                            received_at: now,
+                        })
+                    }
+                    None => (),
+                }
+            }
+
+            let released: Vec<_> = self
+                .held
+                .iter()
+                .filter(|(_, (release, _))| *release <= now)
+                .map(|(subsystem, (_, received_at))| (*subsystem, *received_at))
+                .collect();
+
+            for (subsystem, received_at) in released {
+                self.held.remove(&subsystem);
+                self.ready.push_back(Timestamped {
+                    event: IdleEvent::Changed(subsystem),
+                    received_at,
+                });
+            }
+        }
+    }
+}
+
+struct TickState<'a> {
+    client: &'a mut MpdClient,
+    interval: Duration,
+    /// Progress of the last status, None when it has to be fetched again
+    progress: Option<Progress>,
+    next_tick: Instant,
+    done: bool,
+}
+
+impl TickState<'_> {
+    async fn next_tick(&mut self) -> Result<Duration, Error> {
+        loop {
+            let progress = match self.progress {
+                Some(progress) => progress,
+                None => {
+                    let progress = Progress::from(&self.client.status().await?);
+                    self.progress = Some(progress);
+                    self.next_tick = progress.fetched + self.interval;
+                    return Ok(progress.elapsed);
+                }
+            };
+
+            // Nothing moves while paused or stopped, until the player changes
+            let deadline = match progress.state {
+                State::Play => Some(self.next_tick),
+                State::Pause | State::Stop => None,
+            };
+
+            if !self
+                .client
+                .idle_until(&[Subsystem::Player], deadline)
+                .await?
+                .is_empty()
+            {
+                self.progress = None;
+                continue;
+            }
+
+            let now = Instant::now();
+            self.next_tick = (self.next_tick + self.interval).max(now);
+            return Ok(progress.at(now));
+        }
+    }
+}
+
+impl MpdClient {
+    /// Stream of the elapsed time of the current song, every `interval` while playing
+    ///
+    /// The elapsed time is extrapolated from the last status, which is fetched again on every
+    /// change of the player, such as a seek or a new song. The current elapsed time is emitted
+    /// after every such change, and then nothing until the next change while paused or
+    /// stopped. The stream ends after the first error.
+    pub fn elapsed_ticks(
+        &mut self,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<Duration, Error>> + '_ {
+        let state = TickState {
+            client: self,
+            interval,
+            progress: None,
+            next_tick: Instant::now(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let tick = state.next_tick().await;
+            state.done = tick.is_err();
+            Some((tick, state))
+        })
+    }
+
+    /// Stream of changed subsystems, with the time they were received
+    ///
+    /// With a [`ReconnectPolicy`](crate::ReconnectPolicy) set on the builder, the stream
+    /// reconnects when the connection is lost, and reports it with [`IdleEvent::Reconnected`].
+    /// The stream ends after the first error, including failing to reconnect.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Debounce, Error, IdleEvent, MpdClient, ReconnectPolicy, Subsystem};
+    /// use futures_lite::StreamExt;
+    /// use std::time::Duration;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mut mpd = MpdClient::builder()
+    ///         .reconnect(ReconnectPolicy::new().forever())
+    ///         .build();
+    ///     mpd.connect("localhost:6600").await?;
+    ///
+    ///     let debounce = Debounce::new().window(Subsystem::Playlist, Duration::from_millis(250));
+    ///     let mut events = Box::pin(mpd.idle_stream(debounce));
+    ///
+    ///     while let Some(event) = events.next().await {
+    ///         match event?.event {
+    ///             IdleEvent::Changed(subsystem) => println!("{}", subsystem),
+    ///             IdleEvent::Reconnected => println!("Reconnected"),
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn idle_stream(
+        &mut self,
+        debounce: Debounce,
+    ) -> impl Stream<Item = Result<Timestamped<IdleEvent>, Error>> + '_ {
+        let state = IdleState {
+            client: self,
+            debounce,
+            ready: VecDeque::new(),
+            held: HashMap::new(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let event = state.next_event().await;
+            state.done = event.is_err();
+            Some((event, state))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::mock::{run, MockServer};
+    use futures_lite::StreamExt;
+    use std::time::Duration;
+
+    #[test]
+    fn elapsed_ticks() {
+        let server = MockServer::new(&[
+            (&["status"], "state: pause\nelapsed: 5.000\nOK\n"),
+            // Paused, so the idle waits for the player to change
+            (&["idle \"player\""], "changed: player\nOK\n"),
+            (
+                &["status"],
+                "state: play\nelapsed: 10.000\nduration: 60.000\nOK\n",
+            ),
+            // Playing, so the idle is left for the next tick
+            (&["idle \"player\""], ""),
+            (&["noidle"], "OK\n"),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let mut ticks = Box::pin(client.elapsed_ticks(Duration::from_millis(10)));
+            assert_eq!(ticks.next().await.unwrap().unwrap(), Duration::from_secs(5));
+            assert_eq!(
+                ticks.next().await.unwrap().unwrap(),
+                Duration::from_secs(10)
+            );
+
+            let tick = ticks.next().await.unwrap().unwrap();
+            assert!(tick > Duration::from_secs(10) && tick < Duration::from_secs(11));
+        });
+
+        server.finish();
+    }
+}
@@ -0,0 +1,247 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::{
+    cmd, Error, Filter, FilterExpr, MpdClient, Primary, SongId, SongPos, Sort, SortKey, Tag,
+    ToFilterExpr, Track,
+};
+
+/// Album with its tracks
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct Album {
+    pub name: String,
+    /// Album artist, or the artist of the first track if it has no album artist
+    pub artist: String,
+    /// Tracks, ordered by disc and track number
+    pub tracks: Vec<Track>,
+    /// Total duration of the tracks
+    pub duration: Duration,
+}
+
+impl Album {
+    /// Group tracks by album name and artist, in order of first appearance
+    pub fn group(tracks: Vec<Track>) -> Vec<Album> {
+        let mut albums: Vec<Album> = Vec::new();
+        // Index in `albums` of every album name and artist
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+
+        for track in tracks {
+            let i = *index
+                .entry(album_key(&track))
+                .or_insert_with_key(|(name, artist)| {
+                    albums.push(Album {
+                        name: name.clone(),
+                        artist: artist.clone(),
+                        ..Album::default()
+                    });
+                    albums.len() - 1
+                });
+
+            let album = &mut albums[i];
+            album.duration += track.duration;
+            album.tracks.push(track);
+        }
+
+        for album in &mut albums {
+            sort_tracks(&mut album.tracks);
+        }
+
+        albums
+    }
+}
+
+/// Album name and artist of a track. The artist is the album artist, or the artist if the track
+/// has no album artist
+pub(crate) fn album_key(track: &Track) -> (String, String) {
+    let name = track.album.clone().unwrap_or_default();
+    let artist = track
+        .album_artist
+        .clone()
+        .or_else(|| track.artist.primary().map(String::from))
+        .unwrap_or_default();
+
+    (name, artist)
+}
+
+/// Moves rearranging the songs `current` into the order `target`, both listing the same ids
+fn moves(mut current: Vec<SongId>, target: &[SongId]) -> Vec<cmd::MoveId> {
+    let mut moves = Vec::new();
+
+    for (pos, &id) in target.iter().enumerate() {
+        if current[pos] == id {
+            continue;
+        }
+
+        let from = current[pos..].iter().position(|&i| i == id).unwrap() + pos;
+        current.remove(from);
+        current.insert(pos, id);
+        moves.push(cmd::MoveId {
+            id,
+            to: SongPos(pos as u32),
+        });
+    }
+
+    moves
+}
+
+/// Sort tracks by disc and track number
+fn sort_tracks(tracks: &mut [Track]) {
+    tracks.sort_by_key(|t| (t.disc.unwrap_or(0), t.track.unwrap_or(0)));
+}
+
+impl MpdClient {
+    /// All artists in the database
+    pub async fn artists(&mut self) -> Result<Vec<String>, Error> {
+        let mut artists = self.list(Tag::Artist, &Filter::new()).await?;
+        artists.retain(|a| !a.is_empty());
+        Ok(artists)
+    }
+
+    /// Albums with tracks by `artist`
+    pub async fn albums_by_artist(&mut self, artist: &str) -> Result<Vec<Album>, Error> {
+        let tracks = self.find(&Filter::with(Tag::Artist.equals(artist))).await?;
+        Ok(Album::group(tracks))
+    }
+
+    /// Tracks of an album, ordered by disc and track number
+    pub async fn tracks_in_album(
+        &mut self,
+        artist: &str,
+        album: &str,
+    ) -> Result<Vec<Track>, Error> {
+        let filter = Filter::with(Tag::Artist.equals(artist)).and(Tag::Album.equals(album));
+
+        let mut tracks = self.find(&filter).await?;
+        sort_tracks(&mut tracks);
+        Ok(tracks)
+    }
+
+    /// Songs added to the database since `since`, most recently added first. Requires Mpd 0.24
+    pub async fn recently_added(&mut self, since: DateTime<Utc>) -> Result<Vec<Track>, Error> {
+        let query = Filter::with(FilterExpr::AddedSince(since)).to_query();
+        self.exec(cmd::Find::new(query.as_deref()).sort(Sort::by(SortKey::Added).descending()))
+            .await
+    }
+
+    /// Replace the queue with an album and start playing it
+    ///
+    /// The queue is cleared, the tracks added with `findadd` and playback started in one command
+    /// list, so the album is found by the server and other clients never see a half built
+    /// queue. The tracks are added in database order, which is by file name.
+    pub async fn play_album(&mut self, artist: &str, album: &str) -> Result<(), Error> {
+        let filter = Filter::with(Tag::Artist.equals(artist)).and(Tag::Album.equals(album));
+        let query = filter.to_query().unwrap_or_default();
+
+        self.exec(cmd::CommandList((
+            cmd::QueueClear,
+            cmd::FindAdd(&query),
+            cmd::Play(None),
+        )))
+        .await?;
+        Ok(())
+    }
+
+    /// Shuffle the queue by album
+    ///
+    /// The songs of every album are gathered, keeping the order they have in the queue, and the
+    /// albums put in random order. The queue is rearranged with `moveid` in one command list,
+    /// so playback isn't interrupted and other clients never see a half shuffled queue.
+    pub async fn shuffle_albums(&mut self) -> Result<(), Error> {
+        let queue = self.queue().await?;
+        let current: Vec<SongId> = queue.iter().filter_map(|t| t.id).collect();
+
+        let mut albums: Vec<Vec<SongId>> = Vec::new();
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+        for track in &queue {
+            let id = match track.id {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let i = *index.entry(album_key(track)).or_insert_with(|| {
+                albums.push(Vec::new());
+                albums.len() - 1
+            });
+            albums[i].push(id);
+        }
+
+        fastrand::shuffle(&mut albums);
+        let target: Vec<SongId> = albums.into_iter().flatten().collect();
+
+        let moves = moves(current, &target);
+        if !moves.is_empty() {
+            self.exec(cmd::CommandList(moves)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Add the albums of the songs exactly matching `filter` to the queue, in random order
+    ///
+    /// The songs of every album are added together, ordered by disc and track number.
+    pub async fn add_albums_shuffled(&mut self, filter: &Filter) -> Result<(), Error> {
+        let mut albums = Album::group(self.find(filter).await?);
+        fastrand::shuffle(&mut albums);
+
+        let adds: Vec<_> = albums
+            .iter()
+            .flat_map(|album| &album.tracks)
+            .map(|t| cmd::QueueAdd(&t.file))
+            .collect();
+
+        if !adds.is_empty() {
+            self.exec(cmd::CommandList(adds)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{moves, Album};
+    use crate::{SongId, SongPos, Track};
+    use std::time::Duration;
+
+    #[test]
+    fn group_albums() {
+        let track = |file: &str, album: &str, nr: u32| {
+            Track::builder(file)
+                .artist("Artist")
+                .album(album)
+                .track(nr)
+                .duration(Duration::from_secs(60))
+                .build()
+        };
+
+        let albums = Album::group(vec![
+            track("a2", "A", 2),
+            track("b1", "B", 1),
+            track("a1", "A", 1),
+        ]);
+
+        assert_eq!(albums.len(), 2);
+        assert_eq!(albums[0].name, "A");
+        assert_eq!(albums[0].artist, "Artist");
+        assert_eq!(albums[0].duration, Duration::from_secs(120));
+
+        let files: Vec<_> = albums[0].tracks.iter().map(|t| t.file.as_str()).collect();
+        assert_eq!(files, vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn album_moves() {
+        let ids = |ids: &[u32]| ids.iter().map(|&i| SongId(i)).collect::<Vec<_>>();
+
+        let moves = moves(ids(&[1, 2, 3, 4, 5]), &ids(&[4, 5, 1, 2, 3]));
+        let moves: Vec<_> = moves.iter().map(|m| (m.id, m.to)).collect();
+        assert_eq!(
+            moves,
+            vec![(SongId(4), SongPos(0)), (SongId(5), SongPos(1))]
+        );
+
+        assert!(super::moves(ids(&[1, 2]), &ids(&[1, 2])).is_empty());
+    }
+}
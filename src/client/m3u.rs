@@ -0,0 +1,67 @@
+//! Conversion between song lists and M3U8 playlists
+
+use crate::{Primary, Track};
+
+/// Render songs as an extended M3U8 playlist
+///
+/// Each song gets an `#EXTINF` line with its duration and `artist - title`, when known.
+pub fn to_m3u(tracks: &[Track]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+
+    for track in tracks {
+        let title = match (track.artist.primary(), &track.title) {
+            (Some(artist), Some(title)) => format!("{} - {}", artist, title),
+            (None, Some(title)) => title.clone(),
+            _ => String::new(),
+        };
+
+        if !title.is_empty() || !track.duration.is_zero() {
+            m3u.push_str(&format!("#EXTINF:{},{}\n", track.duration.as_secs(), title));
+        }
+
+        m3u.push_str(&track.file);
+        m3u.push('\n');
+    }
+
+    m3u
+}
+
+/// Uris of the songs in an M3U or M3U8 playlist
+///
+/// Comments and directives, such as `#EXTINF`, are skipped.
+pub fn parse_m3u(m3u: &str) -> Vec<String> {
+    m3u.trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn m3u_roundtrip() {
+        let tracks = vec![
+            Track::builder("music/a.flac")
+                .artist("Artist")
+                .title("Title")
+                .duration(Duration::from_secs(61))
+                .build(),
+            Track::builder("http://radio.example/stream").build(),
+        ];
+
+        let m3u = to_m3u(&tracks);
+        assert_eq!(
+            m3u,
+            "#EXTM3U\n#EXTINF:61,Artist - Title\nmusic/a.flac\nhttp://radio.example/stream\n"
+        );
+        assert_eq!(
+            parse_m3u(&format!("\u{feff}{}", m3u.replace('\n', "\r\n"))),
+            vec!["music/a.flac", "http://radio.example/stream"]
+        );
+    }
+}
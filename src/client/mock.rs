@@ -0,0 +1,85 @@
+//! Mock server for the tests of the client
+
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::thread::{self, JoinHandle};
+
+use crate::MpdClient;
+
+/// Run `f` to completion, on the runtime of the backend
+#[cfg(feature = "backend-tokio")]
+pub(crate) fn run<F: Future>(f: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(f)
+}
+
+/// Run `f` to completion, on the runtime of the backend
+#[cfg(not(feature = "backend-tokio"))]
+pub(crate) fn run<F: Future>(f: F) -> F::Output {
+    futures_lite::future::block_on(f)
+}
+
+/// Server accepting one connection, and answering the commands of a script
+///
+/// Every step of the script is the lines the client is expected to send, without line ends,
+/// and the response to send back once they were received.
+pub(crate) struct MockServer {
+    pub(crate) addr: SocketAddr,
+    thread: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Server of Mpd 0.23.5
+    pub(crate) fn new(script: &[(&[&str], &str)]) -> Self {
+        Self::with_version("0.23.5", script)
+    }
+
+    /// Server reporting the protocol version `version`
+    pub(crate) fn with_version(version: &str, script: &[(&[&str], &str)]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let greeting = format!("OK MPD {}\n", version);
+        let script: Vec<(Vec<String>, String)> = script
+            .iter()
+            .map(|(lines, resp)| {
+                (
+                    lines.iter().map(|l| l.to_string()).collect(),
+                    resp.to_string(),
+                )
+            })
+            .collect();
+
+        let thread = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(greeting.as_bytes()).unwrap();
+
+            let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+
+            for (expected, resp) in script {
+                for line in expected {
+                    assert_eq!(lines.next().unwrap().unwrap(), line);
+                }
+                stream.write_all(resp.as_bytes()).unwrap();
+            }
+        });
+
+        Self { addr, thread }
+    }
+
+    /// Client connected to the server
+    pub(crate) async fn client(&self) -> MpdClient {
+        let mut client = MpdClient::new();
+        client.connect(self.addr).await.unwrap();
+        client
+    }
+
+    /// Wait for the end of the script, failing if the client didn't follow it
+    pub(crate) fn finish(self) {
+        self.thread.join().unwrap();
+    }
+}
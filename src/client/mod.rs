@@ -1,13 +1,74 @@
+//! The async client, with its builder, errors and commands
+
+mod art;
+mod backend;
+mod builder;
+mod cache;
 pub mod cmd;
+mod env;
 mod error;
-mod filter;
+mod fetch;
+mod fingerprint;
+mod health;
+mod idle;
+mod library;
+pub mod m3u;
+#[cfg(test)]
+mod mock;
 mod mpdclient;
+mod multi;
+mod pager;
+mod prefetch;
+mod queue;
+mod reconnect;
 pub(crate) mod resp;
+mod response_cache;
+mod resume;
+mod secret;
+mod smart;
+mod snapshot;
+mod stop_after;
+mod storage;
+mod tap;
+mod throttle;
+mod transport;
+mod tree;
+mod url;
+mod walk;
+mod watch;
 //pub(crate) mod io;
 
-pub use error::Error;
-pub use filter::*;
+pub use art::{ArtCache, ArtKey};
+pub use backend::ToSocketAddrs;
+pub use builder::MpdClientBuilder;
+pub use cache::LibraryCache;
+pub use error::{Ack, AckCode, Error, ErrorKind};
+pub use fetch::FetchProgress;
+pub use fingerprint::fingerprints;
+pub use health::Health;
+pub use idle::{Debounce, IdleEvent, Timestamped};
+pub use library::Album;
 pub use mpdclient::*;
+pub use multi::MpdMultiClient;
+pub use pager::SearchPager;
+pub use prefetch::Prefetcher;
+pub use queue::Queue;
+pub use reconnect::ReconnectPolicy;
+pub use resume::SavedPosition;
+pub use secret::SecretString;
+pub use smart::SmartPlaylist;
+pub use stop_after::StopAfterCurrent;
+pub use storage::StorageEvent;
+pub use tap::WireDirection;
+pub use throttle::RateLimit;
+pub use tree::{DirNode, FileTree};
+pub use watch::{StatsDelta, StatsWatcher};
 
-pub use resp::handlers::ResponseHandler;
-pub use resp::WrappedResponse;
+pub use resp::handlers::{
+    BinaryResponse, CommandListResponse, KeyValueResponse, ListResponse, MixedResponseResponse,
+    OkResponse, RawResponse, ResponseHandler, SingleLineResp, SingleValue, Tracks,
+};
+pub use resp::respmap::RespMap;
+pub use resp::respmap_handlers::{ListallResponse, ListallinfoResponse, MixedResponse};
+pub use resp::{BinaryChunk, WrappedResponse};
+pub use response_cache::ResponseCache;
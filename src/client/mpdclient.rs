@@ -1,25 +1,147 @@
-use async_net::{AsyncToSocketAddrs, TcpStream};
-use futures_lite::{io::BufReader, AsyncWriteExt};
+use futures_lite::{future, io::BufReader, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt};
+use std::collections::HashMap;
+use std::fmt;
 use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+#[cfg(unix)]
+use crate::client::backend::connect_unix;
+use crate::client::backend::{connect_tcp, sleep_until, TcpStream, ToSocketAddrs};
+use crate::client::fetch::ProgressCounter;
+use crate::client::tap::{TapReader, WireTap};
+use crate::client::throttle::TokenBucket;
+use crate::client::transport::{SocketOptions, Transport};
 use crate::resp::WrappedResponse;
 use crate::{
     client::resp::{
-        handlers::ResponseHandler,
-        read_resp_line,
+        handlers::{ListResponse, ResponseHandler},
+        read_pairs, read_resp_line,
         respmap_handlers::{ListallResponse, ListallinfoResponse},
     },
     cmd::{self, MpdCmd},
-    DatabaseVersion, Error, Filter, Stats, Status, Subsystem, Track,
+    AckCode, ConsumeMode, DatabaseVersion, Error, Filter, Fingerprint, MpdClientBuilder, Output,
+    Position, ReconnectPolicy, ReplayGainMode, SecretString, SingleMode, SongId, SongPos, State,
+    Stats, Status, Sticker, StickerMatch, StoredPlaylist, Subsystem, Tag, Track, Version, Volume,
 };
 
+/// Name of the sticker used for song ratings
+const RATING_STICKER: &str = "rating";
+/// Name of the sticker used for play counts
+const PLAYCOUNT_STICKER: &str = "playcount";
+/// Songs whose stickers are listed per command list by `stickers_for`
+const STICKER_BATCH: usize = 256;
+/// Songs added per command list by `import_m3u`
+const PLAYLIST_BATCH: usize = 256;
+
+/// Address of a server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ServerAddress {
+    Tcp(SocketAddr),
+    /// Path of a unix socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// Name of a stream given to [`MpdClient::connect_stream`]
+    Stream(String),
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerAddress::Tcp(addr) => addr.fmt(f),
+            #[cfg(unix)]
+            ServerAddress::Unix(path) => path.display().fmt(f),
+            ServerAddress::Stream(name) => name.fmt(f),
+        }
+    }
+}
+
+/// State of the connection to the server
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Not connected, or the connection was lost
+    Disconnected,
+    /// Connected and ready for commands
+    Connected {
+        /// When the connection was made
+        since: Instant,
+        /// Address of the server
+        server: ServerAddress,
+        /// Protocol version reported by the server
+        version: Option<Version>,
+    },
+    /// Connected, with an idle command waiting for the server
+    Idle,
+}
+
+/// Change of the connection state, reported to the handler set with
+/// [`MpdClientBuilder::on_event`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// Connected with [`MpdClient::connect`]
+    Connected {
+        server: ServerAddress,
+        version: Option<Version>,
+    },
+    /// Connected again with [`MpdClient::reconnect`]
+    Reconnected {
+        server: ServerAddress,
+        version: Option<Version>,
+    },
+    /// Authenticated with a password
+    Authenticated,
+    /// The connection was lost
+    Disconnected {
+        /// The error that revealed the lost connection
+        reason: String,
+    },
+}
+
+/// Handler of connection events
+pub(crate) type EventHandler = Arc<dyn Fn(&ConnectionEvent) + Send + Sync>;
+
 /// Mpd Client
 #[derive(Default)]
 pub struct MpdClient {
     /// Buffered Stream
-    stream: Option<BufReader<TcpStream>>,
-    // Addr
-    addr: Option<SocketAddr>,
+    stream: Option<BufReader<Transport>>,
+    /// Address of the server, for reconnect
+    addr: Option<ServerAddress>,
+    /// Addresses tried in order by `connect_failover` and `reconnect`
+    pub(crate) failover: Vec<ServerAddress>,
+    /// Set while an idle command is waiting for its response
+    idle: bool,
+    /// Protocol version reported by the server
+    version: Option<Version>,
+    /// When the current connection was made
+    connected_since: Option<Instant>,
+    /// Tags the server is asked to send after connecting, all tags if None
+    pub(crate) tag_profile: Option<Vec<Tag>>,
+    pub(crate) event_handler: Option<EventHandler>,
+    /// Password sent after connecting
+    pub(crate) password: Option<SecretString>,
+    /// Partition switched to after connecting, the default partition if None
+    pub(crate) partition: Option<String>,
+    /// How to reconnect after the connection was lost, never if None
+    pub(crate) reconnect_policy: Option<ReconnectPolicy>,
+    pub(crate) socket_options: SocketOptions,
+    /// Receives the lines sent and received, if set
+    pub(crate) wire_tap: Option<WireTap>,
+    /// Counts what is read, once a progress handle was asked for
+    pub(crate) progress: Option<ProgressCounter>,
+    /// Limits the rate commands are sent at, if set
+    pub(crate) throttle: Option<TokenBucket>,
+    /// Commands taking longer are logged as slow, if set
+    pub(crate) slow_command_threshold: Option<Duration>,
+    /// Commands are checked against the version of the server before they are sent
+    pub(crate) check_versions: bool,
+    /// Most songs a recursive `listallinfo` of the whole database is allowed for, if set
+    pub(crate) listallinfo_limit: Option<u32>,
+    /// Domain to verify the server certificate against, when connecting with TLS
+    #[cfg(feature = "tls")]
+    pub(crate) tls_domain: Option<String>,
 }
 
 impl MpdClient {
@@ -28,43 +150,390 @@ impl MpdClient {
         Self {
             stream: None,
             addr: None,
+            failover: Vec::new(),
+            idle: false,
+            version: None,
+            connected_since: None,
+            tag_profile: None,
+            event_handler: None,
+            password: None,
+            partition: None,
+            reconnect_policy: None,
+            socket_options: SocketOptions::default(),
+            wire_tap: None,
+            progress: None,
+            throttle: None,
+            slow_command_threshold: None,
+            check_versions: false,
+            listallinfo_limit: None,
+            #[cfg(feature = "tls")]
+            tls_domain: None,
+        }
+    }
+
+    /// Builder for a client with non-default settings
+    pub fn builder() -> MpdClientBuilder {
+        MpdClientBuilder::new()
+    }
+
+    pub async fn connect<A: ToSocketAddrs>(&mut self, addr: A) -> Result<String, Error> {
+        let stream = connect_tcp(addr).await?;
+        let version = self.open_tcp(stream).await?;
+        self.emit_connected(false);
+        Ok(version)
+    }
+
+    /// Connect to a server listening on a unix socket
+    #[cfg(unix)]
+    pub async fn connect_unix<P: AsRef<Path>>(&mut self, path: P) -> Result<String, Error> {
+        let path = path.as_ref().to_path_buf();
+        let stream = connect_unix(&path).await?;
+        let version = self
+            .open(Transport::Unix(stream), ServerAddress::Unix(path))
+            .await?;
+        self.emit_connected(false);
+        Ok(version)
+    }
+
+    /// Talk to a server over `stream`, for connections other than TCP and unix sockets, such as
+    /// a tunnel or a proxy. `name` identifies the server in [`ConnectionState`] and
+    /// [`ConnectionEvent`]s.
+    ///
+    /// The client can't open the stream again, so [`reconnect`](Self::reconnect) fails with
+    /// [`Error::Disconnected`] once it is lost.
+    pub async fn connect_stream<S>(&mut self, stream: S, name: &str) -> Result<String, Error>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let version = self
+            .open(
+                Transport::Stream(Box::new(stream)),
+                ServerAddress::Stream(name.to_string()),
+            )
+            .await?;
+        self.emit_connected(false);
+        Ok(version)
+    }
+
+    /// Connect to the first server of the addresses given to [`MpdClientBuilder::failover`]
+    /// that accepts the connection
+    ///
+    /// The addresses are tried in order, and the one connected to is the server of the
+    /// [`ConnectionState`] and the [`ConnectionEvent::Connected`] event. Fails with the error
+    /// of the last address if none of them can be connected to.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Error, MpdClient, ServerAddress};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     // The local server when at home, the one at the office otherwise
+    ///     let mut mpd = MpdClient::builder()
+    ///         .failover(&[
+    ///             ServerAddress::Unix("/run/mpd/socket".into()),
+    ///             ServerAddress::Tcp("192.168.1.20:6600".parse().unwrap()),
+    ///         ])
+    ///         .build();
+    ///
+    ///     mpd.connect_failover().await?;
+    ///     println!("{:?}", mpd.connection_state());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn connect_failover(&mut self) -> Result<String, Error> {
+        let version = self.open_failover().await?;
+        self.emit_connected(false);
+        Ok(version)
+    }
+
+    /// Connect again to the server connected to last
+    ///
+    /// With addresses given to [`MpdClientBuilder::failover`], they are tried again in order,
+    /// starting with the first one.
+    pub async fn reconnect(&mut self) -> Result<(), Error> {
+        if !self.failover.is_empty() {
+            log::debug!(
+                "Reconnection to the first of {} servers",
+                self.failover.len()
+            );
+            self.open_failover().await?;
+            self.emit_connected(true);
+            return Ok(());
+        }
+
+        let addr = match self.addr.clone() {
+            Some(addr) => addr,
+            None => {
+                log::warn!("Reconnect without previous connection");
+                return Err(Error::Disconnected);
+            }
+        };
+
+        log::debug!("Reconnection to: {}", addr);
+
+        self.open_addr(addr).await?;
+        self.emit_connected(true);
+        Ok(())
+    }
+
+    /// Open a connection to the first of the failover addresses that can be connected to
+    async fn open_failover(&mut self) -> Result<String, Error> {
+        let mut last_error = Error::ValueError {
+            msg: "no failover addresses".into(),
+        };
+
+        for addr in self.failover.clone() {
+            match self.open_addr(addr.clone()).await {
+                Ok(version) => return Ok(version),
+                Err(e) => {
+                    log::debug!("Connection to {} failed: {}", addr, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        self.stream = None;
+        self.connected_since = None;
+        Err(last_error)
+    }
+
+    async fn open_addr(&mut self, addr: ServerAddress) -> Result<String, Error> {
+        match addr {
+            ServerAddress::Tcp(addr) => {
+                let stream = connect_tcp(addr).await?;
+                self.open_tcp(stream).await
+            }
+            #[cfg(unix)]
+            ServerAddress::Unix(path) => {
+                let stream = connect_unix(&path).await?;
+                self.open(Transport::Unix(stream), ServerAddress::Unix(path))
+                    .await
+            }
+            ServerAddress::Stream(_) => {
+                log::warn!("Reconnect of a stream given by the user");
+                Err(Error::Disconnected)
+            }
         }
     }
 
-    pub async fn connect<A: AsyncToSocketAddrs>(&mut self, addr: A) -> Result<String, Error> {
-        let stream = TcpStream::connect(addr).await?;
+    async fn open_tcp(&mut self, stream: TcpStream) -> Result<String, Error> {
         // Save the resolved adress for reconnect
         let sock_addr = stream.peer_addr()?;
+        self.socket_options.apply(&stream)?;
+
+        #[cfg(feature = "tls")]
+        let transport = match &self.tls_domain {
+            Some(domain) => Transport::tls(stream, domain).await?,
+            None => Transport::Tcp(stream),
+        };
+        #[cfg(not(feature = "tls"))]
+        let transport = Transport::Tcp(stream);
 
-        let reader = BufReader::new(stream);
+        self.open(transport, ServerAddress::Tcp(sock_addr)).await
+    }
 
-        log::debug!("server: {:?}", sock_addr);
+    async fn open(&mut self, transport: Transport, addr: ServerAddress) -> Result<String, Error> {
+        log::debug!("server: {}", addr);
 
-        self.stream = Some(reader);
-        self.addr = Some(sock_addr);
+        self.stream = Some(BufReader::with_capacity(
+            self.socket_options.read_buffer_size,
+            transport,
+        ));
+        self.addr = Some(addr);
+        self.idle = false;
+        self.connected_since = Some(Instant::now());
 
         // After connect, the server replies with a a version reply
-        Ok(self.read_version().await?)
+        let version = self.read_version().await?;
+
+        if let Some(password) = self.password.clone() {
+            self.exec(cmd::Password(&password)).await?;
+        }
+
+        if let Some(partition) = self.partition.clone() {
+            self.exec(cmd::Partition(&partition)).await?;
+        }
+
+        if self.tag_profile.is_some() {
+            self.reset_tag_types().await?;
+        }
+
+        Ok(version)
     }
 
-    pub async fn reconnect(&mut self) -> Result<(), Error> {
-        if let Some(addr) = self.addr {
-            log::debug!("Reconnection to: {:?}", addr);
-            self.connect(addr).await.map(|_| ())
-        } else {
-            log::warn!("Reconnect without previous connection");
-            Err(Error::Disconnected)
+    fn emit_connected(&self, reconnected: bool) {
+        if let Some(server) = self.addr.clone() {
+            let version = self.version;
+
+            self.emit(if reconnected {
+                ConnectionEvent::Reconnected { server, version }
+            } else {
+                ConnectionEvent::Connected { server, version }
+            });
+        }
+
+        if self.password.is_some() {
+            self.emit(ConnectionEvent::Authenticated);
         }
     }
 
-    async fn read_version(&mut self) -> Result<String, Error> {
-        let br = self.stream.as_mut().ok_or(Error::Disconnected)?;
+    fn emit(&self, event: ConnectionEvent) {
+        if let Some(handler) = &self.event_handler {
+            handler(&event);
+        }
+    }
 
-        let version = read_resp_line(br).await?;
+    /// Reader of responses, passing them to the wire tap and progress counter
+    fn reader(&mut self) -> Result<TapReader<'_, Transport>, Error> {
+        Ok(TapReader {
+            inner: self.stream.as_mut().ok_or(Error::Disconnected)?,
+            tap: self.wire_tap.as_mut(),
+            progress: self.progress.as_mut(),
+            bytes: 0,
+        })
+    }
+
+    async fn read_version(&mut self) -> Result<String, Error> {
+        let version = read_resp_line(&mut self.reader()?).await?;
         log::debug!("Connected: {}", version);
+        self.version = version.parse().ok();
         Ok(version)
     }
 
+    /// Limit the tags sent by the server to the tag profile, or send all tags without one
+    async fn reset_tag_types(&mut self) -> Result<(), Error> {
+        if self.version < Some(Version::new(0, 21, 0)) {
+            log::warn!("Server does not support tag types, ignoring the tag profile");
+            return Ok(());
+        }
+
+        match self.tag_profile.clone() {
+            Some(tags) => {
+                self.exec(cmd::TagTypes::Clear).await?;
+
+                if tags.is_empty() {
+                    Ok(())
+                } else {
+                    self.exec(cmd::TagTypes::Enable(&tags)).await
+                }
+            }
+            None => self.exec(cmd::TagTypes::All).await,
+        }
+    }
+
+    /// Authenticate with a password, to get the permissions given to it by the server
+    ///
+    /// A password set with [`MpdClientBuilder::password`] is sent automatically after connecting.
+    pub async fn password(&mut self, password: &SecretString) -> Result<(), Error> {
+        self.exec(cmd::Password(password)).await?;
+        self.emit(ConnectionEvent::Authenticated);
+        Ok(())
+    }
+
+    /// Close the connection
+    ///
+    /// Tells the server that the client is done, and shuts down the connection. The client can
+    /// connect again with [`reconnect`](Self::reconnect). Dropping the client shuts down the
+    /// connection as well, without telling the server.
+    pub async fn close(&mut self) -> Result<(), Error> {
+        if self.stream.is_none() {
+            return Ok(());
+        }
+
+        // The server closes the connection without responding, even when idling. If the
+        // connection was already lost, there is nothing left to close.
+        if let Err(Error::Disconnected) = self.send_command(&cmd::Close.to_cmdline()).await {
+            return Ok(());
+        }
+
+        if let Some(mut stream) = self.stream.take() {
+            if stream.get_ref().closes_on_drop() {
+                stream.close().await?;
+            }
+            stream.get_ref().shutdown()?;
+
+            self.emit(ConnectionEvent::Disconnected {
+                reason: "Connection closed".into(),
+            });
+        }
+
+        self.idle = false;
+        self.connected_since = None;
+        Ok(())
+    }
+
+    /// State of the connection
+    ///
+    /// The client only notices a lost connection when a command fails because of it.
+    pub fn connection_state(&self) -> ConnectionState {
+        match (&self.stream, self.addr.clone(), self.connected_since) {
+            (Some(_), _, _) if self.idle => ConnectionState::Idle,
+            (Some(_), Some(server), Some(since)) => ConnectionState::Connected {
+                since,
+                server,
+                version: self.version,
+            },
+            _ => ConnectionState::Disconnected,
+        }
+    }
+
+    /// Connected to the server, idling or not
+    pub fn is_connected(&self) -> bool {
+        self.connection_state() != ConnectionState::Disconnected
+    }
+
+    /// Partition the client is pinned to, None for the default partition
+    pub fn partition(&self) -> Option<&str> {
+        self.partition.as_deref()
+    }
+
+    /// Switch to the partition `name`, and stay in it after reconnecting. Requires Mpd 0.22
+    pub async fn switch_partition(&mut self, name: &str) -> Result<(), Error> {
+        self.exec(cmd::Partition(name)).await?;
+        self.partition = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Protocol version of the connected server
+    pub fn server_version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Stored playlists
+    pub async fn playlists(&mut self) -> Result<Vec<StoredPlaylist>, Error> {
+        self.exec(cmd::ListPlaylists).await
+    }
+
+    /// Audio outputs of the server
+    pub async fn outputs(&mut self) -> Result<Vec<Output>, Error> {
+        self.exec(cmd::Outputs).await
+    }
+
+    /// Set a runtime attribute of the output `id`. Requires Mpd 0.21
+    pub async fn set_output_attribute(
+        &mut self,
+        id: u32,
+        name: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        self.exec(cmd::OutputSet { id, name, value }).await
+    }
+
+    /// Enable or disable DSD over PCM on the ALSA output `id`. Requires Mpd 0.21
+    pub async fn set_dop(&mut self, id: u32, enabled: bool) -> Result<(), Error> {
+        let value = if enabled { "1" } else { "0" };
+        self.set_output_attribute(id, Output::DOP, value).await
+    }
+
+    /// Restrict the audio formats of the ALSA output `id`, allowing all formats if empty.
+    /// Requires Mpd 0.21
+    pub async fn set_allowed_formats(&mut self, id: u32, formats: &[&str]) -> Result<(), Error> {
+        self.set_output_attribute(id, Output::ALLOWED_FORMATS, &formats.join(" "))
+            .await
+    }
+
     /// Get stats on the music database
     pub async fn stats(&mut self) -> Result<Stats, Error> {
         self.exec(cmd::Stats).await
@@ -75,6 +544,29 @@ impl MpdClient {
         Ok(status)
     }
 
+    /// The song playing or paused, None if stopped
+    pub async fn current_song(&mut self) -> Result<Option<Track>, Error> {
+        let songs = self.exec(cmd::CurrentSong).await?;
+        Ok(songs.into_iter().next())
+    }
+
+    /// The status together with the current song, fetched in one command list
+    ///
+    /// Unlike calling [`status`](Self::status) and [`current_song`](Self::current_song) one
+    /// after the other, the song can't change in between, so the elapsed time of the status
+    /// always belongs to the returned song.
+    pub async fn status_and_currentsong(&mut self) -> Result<(Status, Option<Track>), Error> {
+        let (status, songs) = self
+            .exec(cmd::CommandList((cmd::Status, cmd::CurrentSong)))
+            .await?;
+        Ok((status, songs.into_iter().next()))
+    }
+
+    /// Clear the player error reported in [`Status::error`]
+    pub async fn clear_error(&mut self) -> Result<(), Error> {
+        self.exec(cmd::ClearError).await
+    }
+
     pub async fn update(&mut self, path: Option<&str>) -> Result<DatabaseVersion, Error> {
         self.exec(cmd::Update(path)).await
     }
@@ -87,14 +579,50 @@ impl MpdClient {
         self.exec(cmd::Idle).await
     }
 
+    /// Wait until one of `subsystems` changes, or any subsystem if empty, for at most `timeout`
+    ///
+    /// Returns the changed subsystems, or None if nothing changed in time, in which case the
+    /// idle command is cancelled with `noidle`. Useful for doing periodic work in between
+    /// waiting for changes.
+    pub async fn idle_timeout(
+        &mut self,
+        subsystems: &[Subsystem],
+        timeout: Duration,
+    ) -> Result<Option<Vec<Subsystem>>, Error> {
+        let changed = self
+            .idle_until(subsystems, Some(Instant::now() + timeout))
+            .await?;
+
+        Ok(if changed.is_empty() {
+            None
+        } else {
+            Some(changed)
+        })
+    }
+
+    /// Cancel an idle command that was interrupted before the server replied
+    ///
+    /// This is done automatically by [`exec`](Self::exec) before sending a new command, and
+    /// does nothing if the client is not idling.
     pub async fn noidle(&mut self) -> Result<(), Error> {
-        self.exec(cmd::NoIdle).await
+        self.leave_idle().await
     }
 
     pub async fn setvol(&mut self, volume: u32) -> Result<(), Error> {
         self.exec(cmd::Setvol(volume)).await
     }
 
+    /// The volume, without fetching the whole status. Requires Mpd 0.23
+    ///
+    /// Fails when the server doesn't know the volume, such as when it has no mixer.
+    pub async fn getvol(&mut self) -> Result<Volume, Error> {
+        self.exec(cmd::GetVol).await
+    }
+
+    pub async fn replay_gain_status(&mut self) -> Result<ReplayGainMode, Error> {
+        self.exec(cmd::ReplayGainStatus).await
+    }
+
     pub async fn repeat(&mut self, repeat: bool) -> Result<(), Error> {
         self.exec(cmd::Repeat(repeat)).await
     }
@@ -103,8 +631,13 @@ impl MpdClient {
         self.exec(cmd::Random(random)).await
     }
 
-    pub async fn consume(&mut self, consume: bool) -> Result<(), Error> {
-        self.exec(cmd::Consume(consume)).await
+    /// Set the consume mode. Takes a `bool` as well, for turning it on or off
+    pub async fn consume<M: Into<ConsumeMode>>(&mut self, mode: M) -> Result<(), Error> {
+        self.exec(cmd::Consume(mode.into())).await
+    }
+
+    pub async fn single(&mut self, single: SingleMode) -> Result<(), Error> {
+        self.exec(cmd::Single(single)).await
     }
 
     // Playback controls
@@ -113,7 +646,7 @@ impl MpdClient {
         self.play_pause(true).await
     }
 
-    pub async fn playid(&mut self, id: u32) -> Result<(), Error> {
+    pub async fn playid(&mut self, id: SongId) -> Result<(), Error> {
         self.exec(cmd::PlayId(id)).await
     }
 
@@ -125,6 +658,15 @@ impl MpdClient {
         self.exec(cmd::PlayPause(!play)).await
     }
 
+    /// Toggle between playing and paused, starting playback if the player is stopped
+    pub async fn toggle(&mut self) -> Result<(), Error> {
+        match self.status().await?.state {
+            State::Play => self.exec(cmd::PlayPause(true)).await,
+            State::Pause => self.exec(cmd::PlayPause(false)).await,
+            State::Stop => self.exec(cmd::Play(None)).await,
+        }
+    }
+
     pub async fn next(&mut self) -> Result<(), Error> {
         self.exec(cmd::Next).await
     }
@@ -145,10 +687,57 @@ impl MpdClient {
         self.exec(cmd::Listall(path)).await
     }
 
+    /// Chromaprint fingerprint of the song `uri`. Requires Mpd 0.22, built with chromaprint
+    ///
+    /// The server decodes the song to calculate it, which takes a while.
+    pub async fn fingerprint(&mut self, uri: &str) -> Result<Fingerprint, Error> {
+        self.exec(cmd::GetFingerprint(uri)).await
+    }
+
+    /// Recursive listing of `path`, or of the whole database
+    ///
+    /// Listing the whole database of a large library takes the server and the client a long
+    /// time. If a limit was set with [`MpdClientBuilder::listallinfo_limit`], the number of songs
+    /// in the database is checked first, and [`Error::LibraryTooLarge`] returned when there are
+    /// more. [`MpdClient::walk`] lists the database a directory at a time instead.
     pub async fn listallinfo(&mut self, path: Option<&str>) -> Result<ListallinfoResponse, Error> {
+        let whole_db = path.is_none_or(|p| p.trim_matches('/').is_empty());
+
+        if let (true, Some(limit)) = (whole_db, self.listallinfo_limit) {
+            let songs = self.stats().await?.songs;
+            if songs > limit {
+                return Err(Error::LibraryTooLarge { songs, limit });
+            }
+        }
+
         self.exec(cmd::ListallInfo(path)).await
     }
 
+    /// Contents of the directory `path`, or of the root directory
+    pub async fn lsinfo(&mut self, path: Option<&str>) -> Result<ListallinfoResponse, Error> {
+        self.exec(cmd::Lsinfo(path)).await
+    }
+
+    /// Unique values of `tag` for songs matching `filter`
+    pub async fn list(&mut self, tag: Tag, filter: &Filter) -> Result<Vec<String>, Error> {
+        let query = filter.to_query();
+        let pairs = self
+            .exec(cmd::List {
+                tag,
+                filter: query.as_deref(),
+                group: None,
+            })
+            .await?;
+
+        Ok(tag_values(tag, pairs))
+    }
+
+    /// Find songs exactly matching `filter`
+    pub async fn find(&mut self, filter: &Filter) -> Result<Vec<Track>, Error> {
+        self.exec(cmd::Find::new(filter.to_query().as_deref()))
+            .await
+    }
+
     // Queue handling commands
 
     pub async fn queue_add(&mut self, path: &str) -> Result<(), Error> {
@@ -163,8 +752,329 @@ impl MpdClient {
         self.exec(cmd::PlaylistInfo).await
     }
 
+    /// Songs in the queue that changed since queue version `version`, as reported in
+    /// [`Status::playlist`]
+    pub async fn queue_changes(&mut self, version: u32) -> Result<Vec<Track>, Error> {
+        self.exec(cmd::PlChanges(version)).await
+    }
+
+    /// Add a song to the queue, at the end or at `pos`, returning the id of the new song
+    pub async fn queue_add_id(&mut self, uri: &str, pos: Option<SongPos>) -> Result<SongId, Error> {
+        self.exec(cmd::AddId {
+            uri,
+            pos: pos.map(Position::At),
+        })
+        .await
+    }
+
+    /// Add a song to be played right after the current song, returning the id of the new song
+    ///
+    /// Adds the song to the end of the queue when nothing is playing. Servers older than
+    /// Mpd 0.23 don't support positions relative to the current song, for which the position
+    /// is taken from the status.
+    pub async fn play_next(&mut self, uri: &str) -> Result<SongId, Error> {
+        let relative = self.version >= Some(Version::new(0, 23, 0));
+
+        // The relative position is still used when supported, as the current song can change
+        // before the song is added
+        let pos = match self.status().await?.song {
+            Some(_) if relative => Some(Position::AfterCurrent(0)),
+            Some(SongPos(current)) => Some(Position::At(SongPos(current + 1))),
+            None => None,
+        };
+
+        self.exec(cmd::AddId { uri, pos }).await
+    }
+
+    /// The song with the id `id` in the queue, None if there is no such song
+    pub async fn queue_song(&mut self, id: SongId) -> Result<Option<Track>, Error> {
+        match self.exec(cmd::PlaylistId(id)).await {
+            Ok(songs) => Ok(songs.into_iter().next()),
+            Err(e) if is_no_exist(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remove the song with id `id` from the queue
+    pub async fn queue_delete_id(&mut self, id: SongId) -> Result<(), Error> {
+        self.exec(cmd::DeleteId(id)).await
+    }
+
+    /// Seek to `time` in the song with id `id`, and play it
+    pub async fn seek_id(&mut self, id: SongId, time: Duration) -> Result<(), Error> {
+        self.exec(cmd::SeekId { id, time }).await
+    }
+
+    /// Move the song with id `id` to position `to` in the queue
+    pub async fn queue_move_id(&mut self, id: SongId, to: SongPos) -> Result<(), Error> {
+        self.exec(cmd::MoveId { id, to }).await
+    }
+
+    /// The queue, with only the artist and title tags of the songs
+    ///
+    /// The file, position, id and duration are always included. Listing a large queue is a lot
+    /// faster this way, since the server doesn't have to send every tag of every song. Servers
+    /// older than Mpd 0.21 can't limit the tags, and send all of them.
+    ///
+    /// The tags of the tag profile, if any, are sent again afterwards.
+    pub async fn queue_compact(&mut self) -> Result<Vec<Track>, Error> {
+        if self.version < Some(Version::new(0, 21, 0)) {
+            return self.queue().await;
+        }
+
+        let queue = self
+            .exec(cmd::CommandList((
+                cmd::TagTypes::Clear,
+                cmd::TagTypes::Enable(&[Tag::Artist, Tag::Title]),
+                cmd::PlaylistInfo,
+            )))
+            .await
+            .map(|(_, _, queue)| queue);
+
+        // The tag types may have been changed even if the command list failed
+        let reset = self.reset_tag_types().await;
+        let queue = queue?;
+        reset?;
+        Ok(queue)
+    }
+
+    // Stored playlists
+
+    /// Get the songs of a stored playlist
+    pub async fn listplaylistinfo(&mut self, name: &str) -> Result<Vec<Track>, Error> {
+        self.exec(cmd::ListPlaylistInfo(name)).await
+    }
+
+    /// Add a song to a stored playlist, creating the playlist if it doesn't exist
+    pub async fn playlist_add(&mut self, name: &str, uri: &str) -> Result<(), Error> {
+        self.exec(cmd::PlaylistAdd { name, uri }).await
+    }
+
+    /// Replace the queue with a stored playlist and start playing it
+    ///
+    /// The queue is cleared, the playlist loaded and playback started in one command list, so
+    /// other clients never see a half built queue. If the playlist doesn't exist, the queue is
+    /// left cleared.
+    pub async fn play_playlist(&mut self, name: &str) -> Result<(), Error> {
+        self.exec(cmd::CommandList((
+            cmd::QueueClear,
+            cmd::Load::new(name),
+            cmd::Play(None),
+        )))
+        .await?;
+        Ok(())
+    }
+
+    /// A stored playlist as extended M3U8
+    pub async fn export_playlist(&mut self, name: &str) -> Result<String, Error> {
+        let tracks = self.listplaylistinfo(name).await?;
+        Ok(crate::m3u::to_m3u(&tracks))
+    }
+
+    /// Add the songs of an M3U or M3U8 playlist to the stored playlist `name`
+    ///
+    /// The uris must be known by the server, as paths relative to the music directory or as
+    /// urls of streams. The songs are added in command lists of up to 256 songs. If a song can't
+    /// be added, the songs before it stay in the playlist, and the error is of the `playlistadd`
+    /// of that song.
+    pub async fn import_m3u(&mut self, name: &str, m3u: &str) -> Result<(), Error> {
+        let uris = crate::m3u::parse_m3u(m3u);
+
+        for batch in uris.chunks(PLAYLIST_BATCH) {
+            let adds: Vec<_> = batch
+                .iter()
+                .map(|uri| cmd::PlaylistAdd { name, uri })
+                .collect();
+
+            if let Err(e) = self.exec(cmd::CommandList(adds.clone())).await {
+                // Report the song that failed rather than the whole command list
+                let failed = e.ack().and_then(|ack| adds.get(ack.list_index as usize));
+                return Err(match failed {
+                    Some(add) => e
+                        .into_root()
+                        .in_command(cmd::PlaylistAdd::CMD, &add.to_cmdline()),
+                    None => e,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Search a stored playlist for songs matching `filter`. Requires Mpd 0.24
+    ///
+    /// An empty filter returns all songs in the playlist.
+    pub async fn search_playlist(
+        &mut self,
+        name: &str,
+        filter: &Filter,
+    ) -> Result<Vec<Track>, Error> {
+        match filter.to_query() {
+            Some(query) => {
+                self.exec(cmd::SearchPlaylist {
+                    name,
+                    filter: &query,
+                    window: None,
+                })
+                .await
+            }
+            None => self.listplaylistinfo(name).await,
+        }
+    }
+
+    //
+    // Stickers
+    //
+
+    /// Get the value of the sticker `name` of a song
+    pub async fn sticker_get(&mut self, uri: &str, name: &str) -> Result<String, Error> {
+        self.exec(cmd::StickerGet { uri, name })
+            .await
+            .map(|sticker| sticker.value)
+    }
+
+    pub async fn sticker_set(&mut self, uri: &str, name: &str, value: &str) -> Result<(), Error> {
+        self.exec(cmd::StickerSet { uri, name, value }).await
+    }
+
+    /// Delete the sticker `name` of a song, or all of its stickers if `name` is None
+    pub async fn sticker_delete(&mut self, uri: &str, name: Option<&str>) -> Result<(), Error> {
+        self.exec(cmd::StickerDelete { uri, name }).await
+    }
+
+    pub async fn sticker_list(&mut self, uri: &str) -> Result<Vec<Sticker>, Error> {
+        self.exec(cmd::StickerList(uri)).await
+    }
+
+    /// The sticker `name` of each of `uris`, keyed by uri. Songs without the sticker are left
+    /// out
+    ///
+    /// The stickers of the songs are listed in command lists of up to 256 songs, so stickers
+    /// for a whole queue take a few round trips instead of one per song. Fails if one of the
+    /// songs doesn't exist.
+    pub async fn stickers_for<S: AsRef<str>>(
+        &mut self,
+        uris: &[S],
+        name: &str,
+    ) -> Result<HashMap<String, String>, Error> {
+        let mut values = HashMap::new();
+
+        for batch in uris.chunks(STICKER_BATCH) {
+            let lists: Vec<_> = batch
+                .iter()
+                .map(|uri| cmd::StickerList(uri.as_ref()))
+                .collect();
+
+            let stickers = self.exec(cmd::CommandList(lists)).await?;
+
+            for (uri, stickers) in batch.iter().zip(stickers) {
+                if let Some(sticker) = stickers.into_iter().find(|s| s.name == name) {
+                    values.insert(uri.as_ref().to_string(), sticker.value);
+                }
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Find songs below `uri` that have the sticker `name`
+    ///
+    /// See [`cmd::StickerFind`] for filtering on the value, sorting and windowing
+    pub async fn sticker_find(
+        &mut self,
+        uri: &str,
+        name: &str,
+    ) -> Result<Vec<StickerMatch>, Error> {
+        self.exec(cmd::StickerFind::new(uri, name)).await
+    }
+
+    /// Cover of the album of the song `uri`, None if there is none. Requires Mpd 0.21
+    ///
+    /// The cover is the `cover.png`, `cover.jpg` or similar file in the directory of the song.
+    pub async fn albumart(&mut self, uri: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut data = Vec::new();
+        let written = self.albumart_to(uri, &mut data).await?;
+        Ok(written.map(|_| data))
+    }
+
+    /// Write the cover of the album of the song `uri` to `writer`. Returns the size of the
+    /// cover, or None if there is none. Requires Mpd 0.21
+    ///
+    /// The cover is written a chunk at a time as it arrives, without keeping all of it in
+    /// memory. The size of the chunks is set with [`binary_limit`](Self::binary_limit).
+    pub async fn albumart_to<W>(&mut self, uri: &str, mut writer: W) -> Result<Option<usize>, Error>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut offset = 0;
+
+        loop {
+            let chunk = match self.exec(cmd::AlbumArt { uri, offset }).await {
+                Ok(chunk) => chunk,
+                Err(e) if is_no_exist(&e) => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+            if chunk.data.is_empty() {
+                break;
+            }
+
+            writer.write_all(&chunk.data).await?;
+            offset += chunk.data.len();
+
+            if offset >= chunk.size {
+                break;
+            }
+        }
+
+        writer.flush().await?;
+        Ok(Some(offset))
+    }
+
+    /// Set the maximum size of the chunks of binary responses, such as covers. Requires
+    /// Mpd 0.22.4
+    pub async fn binary_limit(&mut self, size: u32) -> Result<(), Error> {
+        self.exec(cmd::BinaryLimit(size)).await
+    }
+
+    /// Get the rating of a song, stored in the `rating` sticker
+    pub async fn rating(&mut self, uri: &str) -> Result<Option<u8>, Error> {
+        match self.sticker_get(uri, RATING_STICKER).await {
+            Ok(value) => Ok(Some(value.parse()?)),
+            Err(e) if is_no_exist(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set the rating of a song, in the range 1 to 5
+    pub async fn set_rating(&mut self, uri: &str, rating: u8) -> Result<(), Error> {
+        if !(1..=5).contains(&rating) {
+            return Err(Error::ValueError {
+                msg: format!("rating out of range: {}", rating),
+            });
+        }
+
+        self.sticker_set(uri, RATING_STICKER, &rating.to_string())
+            .await
+    }
+
+    /// Increment the play count of a song, stored in the `playcount` sticker. Returns the new
+    /// play count, which stays at `u32::MAX` once reached
+    pub async fn increment_playcount(&mut self, uri: &str) -> Result<u32, Error> {
+        let count: u32 = match self.sticker_get(uri, PLAYCOUNT_STICKER).await {
+            Ok(value) => value.parse()?,
+            Err(e) if is_no_exist(&e) => 0,
+            Err(e) => return Err(e),
+        };
+
+        let count = count.saturating_add(1);
+        self.sticker_set(uri, PLAYCOUNT_STICKER, &count.to_string())
+            .await?;
+        Ok(count)
+    }
+
     /// # Example
-    /// ```
+    /// ```no_run
     /// use async_mpd::{MpdClient, Error, Tag, Filter, ToFilterExpr};
     ///
     /// #[async_std::main]
@@ -184,13 +1094,15 @@ impl MpdClient {
     /// }
     /// ```
     pub async fn search(&mut self, filter: &Filter) -> Result<Vec<Track>, Error> {
-        self.exec(cmd::Search(filter.to_query().as_deref())).await
+        self.exec(cmd::Search::new(filter.to_query().as_deref()))
+            .await
     }
 
     /// Execute a Mpd Command. Returns a enum wrapped Response
     pub async fn exec_wrapped<C>(&mut self, cmd: C) -> Result<WrappedResponse, crate::Error>
     where
         C: MpdCmd,
+        <C::Handler as ResponseHandler>::Response: Into<WrappedResponse>,
     {
         self.exec(cmd).await.map(Into::into)
     }
@@ -204,25 +1116,436 @@ impl MpdClient {
         C: MpdCmd,
     {
         let cmdline = cmd.to_cmdline();
+        log::debug!("command: {}", cmd.redacted_cmdline().trim_end());
+
+        // Nothing is sent for a command that would be split into several
+        cmd::check_line_breaks(&cmdline)
+            .map_err(|e| e.in_command(C::CMD, &cmd.redacted_cmdline()))?;
+
+        if let (true, Some(server)) = (self.check_versions, self.version) {
+            let required = cmd.min_version();
+            if server < required {
+                return Err(Error::Unsupported {
+                    cmd: C::CMD,
+                    required,
+                    server,
+                });
+            }
+        }
+
+        // An idle command can be left waiting if its future was dropped
+        self.leave_idle().await?;
+        self.throttle().await;
 
+        let start = Instant::now();
         self.send_command(&cmdline).await?;
+        self.idle = C::CMD == crate::cmd::Idle::CMD;
+
+        // Handle the response associated with this command
+        let mut reader = self.reader()?;
+        let resp = C::Handler::handle(&mut reader).await;
+        let bytes = reader.bytes;
+
+        if !self.idle {
+            self.check_slow(C::CMD, start.elapsed(), bytes);
+        }
+        self.idle = false;
+        self.check_connection(resp)
+            .map_err(|e| e.in_command(C::CMD, &cmd.redacted_cmdline()))
+    }
 
+    /// Log a warning if the command took longer than the slow command threshold
+    fn check_slow(&self, cmd: &'static str, duration: Duration, bytes: usize) {
+        match self.slow_command_threshold {
+            Some(threshold) if duration > threshold => {
+                let duration_ms = duration.as_millis() as u64;
+                log::warn!(
+                    cmd,
+                    duration_ms,
+                    bytes;
+                    "Slow command `{}`: {} ms, {} bytes", cmd, duration_ms, bytes
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// Idle until one of `subsystems` changes, any subsystem if empty, or until `deadline` has
+    /// passed
+    ///
+    /// Returns all subsystems reported by the server, which is empty if the deadline passed
+    /// without changes.
+    pub(crate) async fn idle_until(
+        &mut self,
+        subsystems: &[Subsystem],
+        deadline: Option<Instant>,
+    ) -> Result<Vec<Subsystem>, Error> {
+        self.leave_idle().await?;
+        self.send_command(&cmd::IdleFor(subsystems).to_cmdline())
+            .await?;
+        self.idle = true;
+
+        if let Some(deadline) = deadline {
+            let br = self.stream.as_mut().ok_or(Error::Disconnected)?;
+
+            // Waiting for the response to be buffered doesn't consume it, so this wait is safe
+            // to abandon when the deadline passes
+            let changed = future::or(async { br.fill_buf().await.map(|_| true) }, async {
+                sleep_until(deadline).await;
+                Ok(false)
+            })
+            .await
+            .map_err(Error::from);
+
+            if !self.check_connection(changed)? {
+                self.send_command(&cmd::NoIdle.to_cmdline()).await?;
+            }
+        }
+
+        self.finish_idle().await
+    }
+
+    /// Send an idle command, unless one is already waiting
+    pub(crate) async fn enter_idle(&mut self) -> Result<(), Error> {
+        if !self.idle {
+            self.send_command(&cmd::Idle.to_cmdline()).await?;
+            self.idle = true;
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the response of the waiting idle command, without reading it
+    ///
+    /// Waiting for the response to be buffered doesn't consume it, so this wait is safe to
+    /// abandon.
+    pub(crate) async fn idle_ready(&mut self) -> Result<(), Error> {
         let br = self.stream.as_mut().ok_or(Error::Disconnected)?;
+        let ready = br.fill_buf().await.map(|_| ()).map_err(Error::from);
+        self.check_connection(ready)
+    }
 
-        // Handle the response associated with this command
-        C::Handler::handle(br).await
+    /// Read the response of the waiting idle command
+    pub(crate) async fn finish_idle(&mut self) -> Result<Vec<Subsystem>, Error> {
+        let changed = ListResponse::<Subsystem>::handle(&mut self.reader()?).await;
+        self.idle = false;
+        self.check_connection(changed)
+    }
+
+    /// Drop the connection if `res` shows that it was lost
+    fn check_connection<T>(&mut self, res: Result<T, Error>) -> Result<T, Error> {
+        if let Err(e @ (Error::Disconnected | Error::IOError(_))) = &res {
+            log::debug!("Connection lost: {}", e);
+
+            if self.stream.take().is_some() {
+                self.emit(ConnectionEvent::Disconnected {
+                    reason: e.to_string(),
+                });
+            }
+
+            self.idle = false;
+            self.connected_since = None;
+        }
+
+        res
+    }
+
+    async fn leave_idle(&mut self) -> Result<(), Error> {
+        if !self.idle {
+            return Ok(());
+        }
+
+        log::debug!("Leaving idle");
+        self.send_command(&cmd::NoIdle.to_cmdline()).await?;
+
+        // Drain the response of the interrupted idle. If the server already sent it, the
+        // noidle is ignored by the server.
+        let drained = read_pairs(&mut self.reader()?).await;
+        self.check_connection(drained)?;
+
+        self.idle = false;
+        Ok(())
     }
 
     async fn send_command(&mut self, line: &str) -> Result<(), crate::Error> {
+        if let Some(tap) = &self.wire_tap {
+            tap.sent(line);
+        }
+
         // Get the underlying TcpStream and write command to the socket
-        self.stream
+        let sent = self
+            .stream
             .as_mut()
             .ok_or(crate::Error::Disconnected)?
             .get_mut()
             .write_all(line.as_bytes())
             .await
-            .map_err(|_| crate::Error::Disconnected)?;
+            .map_err(|_| crate::Error::Disconnected);
 
-        Ok(())
+        // Data written to a TLS stream may be buffered until flushed
+        let sent = match (sent, self.stream.as_mut()) {
+            (Ok(()), Some(stream)) => stream
+                .get_mut()
+                .flush()
+                .await
+                .map_err(|_| crate::Error::Disconnected),
+            (sent, _) => sent,
+        };
+
+        self.check_connection(sent)
+    }
+}
+
+impl Drop for MpdClient {
+    fn drop(&mut self) {
+        if let Some(stream) = &self.stream {
+            let _ = stream.get_ref().shutdown();
+        }
+    }
+}
+
+/// The server responds with ACK error 50 when getting a sticker or file that doesn't exist
+pub(crate) fn is_no_exist(e: &Error) -> bool {
+    e.ack().is_some_and(|ack| ack.code == AckCode::NoExist)
+}
+
+/// Values of `tag` in the response of `list`
+pub(crate) fn tag_values(tag: Tag, pairs: Vec<(String, String)>) -> Vec<String> {
+    let name = tag.to_string();
+
+    pairs
+        .into_iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case(&name))
+        .map(|(_, v)| v)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::STICKER_BATCH;
+    use crate::client::mock::{run, MockServer};
+    use crate::{AckCode, ErrorKind, MpdClient, State, Subsystem};
+    use std::cell::RefCell;
+    use std::sync::Once;
+    use std::time::Duration;
+
+    thread_local! {
+        /// The `cmd` of the warnings logged by the current thread
+        static WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Logger keeping the warnings, per thread as the tests run in parallel
+    struct WarningLogger;
+
+    impl log::Log for WarningLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                let cmd = record.key_values().get("cmd".into());
+                let cmd = cmd.map(|cmd| cmd.to_string()).unwrap_or_default();
+                WARNINGS.with(|warnings| warnings.borrow_mut().push(cmd));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn capture_warnings() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&WarningLogger).unwrap();
+            log::set_max_level(log::LevelFilter::Warn);
+        });
+    }
+
+    #[test]
+    fn queue_compact() {
+        const LIST: &[&str] = &[
+            "command_list_ok_begin",
+            "tagtypes \"clear\"",
+            "tagtypes \"enable\" \"Artist\" \"Title\"",
+            "playlistinfo",
+            "command_list_end",
+        ];
+
+        let server = MockServer::new(&[
+            (
+                LIST,
+                "list_OK\nlist_OK\nfile: a.flac\nArtist: A\nPos: 0\nId: 1\nlist_OK\nOK\n",
+            ),
+            (&["tagtypes \"all\""], "OK\n"),
+            (LIST, "list_OK\nlist_OK\nACK [5@2] {playlistinfo} boom\n"),
+            // The tag types are reset even though the list failed, and the error of the list is
+            // returned if resetting fails too
+            (
+                &["tagtypes \"all\""],
+                "ACK [4@0] {tagtypes} you don't have permission\n",
+            ),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let queue = client.queue_compact().await.unwrap();
+            assert_eq!(queue[0].file, "a.flac");
+
+            let err = client.queue_compact().await.unwrap_err();
+            assert_eq!(err.ack().unwrap().command, "playlistinfo");
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn import_m3u() {
+        let server = MockServer::new(&[(
+            &[
+                "command_list_ok_begin",
+                "playlistadd \"p\" \"a.flac\"",
+                "playlistadd \"p\" \"b.flac\"",
+                "playlistadd \"p\" \"c.flac\"",
+                "command_list_end",
+            ],
+            "list_OK\nACK [50@1] {playlistadd} No such song\n",
+        )]);
+
+        let err = run(async {
+            let mut client = server.client().await;
+            client
+                .import_m3u("p", "#EXTM3U\na.flac\nb.flac\nc.flac\n")
+                .await
+                .unwrap_err()
+        });
+        server.finish();
+
+        assert_eq!(err.ack().unwrap().code, AckCode::NoExist);
+        assert!(err.to_string().contains("b.flac"), "{}", err);
+        assert!(!err.to_string().contains("c.flac"), "{}", err);
+    }
+
+    #[test]
+    fn increment_playcount() {
+        let server = MockServer::new(&[
+            (
+                &[r#"sticker "get" "song" "a.flac" "playcount""#],
+                "sticker: playcount=4294967295\nOK\n",
+            ),
+            (
+                &[r#"sticker "set" "song" "a.flac" "playcount" "4294967295""#],
+                "OK\n",
+            ),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+            assert_eq!(
+                client.increment_playcount("a.flac").await.unwrap(),
+                u32::MAX
+            );
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn stickers_for() {
+        let uris: Vec<String> = (0..=STICKER_BATCH).map(|i| format!("{}.flac", i)).collect();
+
+        // Every other song is rated, the others have another sticker or none
+        let sticker = |i: usize| match i % 4 {
+            0 | 2 => format!("sticker: rating={}\n", i),
+            1 => "sticker: playcount=1\n".to_string(),
+            _ => String::new(),
+        };
+
+        let batches: Vec<(Vec<String>, String)> = uris
+            .chunks(STICKER_BATCH)
+            .enumerate()
+            .map(|(n, batch)| {
+                let mut lines = vec!["command_list_ok_begin".to_string()];
+                let mut resp = String::new();
+                for (i, uri) in batch.iter().enumerate() {
+                    lines.push(format!("sticker \"list\" \"song\" \"{}\"", uri));
+                    resp += &sticker(n * STICKER_BATCH + i);
+                    resp += "list_OK\n";
+                }
+                lines.push("command_list_end".to_string());
+                (lines, resp + "OK\n")
+            })
+            .collect();
+        let lines: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|(lines, _)| lines.iter().map(String::as_str).collect())
+            .collect();
+        let script: Vec<(&[&str], &str)> = lines
+            .iter()
+            .zip(&batches)
+            .map(|(lines, (_, resp))| (lines.as_slice(), resp.as_str()))
+            .collect();
+        assert_eq!(script.len(), 2);
+
+        let server = MockServer::new(&script);
+
+        let ratings = run(async {
+            let mut client = server.client().await;
+            client.stickers_for(&uris, "rating").await.unwrap()
+        });
+        server.finish();
+
+        assert_eq!(ratings.len(), STICKER_BATCH / 2 + 1);
+        assert_eq!(ratings["0.flac"], "0");
+        assert_eq!(
+            ratings[&format!("{}.flac", STICKER_BATCH)],
+            STICKER_BATCH.to_string()
+        );
+        assert!(!ratings.contains_key("1.flac"));
+        assert!(!ratings.contains_key("3.flac"));
+    }
+
+    #[test]
+    fn slow_commands() {
+        capture_warnings();
+
+        let server = MockServer::new(&[
+            (&["status"], "state: stop\nOK\n"),
+            (&["idle"], "changed: player\nOK\n"),
+        ]);
+
+        run(async {
+            let mut client = MpdClient::builder()
+                .slow_command_threshold(Duration::ZERO)
+                .build();
+            client.connect(server.addr).await.unwrap();
+
+            client.status().await.unwrap();
+            // Waiting in idle isn't slow
+            assert_eq!(client.idle().await.unwrap(), Subsystem::Player);
+        });
+        server.finish();
+
+        WARNINGS.with(|warnings| assert_eq!(*warnings.borrow(), ["status"]));
+    }
+
+    #[test]
+    fn line_break_not_sent() {
+        let server = MockServer::new(&[(&["status"], "state: stop\nOK\n")]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let err = client.queue_add("a.flac\nclear").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Usage);
+            assert_eq!(
+                err.root().to_string(),
+                "Invalid argument: argument contains a line break"
+            );
+            // The connection is still in sync
+            assert_eq!(client.status().await.unwrap().state, State::Stop);
+        });
+
+        server.finish();
     }
 }
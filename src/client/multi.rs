@@ -0,0 +1,172 @@
+use futures_lite::future;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Poll;
+
+use crate::{
+    client::resp::handlers::ResponseHandler, cmd::MpdCmd, Error, MpdClient, Subsystem,
+    ToSocketAddrs,
+};
+
+type IdleFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+/// Named connections to several servers, such as the players of a multi-room setup
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, MpdMultiClient};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut servers = MpdMultiClient::new();
+///     servers.connect("kitchen", "kitchen.local:6600").await?;
+///     servers.connect("living room", "livingroom.local:6600").await?;
+///
+///     servers.pause_all().await?;
+///
+///     while let Some((name, changed)) = servers.idle().await {
+///         println!("{}: {:?}", name, changed?);
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct MpdMultiClient {
+    clients: BTreeMap<String, MpdClient>,
+}
+
+impl MpdMultiClient {
+    /// No servers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to the server at `addr`, replacing any server with the same name
+    pub async fn connect<A: ToSocketAddrs>(
+        &mut self,
+        name: &str,
+        addr: A,
+    ) -> Result<String, Error> {
+        let mut client = MpdClient::new();
+        let version = client.connect(addr).await?;
+        self.insert(name, client);
+        Ok(version)
+    }
+
+    /// Add a client, replacing any client with the same name
+    pub fn insert(&mut self, name: &str, client: MpdClient) -> Option<MpdClient> {
+        self.clients.insert(name.to_string(), client)
+    }
+
+    /// Remove the client with the given name
+    pub fn remove(&mut self, name: &str) -> Option<MpdClient> {
+        self.clients.remove(name)
+    }
+
+    /// The client with the given name
+    pub fn get(&mut self, name: &str) -> Option<&mut MpdClient> {
+        self.clients.get_mut(name)
+    }
+
+    /// Names of the servers, in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Execute a command on every connected server, one after the other
+    ///
+    /// Returns the result from each server, by name.
+    pub async fn exec_all<C>(
+        &mut self,
+        cmd: C,
+    ) -> Vec<(
+        String,
+        Result<<C::Handler as ResponseHandler>::Response, Error>,
+    )>
+    where
+        C: MpdCmd + Clone,
+    {
+        let mut results = Vec::new();
+
+        for (name, client) in self.connected() {
+            results.push((name.clone(), client.exec(cmd.clone()).await));
+        }
+
+        results
+    }
+
+    /// Pause playback on every connected server
+    ///
+    /// All servers are tried, and the first error is returned.
+    pub async fn pause_all(&mut self) -> Result<(), Error> {
+        let mut res = Ok(());
+
+        for (_, client) in self.connected() {
+            let paused = client.pause().await;
+            res = res.and(paused);
+        }
+
+        res
+    }
+
+    /// Wait until a subsystem changes on any of the connected servers
+    ///
+    /// Returns the name of the server with the changed subsystems, or None if no server is
+    /// connected. The other servers are left idling, and their changes are returned by later
+    /// calls.
+    pub async fn idle(&mut self) -> Option<(String, Result<Vec<Subsystem>, Error>)> {
+        let mut clients: Vec<_> = self.connected().collect();
+
+        if clients.is_empty() {
+            return None;
+        }
+
+        for (name, client) in clients.iter_mut() {
+            if let Err(e) = client.enter_idle().await {
+                return Some((name.to_string(), Err(e)));
+            }
+        }
+
+        let (ready, res) = {
+            let mut waiting: Vec<IdleFuture<'_>> = clients
+                .iter_mut()
+                .map(|(_, client)| Box::pin(client.idle_ready()) as IdleFuture<'_>)
+                .collect();
+
+            future::poll_fn(|cx| {
+                for (i, fut) in waiting.iter_mut().enumerate() {
+                    if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                        return Poll::Ready((i, res));
+                    }
+                }
+
+                Poll::Pending
+            })
+            .await
+        };
+
+        let (name, client) = &mut clients[ready];
+        let changed = match res {
+            Ok(()) => client.finish_idle().await,
+            Err(e) => Err(e),
+        };
+
+        Some((name.to_string(), changed))
+    }
+
+    fn connected(&mut self) -> impl Iterator<Item = (&String, &mut MpdClient)> {
+        self.clients
+            .iter_mut()
+            .filter(|(_, client)| client.is_connected())
+    }
+}
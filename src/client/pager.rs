@@ -0,0 +1,217 @@
+use crate::{cmd, Error, Filter, MpdClient, Sort, Track};
+
+/// Paged search results
+///
+/// Fetches the songs matching a filter a page at a time, using the `window` parameter of
+/// `search`, so that large result sets don't have to be transferred at once.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, Filter, MpdClient, SearchPager, Tag, ToFilterExpr};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let filter = Filter::new().and(Tag::Artist.contains("abba"));
+///     let mut pager = SearchPager::new(&filter, 50).sort(Tag::Title);
+///
+///     loop {
+///         let tracks = pager.next_page(&mut mpd).await?;
+///         if tracks.is_empty() {
+///             break;
+///         }
+///         println!("page {:?}: {} tracks", pager.current_page(), tracks.len());
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SearchPager {
+    query: Option<String>,
+    sort: Option<Sort>,
+    page_size: u32,
+    /// Index of the last fetched page
+    page: Option<u32>,
+    /// Set when a page shorter than `page_size` has been fetched
+    last_page: Option<u32>,
+}
+
+impl SearchPager {
+    /// Pager over the songs matching `filter`, `page_size` songs at a time
+    pub fn new(filter: &Filter, page_size: u32) -> Self {
+        Self {
+            query: filter.to_query(),
+            sort: None,
+            page_size: page_size.max(1),
+            page: None,
+            last_page: None,
+        }
+    }
+
+    /// Sort the results by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    /// Index of the last fetched page, starting at zero
+    pub fn current_page(&self) -> Option<u32> {
+        self.page
+    }
+
+    /// Fetch the page after the current one
+    ///
+    /// Returns an empty list, and stays on the current page, when there are no more results.
+    /// Fails with [`Error::InvalidArgument`] if the filter is empty.
+    pub async fn next_page(&mut self, client: &mut MpdClient) -> Result<Vec<Track>, Error> {
+        let next = match self.page {
+            Some(page) if Some(page) == self.last_page => return Ok(Vec::new()),
+            Some(page) => page + 1,
+            None => 0,
+        };
+
+        let tracks = self.fetch(client, next).await?;
+
+        if tracks.is_empty() && next > 0 {
+            self.last_page = self.page;
+            return Ok(tracks);
+        }
+
+        if (tracks.len() as u32) < self.page_size {
+            self.last_page = Some(next);
+        }
+
+        self.page = Some(next);
+        Ok(tracks)
+    }
+
+    /// Fetch the page before the current one
+    ///
+    /// Returns an empty list, and stays on the current page, when on the first page.
+    pub async fn prev_page(&mut self, client: &mut MpdClient) -> Result<Vec<Track>, Error> {
+        let prev = match self.page {
+            Some(page) if page > 0 => page - 1,
+            _ => return Ok(Vec::new()),
+        };
+
+        let tracks = self.fetch(client, prev).await?;
+        self.page = Some(prev);
+        Ok(tracks)
+    }
+
+    async fn fetch(&self, client: &mut MpdClient, page: u32) -> Result<Vec<Track>, Error> {
+        let start = page.saturating_mul(self.page_size);
+        let end = start.saturating_add(self.page_size);
+
+        // The server requires a filter for searches
+        let query = self
+            .query
+            .as_deref()
+            .ok_or_else(|| Error::InvalidArgument {
+                msg: "search with an empty filter".into(),
+            })?;
+
+        let search = cmd::Search {
+            sort: self.sort,
+            ..cmd::Search::new(Some(query)).window(start..end)
+        };
+
+        client.exec(search).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SearchPager;
+    use crate::client::mock::{run, MockServer};
+    use crate::{ErrorKind, Filter, Tag, ToFilterExpr};
+
+    #[test]
+    fn pages() {
+        let server = MockServer::new(&[
+            (
+                &[r#"search "((Artist == \"abba\"))" "window" "0:2""#],
+                "file: a.flac\nfile: b.flac\nOK\n",
+            ),
+            (
+                &[r#"search "((Artist == \"abba\"))" "window" "2:4""#],
+                "file: c.flac\nOK\n",
+            ),
+            (
+                &[r#"search "((Artist == \"abba\"))" "window" "0:2""#],
+                "file: a.flac\nfile: b.flac\nOK\n",
+            ),
+        ]);
+
+        let mut pager = SearchPager::new(&Filter::with(Tag::Artist.equals("abba")), 2);
+
+        run(async {
+            let mut client = server.client().await;
+
+            assert_eq!(pager.next_page(&mut client).await.unwrap().len(), 2);
+            assert_eq!(pager.current_page(), Some(0));
+            assert_eq!(
+                pager.next_page(&mut client).await.unwrap()[0].file,
+                "c.flac"
+            );
+            assert_eq!(pager.current_page(), Some(1));
+
+            // The short page was the last one, nothing more is fetched
+            assert!(pager.next_page(&mut client).await.unwrap().is_empty());
+            assert_eq!(pager.current_page(), Some(1));
+
+            assert_eq!(pager.prev_page(&mut client).await.unwrap().len(), 2);
+            assert_eq!(pager.current_page(), Some(0));
+            assert!(pager.prev_page(&mut client).await.unwrap().is_empty());
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn full_last_page() {
+        let server = MockServer::new(&[
+            (
+                &[r#"search "((Artist == \"abba\"))" "window" "0:2""#],
+                "file: a.flac\nfile: b.flac\nOK\n",
+            ),
+            (
+                &[r#"search "((Artist == \"abba\"))" "window" "2:4""#],
+                "OK\n",
+            ),
+        ]);
+
+        let mut pager = SearchPager::new(&Filter::with(Tag::Artist.equals("abba")), 2);
+
+        run(async {
+            let mut client = server.client().await;
+
+            assert_eq!(pager.next_page(&mut client).await.unwrap().len(), 2);
+            // The empty page isn't counted
+            assert!(pager.next_page(&mut client).await.unwrap().is_empty());
+            assert_eq!(pager.current_page(), Some(0));
+            assert!(pager.next_page(&mut client).await.unwrap().is_empty());
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn empty_filter() {
+        let server = MockServer::new(&[]);
+        let mut pager = SearchPager::new(&Filter::new(), 2);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let err = pager.next_page(&mut client).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Usage);
+            assert_eq!(pager.current_page(), None);
+        });
+
+        server.finish();
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use crate::{ArtCache, Error, MpdClient, SongId, Subsystem, Track};
+
+/// The next song with its cover, fetched ahead of time
+#[derive(Clone, Debug)]
+struct Prefetched {
+    id: SongId,
+    track: Track,
+    cover: Option<Arc<Vec<u8>>>,
+}
+
+/// Fetches the tags and the cover of the song that plays next, before it starts playing
+///
+/// Follows the `nextsongid` of the status, and fetches the song with `playlistid` and its cover
+/// into an [`ArtCache`], so that a player window can switch to the next song without waiting
+/// for the cover to load. Feed the idle events to [`on_event`](Prefetcher::on_event) to follow
+/// the changes of the next song.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{ArtCache, Error, MpdClient, Prefetcher};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let covers = ArtCache::new();
+///     let mut prefetcher = Prefetcher::new(covers.clone());
+///     prefetcher.refresh(&mut mpd).await?;
+///
+///     loop {
+///         let subsystem = mpd.idle().await?;
+///         if prefetcher.on_event(&mut mpd, subsystem).await? {
+///             println!("Up next: {:?}", prefetcher.next_track().map(|t| &t.file));
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Prefetcher {
+    covers: ArtCache,
+    next: Option<Prefetched>,
+}
+
+impl Prefetcher {
+    /// Prefetch covers into `covers`, which is usually shared with the code showing them
+    pub fn new(covers: ArtCache) -> Self {
+        Self { covers, next: None }
+    }
+
+    /// Fetch the next song if it changed since the last fetch. Returns true if fetched
+    ///
+    /// Tags left out by the tag profile of the client aren't fetched.
+    pub async fn refresh(&mut self, client: &mut MpdClient) -> Result<bool, Error> {
+        let id = client.status().await?.nextsongid;
+
+        if self.next.as_ref().map(|next| next.id) == id {
+            return Ok(false);
+        }
+
+        self.next = None;
+
+        // The song can be removed from the queue between the two commands
+        let track = match id {
+            Some(id) => client.queue_song(id).await?.map(|track| (id, track)),
+            None => None,
+        };
+
+        if let Some((id, track)) = track {
+            let cover = self.covers.get(client, &track).await?;
+            self.next = Some(Prefetched { id, track, cover });
+        }
+
+        Ok(true)
+    }
+
+    /// Handle an idle event, fetching the next song when the player, the queue or the playback
+    /// options changed. Returns true if fetched
+    pub async fn on_event(
+        &mut self,
+        client: &mut MpdClient,
+        subsystem: Subsystem,
+    ) -> Result<bool, Error> {
+        match subsystem {
+            Subsystem::Player | Subsystem::Playlist | Subsystem::Options => {
+                self.refresh(client).await
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// The song that plays next, None if there is none or it wasn't fetched yet
+    pub fn next_track(&self) -> Option<&Track> {
+        self.next.as_ref().map(|next| &next.track)
+    }
+
+    /// Cover of the song that plays next, None if it has no cover
+    pub fn next_cover(&self) -> Option<Arc<Vec<u8>>> {
+        self.next.as_ref().and_then(|next| next.cover.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Prefetcher;
+    use crate::client::mock::{run, MockServer};
+    use crate::{ArtCache, Subsystem};
+
+    #[test]
+    fn next_song() {
+        let server = MockServer::new(&[
+            (&["status"], "state: play\nnextsongid: 5\nOK\n"),
+            (
+                &["playlistid \"5\""],
+                "file: b.flac\nAlbum: B\nPos: 1\nId: 5\nOK\n",
+            ),
+            (
+                &["albumart \"b.flac\" \"0\""],
+                "size: 3\nbinary: 3\njpg\nOK\n",
+            ),
+            // The next song is the same
+            (&["status"], "state: play\nnextsongid: 5\nOK\n"),
+            // The last song is playing
+            (&["status"], "state: play\nOK\n"),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let covers = ArtCache::new();
+            let mut prefetcher = Prefetcher::new(covers.clone());
+
+            assert!(prefetcher.refresh(&mut client).await.unwrap());
+            assert_eq!(prefetcher.next_track().unwrap().file, "b.flac");
+            assert_eq!(prefetcher.next_cover().unwrap().as_slice(), b"jpg");
+            assert_eq!(covers.len(), 1);
+
+            assert!(!prefetcher
+                .on_event(&mut client, Subsystem::Mixer)
+                .await
+                .unwrap());
+            assert!(!prefetcher
+                .on_event(&mut client, Subsystem::Player)
+                .await
+                .unwrap());
+            assert!(prefetcher.refresh(&mut client).await.unwrap());
+            assert!(prefetcher.next_track().is_none());
+        });
+
+        server.finish();
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{Error, MpdClient, SongId, SongPos, Track};
+
+/// Local mirror of the queue on the server
+///
+/// The mirror is brought up to date with [`sync`](Queue::sync), which only fetches the songs
+/// that changed since the last sync, using the queue version reported by `status`. The
+/// mutating methods send the matching command and sync afterwards.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, MpdClient, Queue};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let mut queue = Queue::new();
+///     queue.sync(&mut mpd).await?;
+///
+///     for track in queue.iter() {
+///         println!("{:?}: {}", track.pos, track.file);
+///     }
+///
+///     loop {
+///         mpd.idle().await?;
+///         if queue.sync(&mut mpd).await? {
+///             println!("Queue changed, {} songs", queue.len());
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Queue {
+    /// Queue version of the mirror, None before the first sync
+    version: Option<u32>,
+    tracks: Vec<Track>,
+}
+
+impl Queue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue version the mirror is synchronized to
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// The song at position `pos`
+    pub fn get(&self, pos: SongPos) -> Option<&Track> {
+        self.tracks.get(pos.0 as usize)
+    }
+
+    /// The song with id `id`
+    pub fn get_id(&self, id: SongId) -> Option<&Track> {
+        self.tracks.iter().find(|t| t.id == Some(id))
+    }
+
+    /// Position of the song with id `id`
+    pub fn position(&self, id: SongId) -> Option<SongPos> {
+        self.get_id(id).and_then(|t| t.pos)
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Track> {
+        self.tracks.iter()
+    }
+
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// Bring the mirror up to date with the server. Returns true if anything changed
+    pub async fn sync(&mut self, client: &mut MpdClient) -> Result<bool, Error> {
+        let status = client.status().await?;
+
+        if self.version == Some(status.playlist) {
+            return Ok(false);
+        }
+
+        let changes = match self.version {
+            Some(version) => client.queue_changes(version).await?,
+            None => client.queue().await?,
+        };
+
+        self.apply(changes, status.playlistlength as usize);
+        self.version = Some(status.playlist);
+        Ok(true)
+    }
+
+    /// Apply changed songs to the mirror, and drop songs past the new length of the queue
+    fn apply(&mut self, changes: Vec<Track>, len: usize) {
+        for track in changes {
+            let pos = match track.pos {
+                Some(pos) => pos.0 as usize,
+                None => continue,
+            };
+
+            match pos.cmp(&self.tracks.len()) {
+                std::cmp::Ordering::Less => self.tracks[pos] = track,
+                std::cmp::Ordering::Equal => self.tracks.push(track),
+                std::cmp::Ordering::Greater => {
+                    log::warn!("Queue change past the end of the queue: {}", pos);
+                }
+            }
+        }
+
+        self.tracks.truncate(len);
+    }
+
+    /// Add a song to the end of the queue
+    pub async fn push(&mut self, client: &mut MpdClient, uri: &str) -> Result<SongId, Error> {
+        let id = client.queue_add_id(uri, None).await?;
+        self.sync(client).await?;
+        Ok(id)
+    }
+
+    /// Add a song to the queue at position `pos`
+    pub async fn insert(
+        &mut self,
+        client: &mut MpdClient,
+        pos: SongPos,
+        uri: &str,
+    ) -> Result<SongId, Error> {
+        let id = client.queue_add_id(uri, Some(pos)).await?;
+        self.sync(client).await?;
+        Ok(id)
+    }
+
+    /// Remove the song with id `id`
+    pub async fn remove(&mut self, client: &mut MpdClient, id: SongId) -> Result<(), Error> {
+        client.queue_delete_id(id).await?;
+        self.sync(client).await?;
+        Ok(())
+    }
+
+    /// Move the song with id `id` to position `to`
+    pub async fn move_to(
+        &mut self,
+        client: &mut MpdClient,
+        id: SongId,
+        to: SongPos,
+    ) -> Result<(), Error> {
+        client.queue_move_id(id, to).await?;
+        self.sync(client).await?;
+        Ok(())
+    }
+
+    /// Remove all songs
+    pub async fn clear(&mut self, client: &mut MpdClient) -> Result<(), Error> {
+        client.queue_clear().await?;
+        self.sync(client).await?;
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a Queue {
+    type Item = &'a Track;
+    type IntoIter = std::slice::Iter<'a, Track>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tracks.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn track(pos: u32, id: u32) -> Track {
+        Track::builder(format!("{}.flac", id))
+            .pos(SongPos(pos))
+            .id(SongId(id))
+            .build()
+    }
+
+    #[test]
+    fn apply_changes() {
+        let mut queue = Queue::new();
+        queue.apply(vec![track(0, 1), track(1, 2), track(2, 3)], 3);
+        assert_eq!(queue.len(), 3);
+
+        // Song 3 deleted, song 2 moved to the front
+        queue.apply(vec![track(0, 2), track(1, 1)], 2);
+        let ids: Vec<_> = queue.iter().map(|t| t.id.unwrap().0).collect();
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(queue.position(SongId(1)), Some(SongPos(1)));
+
+        // Song 4 added
+        queue.apply(vec![track(2, 4)], 3);
+        assert_eq!(queue.get(SongPos(2)).unwrap().file, "4.flac");
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use crate::{client::backend::sleep, Error, MpdClient};
+
+/// How to reconnect after the connection was lost
+///
+/// Used by [`MpdClient::idle_stream`], which reconnects on its own. Attempts are made with a
+/// delay that doubles after every failed attempt, up to a maximum.
+///
+/// # Example
+/// ```
+/// use async_mpd::{MpdClient, ReconnectPolicy};
+/// use std::time::Duration;
+///
+/// let mpd = MpdClient::builder()
+///     .reconnect(ReconnectPolicy::new().attempts(10).delay(Duration::from_millis(500)))
+///     .build();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    attempts: Option<u32>,
+    delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReconnectPolicy {
+    /// Up to 5 attempts, starting with a delay of 1 second and waiting at most 30 seconds
+    pub fn new() -> Self {
+        Self {
+            attempts: Some(5),
+            delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Give up after `attempts` failed attempts
+    pub fn attempts(mut self, attempts: u32) -> Self {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    /// Never give up
+    pub fn forever(mut self) -> Self {
+        self.attempts = None;
+        self
+    }
+
+    /// Delay before the first attempt
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Longest delay between attempts
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay before attempt number `attempt`, counting from 0
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt);
+        self.delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+impl MpdClient {
+    /// Reconnect according to the configured [`ReconnectPolicy`]
+    ///
+    /// Returns the error of the last attempt if all attempts failed, or `Error::Disconnected`
+    /// if no policy is configured.
+    pub(crate) async fn reconnect_with_policy(&mut self) -> Result<(), Error> {
+        let policy = match self.reconnect_policy.clone() {
+            Some(policy) => policy,
+            None => return Err(Error::Disconnected),
+        };
+
+        let mut attempt = 0;
+
+        loop {
+            sleep(policy.delay_for(attempt)).await;
+
+            match self.reconnect().await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    log::debug!("Reconnect attempt {} failed: {}", attempt + 1, e);
+                    attempt += 1;
+
+                    if policy.attempts.is_some_and(|attempts| attempt >= attempts) {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ReconnectPolicy;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff() {
+        let policy = ReconnectPolicy::new()
+            .delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(10));
+
+        assert_eq!(policy.delay_for(0), Duration::from_secs(1));
+        assert_eq!(policy.delay_for(2), Duration::from_secs(4));
+        assert_eq!(policy.delay_for(4), Duration::from_secs(10));
+        assert_eq!(policy.delay_for(100), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,142 @@
+//! Behavior of every response handler on pathological responses
+//!
+//! All handlers follow the same rules:
+//! - A response ends at the first `OK`, or at an ACK line, which is returned as
+//!   [`Error::ServerError`]. Nothing after it is read, not even a duplicated `OK`.
+//! - Blank lines, and lines a handler doesn't know, are ignored.
+//! - Binary data is skipped by handlers not expecting it, so data looking like `OK` or an ACK
+//!   doesn't end the response early.
+//! - A response cut off by the end of the stream fails with [`Error::Disconnected`].
+//!
+//! Command lists follow the same rules, with `list_OK` ending the response of every command.
+
+use futures_lite::future::block_on;
+
+use super::handlers::*;
+use crate::{Error, Output, SongId, Status};
+
+/// Kind of result of a handler, with the input left unread
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Ok,
+    ServerError,
+    Disconnected,
+    Other(String),
+}
+
+fn run<H: ResponseHandler>(input: &[u8]) -> (Outcome, &[u8]) {
+    let mut rest = input;
+
+    let outcome = match block_on(H::handle(&mut rest)) {
+        Ok(_) => Outcome::Ok,
+        Err(Error::ServerError { .. }) => Outcome::ServerError,
+        Err(Error::Disconnected) => Outcome::Disconnected,
+        Err(e) => Outcome::Other(e.to_string()),
+    };
+
+    (outcome, rest)
+}
+
+/// Run every handler of a single command on `input`, expecting `outcome` and `rest` left unread
+fn all_handlers(input: &[u8], outcome: Outcome, rest: &[u8]) {
+    let results = [
+        ("Tracks", run::<Tracks>(input)),
+        ("MixedResponseResponse", run::<MixedResponseResponse>(input)),
+        ("KeyValueResponse", run::<KeyValueResponse<Status>>(input)),
+        ("ListResponse", run::<ListResponse<Output>>(input)),
+        ("RawResponse", run::<RawResponse>(input)),
+        ("BinaryResponse", run::<BinaryResponse>(input)),
+        ("SingleLineResp", run::<SingleLineResp<SongId>>(input)),
+        ("OkResponse", run::<OkResponse>(input)),
+    ];
+
+    for (name, result) in results {
+        assert_eq!(
+            (&result.0, result.1),
+            (&outcome, rest),
+            "{} on {:?}",
+            name,
+            String::from_utf8_lossy(input)
+        );
+    }
+}
+
+/// Run the command list handlers on `input`
+fn list_handlers(input: &[u8], outcome: Outcome, rest: &[u8]) {
+    let results = [
+        (
+            "CommandListResponse<(A, B)>",
+            run::<CommandListResponse<(RawResponse, SingleLineResp<SongId>)>>(input),
+        ),
+        (
+            "CommandListResponse<Vec<H>>",
+            run::<CommandListResponse<Vec<RawResponse>>>(input),
+        ),
+    ];
+
+    for (name, result) in results {
+        assert_eq!(
+            (&result.0, result.1),
+            (&outcome, rest),
+            "{} on {:?}",
+            name,
+            String::from_utf8_lossy(input)
+        );
+    }
+}
+
+#[test]
+fn ack_mid_list() {
+    all_handlers(
+        b"Id: 7\nfile: a.flac\nACK [50@0] {lsinfo} No such directory\nOK\n",
+        Outcome::ServerError,
+        b"OK\n",
+    );
+    list_handlers(
+        b"Id: 7\nlist_OK\nACK [50@1] {addid} No such song\nOK\n",
+        Outcome::ServerError,
+        b"OK\n",
+    );
+}
+
+#[test]
+fn unexpected_binary() {
+    // The binary data contains lines looking like the end of the response
+    all_handlers(
+        b"Id: 7\nbinary: 13\nOK\nACK [5@0] \nOK\nnext",
+        Outcome::Ok,
+        b"next",
+    );
+    list_handlers(
+        b"binary: 8\nlist_OK\n\nlist_OK\nId: 7\nlist_OK\nOK\nnext",
+        Outcome::Ok,
+        b"next",
+    );
+}
+
+#[test]
+fn blank_lines() {
+    all_handlers(b"\nId: 7\n\n\nOK\nnext", Outcome::Ok, b"next");
+    list_handlers(
+        b"\nlist_OK\n\nId: 7\n\nlist_OK\nOK\nnext",
+        Outcome::Ok,
+        b"next",
+    );
+}
+
+#[test]
+fn duplicated_ok() {
+    all_handlers(b"Id: 7\nOK\nOK\n", Outcome::Ok, b"OK\n");
+    list_handlers(b"list_OK\nId: 7\nlist_OK\nOK\nOK\n", Outcome::Ok, b"OK\n");
+}
+
+#[test]
+fn end_of_stream() {
+    for input in [&b""[..], b"Id: 7\n", b"Id: 7", b"Id: 7\nbinary: 4\nab"] {
+        all_handlers(input, Outcome::Disconnected, b"");
+    }
+
+    for input in [&b""[..], b"list_OK\n", b"list_OK\nId: 7\nlist_OK\n"] {
+        list_handlers(input, Outcome::Disconnected, b"");
+    }
+}
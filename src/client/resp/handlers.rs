@@ -1,70 +1,162 @@
-use async_net::TcpStream;
 use async_trait::async_trait;
 
-use futures_lite::io::BufReader;
-use futures_lite::{AsyncBufReadExt, StreamExt};
+use futures_lite::io::{AsyncBufRead, AsyncReadExt};
+use futures_lite::AsyncBufReadExt;
 
+use std::fmt;
 use std::marker::PhantomData;
 use std::str::FromStr;
 
-use crate::resp::WrappedResponse;
 use crate::{
     client::resp::{
-        read_resp_line,
+        read_pairs, read_resp_line,
         respmap::RespMap,
         respmap_handlers::{mixed_stream, tracks, ListallinfoResponse},
+        BinaryChunk,
     },
-    Error, Track,
+    DatabaseVersion, Error, ReplayGainMode, SongId, Track, Volume,
 };
 
 #[async_trait]
 /// Response Handler for Cmd
+///
+/// Handlers read the complete response of a command, up to and including the terminating
+/// `OK`, from any buffered reader.
 pub trait ResponseHandler: Sized {
     /// The type of response
-    type Response: Into<WrappedResponse>;
+    type Response;
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, crate::Error>;
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, crate::Error>
+    where
+        R: AsyncBufRead + Unpin + Send;
 }
 
+/// Handler for responses with a list of songs
 pub struct Tracks;
 
 #[async_trait]
 impl ResponseHandler for Tracks {
     type Response = Vec<Track>;
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, Error> {
-        tracks(reader).await.map_err(Into::into)
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        tracks(reader).await
     }
 }
 
+/// Handler for responses with a mix of songs, directories and playlists
 pub struct MixedResponseResponse;
 
 #[async_trait]
 impl ResponseHandler for MixedResponseResponse {
     type Response = ListallinfoResponse;
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, Error> {
-        mixed_stream(reader).await.map_err(Into::into)
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        mixed_stream(reader).await
     }
 }
 
-pub struct RespMapResponse<T> {
+/// Handler for responses of key-value pairs, collected into a [`RespMap`] and converted to `T`
+pub struct KeyValueResponse<T> {
     _0: PhantomData<T>,
 }
 
 #[async_trait]
-impl<T: From<RespMap> + Into<WrappedResponse>> ResponseHandler for RespMapResponse<T> {
+impl<T: From<RespMap> + Send> ResponseHandler for KeyValueResponse<T> {
     type Response = T;
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, Error> {
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
         let mut map = RespMap::new();
-        let mut lines = reader.lines();
 
-        while let Some(line) = lines.next().await {
-            let line = line?;
+        for (k, v) in read_pairs(reader).await? {
+            map.insert(&k, &v);
+        }
+
+        Ok(map.into())
+    }
+}
+
+/// Handler for responses with a list of records, each converted to `T`
+///
+/// A new record is started every time the key of the first line in the response is seen again.
+pub struct ListResponse<T> {
+    _0: PhantomData<T>,
+}
+
+#[async_trait]
+impl<T: From<RespMap> + Send> ResponseHandler for ListResponse<T> {
+    type Response = Vec<T>;
+
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        let mut list = Vec::new();
+        let mut map = RespMap::new();
+        let mut first_key: Option<String> = None;
+
+        for (k, v) in read_pairs(reader).await? {
+            match &first_key {
+                Some(first) if *first == k => {
+                    list.push(T::from(map));
+                    map = RespMap::new();
+                }
+                Some(_) => (),
+                None => first_key = Some(k.clone()),
+            }
+
+            map.insert(&k, &v);
+        }
+
+        if !map.is_empty() {
+            list.push(T::from(map));
+        }
+
+        Ok(list)
+    }
+}
+
+/// Handler for responses with raw key-value pairs
+pub struct RawResponse;
+
+#[async_trait]
+impl ResponseHandler for RawResponse {
+    type Response = Vec<(String, String)>;
+
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        read_pairs(reader).await
+    }
+}
+
+/// Handler for responses carrying a chunk of binary data, such as `albumart`
+pub struct BinaryResponse;
+
+#[async_trait]
+impl ResponseHandler for BinaryResponse {
+    type Response = BinaryChunk;
+
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        let mut chunk = BinaryChunk::default();
+
+        loop {
+            let line = read_resp_line(reader).await?;
             log::debug!("line: '{}'", line);
 
-            if &line == "OK" {
+            if line == "OK" {
                 break;
             }
 
@@ -72,53 +164,378 @@ impl<T: From<RespMap> + Into<WrappedResponse>> ResponseHandler for RespMapRespon
                 return Err(crate::Error::ServerError { msg: line });
             }
 
-            if let Some((k, v)) = line.split_once(": ") {
-                map.insert(k, v);
+            match line.split_once(": ") {
+                Some(("size", v)) => chunk.size = v.parse()?,
+                Some(("type", v)) => chunk.mime = Some(v.to_string()),
+                Some(("binary", v)) => {
+                    let len: usize = v.parse()?;
+
+                    // The buffer grows with the data actually received, so a bogus length
+                    // doesn't cause a huge allocation
+                    (&mut *reader)
+                        .take(len as u64)
+                        .read_to_end(&mut chunk.data)
+                        .await?;
+
+                    if chunk.data.len() != len {
+                        return Err(Error::Disconnected);
+                    }
+
+                    // The binary data is followed by a newline
+                    read_resp_line(reader).await?;
+                }
+                _ => (),
             }
         }
 
-        Ok(map.into())
+        Ok(chunk)
     }
 }
 
+/// Value of a response with a single `key: value` line, read with [`SingleLineResp`]
+pub trait SingleValue: FromStr {
+    /// Key of the line with the value
+    const KEY: &'static str;
+}
+
+impl SingleValue for DatabaseVersion {
+    const KEY: &'static str = "updating_db";
+}
+
+impl SingleValue for SongId {
+    const KEY: &'static str = "Id";
+}
+
+impl SingleValue for Volume {
+    const KEY: &'static str = "volume";
+}
+
+impl SingleValue for ReplayGainMode {
+    const KEY: &'static str = "replay_gain_mode";
+}
+
+/// Handler for responses with a single value, in the line with the key of `T`
+///
+/// Fails with [`Error::ResponseError`] if the line is missing or its value can't be parsed,
+/// instead of falling back to a default value. Other lines are ignored.
 pub struct SingleLineResp<T> {
     _0: PhantomData<T>,
 }
 
 #[async_trait]
-impl<E: Into<crate::Error>, T: FromStr<Err = E> + Into<WrappedResponse>> ResponseHandler
-    for SingleLineResp<T>
+impl<T> ResponseHandler for SingleLineResp<T>
+where
+    T: SingleValue + Send,
+    T::Err: fmt::Display,
 {
     type Response = T;
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, Error> {
-        let line = read_resp_line(reader).await?;
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        let pairs = read_pairs(reader).await?;
 
-        let (_key, value) = line.split_once(": ").ok_or(crate::Error::ValueError {msg: "invalid line".to_string() })?;
+        let (key, value) = pairs
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(T::KEY))
+            .ok_or_else(|| Error::ResponseError {
+                line: match pairs.first() {
+                    Some((k, v)) => format!("{}: {}", k, v),
+                    None => "OK".to_string(),
+                },
+            })?;
 
-        T::from_str(value).map_err(Into::into)
+        T::from_str(value).map_err(|e| {
+            log::debug!("invalid `{}`: {}", key, e);
+            Error::ResponseError {
+                line: format!("{}: {}", key, value),
+            }
+        })
     }
 }
 
+/// Handler for commands that only respond with `OK`
+///
+/// Lines before the `OK` are ignored, like the unknown lines of other responses.
 pub struct OkResponse;
 
 #[async_trait]
 impl ResponseHandler for OkResponse {
     type Response = ();
 
-    async fn handle(reader: &mut BufReader<TcpStream>) -> Result<Self::Response, crate::Error> {
-        let mut lines = reader.lines();
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, crate::Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        read_pairs(reader).await.map(|_| ())
+    }
+}
+
+/// Handler for the responses of a [`CommandList`](crate::cmd::CommandList), with a handler
+/// for every command in the list
+///
+/// The responses of all commands are read before any of them is handled, so that the
+/// connection is left at the end of the list even when handling a response fails.
+pub struct CommandListResponse<T> {
+    _0: PhantomData<T>,
+}
+
+/// Read the response of a command in a command list up to its `list_OK`, and return it as a
+/// complete response ending with `OK`. None at the `OK` ending the list
+async fn read_list_item<R>(reader: &mut R) -> Result<Option<Vec<u8>>, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut resp = Vec::new();
+
+    loop {
+        let start = resp.len();
+
+        if reader.read_until(b'\n', &mut resp).await? == 0 {
+            return Err(Error::Disconnected);
+        }
+
+        let line = &resp[start..];
+
+        if start == 0 && line == b"OK\n" {
+            return Ok(None);
+        }
+
+        if line == b"list_OK\n" {
+            resp.truncate(start);
+            resp.extend_from_slice(b"OK\n");
+            return Ok(Some(resp));
+        }
+
+        if line.starts_with(b"ACK ") {
+            let msg = String::from_utf8_lossy(line).trim().to_string();
+            return Err(Error::ServerError { msg });
+        }
+
+        // Binary data could contain a line looking like `list_OK`, and is copied as is
+        if let Some(len) = line.strip_prefix(b"binary: ") {
+            let len: usize = String::from_utf8_lossy(len).trim().parse()?;
+            let end = resp.len() + len + 1;
+
+            (&mut *reader)
+                .take(len as u64 + 1)
+                .read_to_end(&mut resp)
+                .await?;
+
+            if resp.len() != end {
+                return Err(Error::Disconnected);
+            }
+        }
+    }
+}
+
+macro_rules! command_list_response {
+    ($($handler:ident $resp:ident),+) => {
+        #[async_trait]
+        impl<$($handler),+> ResponseHandler for CommandListResponse<($($handler,)+)>
+        where
+            $($handler: ResponseHandler, $handler::Response: Send,)+
+        {
+            type Response = ($($handler::Response,)+);
+
+            async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+            where
+                R: AsyncBufRead + Unpin + Send,
+            {
+                $(let $resp = read_list_item(reader)
+                    .await?
+                    .ok_or_else(|| Error::ResponseError { line: "OK".into() })?;)+
+
+                let line = read_resp_line(reader).await?;
+                if line != "OK" {
+                    return Err(Error::ServerError { msg: line });
+                }
+
+                Ok(($($handler::handle(&mut &$resp[..]).await?,)+))
+            }
+        }
+    };
+}
+
+command_list_response!(A a, B b);
+command_list_response!(A a, B b, C c);
+command_list_response!(A a, B b, C c, D d);
+command_list_response!(A a, B b, C c, D d, E e);
+
+#[async_trait]
+impl<H> ResponseHandler for CommandListResponse<Vec<H>>
+where
+    H: ResponseHandler,
+    H::Response: Send,
+{
+    type Response = Vec<H::Response>;
+
+    async fn handle<R>(reader: &mut R) -> Result<Self::Response, Error>
+    where
+        R: AsyncBufRead + Unpin + Send,
+    {
+        let mut items = Vec::new();
+        while let Some(item) = read_list_item(reader).await? {
+            items.push(item);
+        }
+
+        let mut responses = Vec::with_capacity(items.len());
+        for item in items {
+            responses.push(H::handle(&mut &item[..]).await?);
+        }
+
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures_lite::future::block_on;
+
+    #[derive(Debug, PartialEq)]
+    struct Output {
+        id: u32,
+        name: String,
+    }
+
+    impl From<RespMap> for Output {
+        fn from(mut map: RespMap) -> Self {
+            Output {
+                id: map.get_def("outputid"),
+                name: map.get_def("outputname"),
+            }
+        }
+    }
+
+    #[test]
+    fn list_response() {
+        let mut input =
+            "outputid: 0\noutputname: alsa\noutputid: 1\noutputname: http\nOK\n".as_bytes();
+
+        let outputs = block_on(ListResponse::<Output>::handle(&mut input)).unwrap();
+        assert_eq!(
+            outputs,
+            vec![
+                Output {
+                    id: 0,
+                    name: "alsa".into()
+                },
+                Output {
+                    id: 1,
+                    name: "http".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_response() {
+        let mut input = &b"size: 6\ntype: image/png\nbinary: 3\n\x00\n\x01\nOK\n"[..];
+
+        let chunk = block_on(BinaryResponse::handle(&mut input)).unwrap();
+        assert_eq!(chunk.size, 6);
+        assert_eq!(chunk.mime.as_deref(), Some("image/png"));
+        assert_eq!(chunk.data, vec![0, b'\n', 1]);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn truncated_responses() {
+        let mut input = &b"size: 6\nbinary: 18446744073709551615\n\x00"[..];
+        assert!(matches!(
+            block_on(BinaryResponse::handle(&mut input)),
+            Err(Error::Disconnected)
+        ));
+
+        let mut input = "file: a.flac\nTitle: A\n".as_bytes();
+        assert!(matches!(
+            block_on(Tracks::handle(&mut input)),
+            Err(Error::Disconnected)
+        ));
+
+        let mut input = "file: a.flac\nACK [50@0] {lsinfo} No such directory\n".as_bytes();
+        assert!(matches!(
+            block_on(MixedResponseResponse::handle(&mut input)),
+            Err(Error::ServerError { .. })
+        ));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn handlers_any_input(data in proptest::collection::vec(proptest::num::u8::ANY, 0..256)) {
+            let _ = block_on(MixedResponseResponse::handle(&mut &data[..]));
+            let _ = block_on(BinaryResponse::handle(&mut &data[..]));
+            let _ = block_on(RawResponse::handle(&mut &data[..]));
+        }
+    }
+
+    #[test]
+    fn command_list_response() {
+        let mut input =
+            &b"volume: 40\nlist_OK\nfile: a.flac\nlist_OK\nbinary: 8\nlist_OK\n\nlist_OK\nOK\n"[..];
+
+        let (status, tracks, chunk) = block_on(CommandListResponse::<(
+            KeyValueResponse<crate::Status>,
+            Tracks,
+            BinaryResponse,
+        )>::handle(&mut input))
+        .unwrap();
+
+        assert_eq!(status.volume, Some(40));
+        assert_eq!(tracks[0].file, "a.flac");
+        assert_eq!(chunk.data, b"list_OK\n");
+        assert!(input.is_empty());
+
+        let mut input = "volume: 40\nlist_OK\nACK [50@1] {load} No such playlist\n".as_bytes();
+        assert!(matches!(
+            block_on(CommandListResponse::<(RawResponse, OkResponse)>::handle(
+                &mut input
+            )),
+            Err(Error::ServerError { .. })
+        ));
+        assert!(input.is_empty());
+
+        let mut input = "list_OK\nId: 7\nlist_OK\nOK\n".as_bytes();
+        let ids = block_on(CommandListResponse::<Vec<RawResponse>>::handle(&mut input)).unwrap();
+        assert_eq!(ids, vec![vec![], vec![("Id".to_string(), "7".to_string())]]);
+        assert!(input.is_empty());
+
+        let mut input = "list_OK\nOK\n".as_bytes();
+        assert!(matches!(
+            block_on(CommandListResponse::<(OkResponse, OkResponse)>::handle(
+                &mut input
+            )),
+            Err(Error::ResponseError { .. })
+        ));
+    }
+
+    #[test]
+    fn single_line_response() {
+        let mut input = "updating_db: 4\nOK\n".as_bytes();
+        let version = block_on(SingleLineResp::<DatabaseVersion>::handle(&mut input)).unwrap();
+        assert_eq!(version.0, 4);
+
+        let mut input = "Id: 7\nOK\n".as_bytes();
+        let id = block_on(SingleLineResp::<SongId>::handle(&mut input)).unwrap();
+        assert_eq!(id, SongId(7));
 
-        if let Some(line) = lines.next().await {
-            let line = line?;
+        let mut input = "replay_gain_mode: album\nOK\n".as_bytes();
+        let mode = block_on(SingleLineResp::<ReplayGainMode>::handle(&mut input)).unwrap();
+        assert_eq!(mode, ReplayGainMode::Album);
 
-            if &line == "OK" {
-                Ok(())
-            } else {
-                Err(crate::Error::ServerError { msg: line })
+        for (input, line) in [
+            ("OK\n", "OK"),
+            ("volume: 40\nOK\n", "volume: 40"),
+            ("updating_db: x\nOK\n", "updating_db: x"),
+        ] {
+            let mut input = input.as_bytes();
+            match block_on(SingleLineResp::<DatabaseVersion>::handle(&mut input)) {
+                Err(Error::ResponseError { line: l }) => assert_eq!(l, line),
+                other => panic!("{:?}", other),
             }
-        } else {
-            Ok(())
+            assert!(input.is_empty());
         }
     }
 }
@@ -1,31 +1,331 @@
 use crate::client::resp::respmap_handlers::{ListallResponse, ListallinfoResponse};
 use crate::protocol::Stats;
-use crate::{protocol, DatabaseVersion, Error, Status, Subsystem, Track};
-use async_net::TcpStream;
-use futures_lite::io::BufReader;
+use crate::{
+    protocol, DatabaseVersion, Error, Fingerprint, Mount, Neighbor, Output, ReplayGainMode, SongId,
+    Status, Sticker, StickerMatch, StoredPlaylist, Subsystem, Track, Volume,
+};
+use futures_lite::io::{self, AsyncBufRead, AsyncReadExt};
 use futures_lite::AsyncBufReadExt;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
 
+#[cfg(test)]
+mod conformance;
 pub mod handlers;
 pub mod respmap;
 pub mod respmap_handlers;
 
+/// A line of a response, without the line ending
+pub(crate) struct Line {
+    /// The line, decoded lossily if it isn't valid UTF-8
+    pub(crate) text: String,
+    /// The undecoded line, if it isn't valid UTF-8
+    pub(crate) raw: Option<Vec<u8>>,
+}
+
+impl Line {
+    /// The undecoded value of a `key: value` line that isn't valid UTF-8
+    pub(crate) fn raw_value(&self) -> Option<&[u8]> {
+        let raw = self.raw.as_deref()?;
+        let sep = raw.windows(2).position(|w| w == b": ")?;
+        Some(&raw[sep + 2..])
+    }
+}
+
+/// Read a line, None if the connection was closed
+///
+/// Lines that aren't valid UTF-8, such as paths in legacy encodings, are decoded lossily
+/// instead of failing, keeping the undecoded line.
+pub(crate) async fn read_line<R>(reader: &mut R) -> Result<Option<Line>, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut buf = Vec::new();
+
+    if reader.read_until(b'\n', &mut buf).await? == 0 {
+        return Ok(None);
+    }
+
+    if buf.ends_with(b"\n") {
+        buf.pop();
+        if buf.ends_with(b"\r") {
+            buf.pop();
+        }
+    }
+
+    let line = match String::from_utf8(buf) {
+        Ok(text) => Line { text, raw: None },
+        Err(e) => Line {
+            text: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+            raw: Some(e.into_bytes()),
+        },
+    };
+
+    Ok(Some(line))
+}
+
 /// Expect one line response
-pub(crate) async fn read_resp_line(reader: &mut BufReader<TcpStream>) -> Result<String, Error> {
-    let mut line = String::new();
-    reader.read_line(&mut line).await?;
-    Ok(line.trim().to_string())
+pub(crate) async fn read_resp_line<R>(reader: &mut R) -> Result<String, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    match read_line(reader).await? {
+        Some(line) => Ok(line.text.trim().to_string()),
+        // The connection was closed
+        None => Err(Error::Disconnected),
+    }
+}
+
+/// Skip the binary data announced by a `binary: <len>` line, and the newline after it
+///
+/// Handlers that don't expect binary data skip it, so that data looking like `OK` or an ACK
+/// doesn't end the response early. A length that isn't a number is left as is.
+pub(crate) async fn skip_binary<R>(reader: &mut R, len: &str) -> Result<(), Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let len = match len.trim().parse::<u64>() {
+        Ok(len) => len.saturating_add(1),
+        Err(_) => return Ok(()),
+    };
+
+    if io::copy((&mut *reader).take(len), &mut io::sink()).await? != len {
+        return Err(Error::Disconnected);
+    }
+
+    Ok(())
+}
+
+/// Read the key-value pairs of a response up to the terminating `OK`
+pub(crate) async fn read_pairs<R>(reader: &mut R) -> Result<Vec<(String, String)>, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut pairs = Vec::new();
+
+    while let Some(Line { text: line, .. }) = read_line(reader).await? {
+        log::debug!("line: '{}'", line);
+
+        if &line == "OK" {
+            return Ok(pairs);
+        }
+
+        if line.starts_with("ACK ") {
+            return Err(crate::Error::ServerError { msg: line });
+        }
+
+        if let Some((k, v)) = line.split_once(": ") {
+            if k == "binary" {
+                skip_binary(reader, v).await?;
+            }
+            pairs.push((k.to_string(), v.to_string()));
+        }
+    }
+
+    // The connection was closed before the response was complete
+    Err(Error::Disconnected)
+}
+
+/// A chunk of binary data, as returned by `albumart` and `readpicture`
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct BinaryChunk {
+    /// Total size of the binary object
+    pub size: usize,
+    /// Mime type, if reported by the server
+    pub mime: Option<String>,
+    /// The data of this chunk
+    pub data: Vec<u8>,
 }
 
 /// A Enum-wrapped response
+///
+/// Serialized with the variant name in snake case as `type`, and the response as `data`.
+///
+/// Variants are added as commands are added, so the enum is non-exhaustive. Variants are named
+/// after the response type they wrap, or the command for responses without a dedicated type.
+/// Responses of types not known by this version of the crate are deserialized as `Unknown`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(remote = "Self")]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum WrappedResponse {
     Ok,
-    ListAllInfo(ListallinfoResponse),
+    ListallInfo(ListallinfoResponse),
     Tracks(Vec<Track>),
     Listall(ListallResponse),
     Subsystem(Subsystem),
     DatabaseVersion(DatabaseVersion),
     Status(Status),
     Stats(Stats),
+    Sticker(Sticker),
+    Stickers(Vec<Sticker>),
+    StickerMatches(Vec<StickerMatch>),
+    Binary(BinaryChunk),
+    Outputs(Vec<Output>),
+    Fingerprint(Fingerprint),
+    StoredPlaylists(Vec<StoredPlaylist>),
+    Volume(Volume),
+    ReplayGainMode(ReplayGainMode),
+    SongId(SongId),
+    Mounts(Vec<Mount>),
+    Neighbors(Vec<Neighbor>),
+    Subsystems(Vec<Subsystem>),
+    /// Key-value pairs of a response without a dedicated type
+    Raw(Vec<(String, String)>),
+    /// Deserialized response of a type not known by this version of the crate
+    Unknown,
+}
+
+impl Serialize for WrappedResponse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        WrappedResponse::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for WrappedResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        #[allow(clippy::large_enum_variant)]
+        enum Tolerant {
+            Known(#[serde(deserialize_with = "WrappedResponse::deserialize")] WrappedResponse),
+            Other {
+                #[serde(rename = "type")]
+                variant: String,
+            },
+        }
+
+        match Tolerant::deserialize(deserializer)? {
+            Tolerant::Known(resp) => Ok(resp),
+            Tolerant::Other { variant }
+                if !WrappedResponse::VARIANTS.contains(&variant.as_str()) =>
+            {
+                Ok(WrappedResponse::Unknown)
+            }
+            Tolerant::Other { variant } => Err(de::Error::custom(format!(
+                "invalid data for response type `{}`",
+                variant
+            ))),
+        }
+    }
+}
+
+impl WrappedResponse {
+    /// Names of the variants, as used when serialized
+    const VARIANTS: &'static [&'static str] = &[
+        "ok",
+        "listall_info",
+        "tracks",
+        "listall",
+        "subsystem",
+        "database_version",
+        "status",
+        "stats",
+        "sticker",
+        "stickers",
+        "sticker_matches",
+        "binary",
+        "outputs",
+        "fingerprint",
+        "stored_playlists",
+        "volume",
+        "replay_gain_mode",
+        "song_id",
+        "mounts",
+        "neighbors",
+        "subsystems",
+        "raw",
+        "unknown",
+    ];
+
+    /// Name of the variant, as used when serialized
+    fn variant(&self) -> &'static str {
+        match self {
+            WrappedResponse::Ok => "ok",
+            WrappedResponse::ListallInfo(_) => "listall_info",
+            WrappedResponse::Tracks(_) => "tracks",
+            WrappedResponse::Listall(_) => "listall",
+            WrappedResponse::Subsystem(_) => "subsystem",
+            WrappedResponse::DatabaseVersion(_) => "database_version",
+            WrappedResponse::Status(_) => "status",
+            WrappedResponse::Stats(_) => "stats",
+            WrappedResponse::Sticker(_) => "sticker",
+            WrappedResponse::Stickers(_) => "stickers",
+            WrappedResponse::StickerMatches(_) => "sticker_matches",
+            WrappedResponse::Binary(_) => "binary",
+            WrappedResponse::Outputs(_) => "outputs",
+            WrappedResponse::Fingerprint(_) => "fingerprint",
+            WrappedResponse::StoredPlaylists(_) => "stored_playlists",
+            WrappedResponse::Volume(_) => "volume",
+            WrappedResponse::ReplayGainMode(_) => "replay_gain_mode",
+            WrappedResponse::SongId(_) => "song_id",
+            WrappedResponse::Mounts(_) => "mounts",
+            WrappedResponse::Neighbors(_) => "neighbors",
+            WrappedResponse::Subsystems(_) => "subsystems",
+            WrappedResponse::Raw(_) => "raw",
+            WrappedResponse::Unknown => "unknown",
+        }
+    }
+}
+
+/// Conversions back from a [`WrappedResponse`], failing with a `ValueError` naming both
+/// variants when the response is of another variant
+macro_rules! try_from_wrapped {
+    ($($ty:ty => $variant:ident, $name:literal;)*) => {
+        $(
+            impl TryFrom<WrappedResponse> for $ty {
+                type Error = Error;
+
+                fn try_from(resp: WrappedResponse) -> Result<Self, Self::Error> {
+                    match resp {
+                        WrappedResponse::$variant(inner) => Ok(inner),
+                        other => Err(mismatch($name, &other)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+fn mismatch(expected: &str, got: &WrappedResponse) -> Error {
+    Error::ValueError {
+        msg: format!("expected {} response, got {}", expected, got.variant()),
+    }
+}
+
+impl TryFrom<WrappedResponse> for () {
+    type Error = Error;
+
+    fn try_from(resp: WrappedResponse) -> Result<Self, Self::Error> {
+        match resp {
+            WrappedResponse::Ok => Ok(()),
+            other => Err(mismatch("ok", &other)),
+        }
+    }
+}
+
+try_from_wrapped! {
+    ListallinfoResponse => ListallInfo, "listall_info";
+    Vec<Track> => Tracks, "tracks";
+    ListallResponse => Listall, "listall";
+    Subsystem => Subsystem, "subsystem";
+    DatabaseVersion => DatabaseVersion, "database_version";
+    Status => Status, "status";
+    Stats => Stats, "stats";
+    Sticker => Sticker, "sticker";
+    Vec<Sticker> => Stickers, "stickers";
+    Vec<StickerMatch> => StickerMatches, "sticker_matches";
+    BinaryChunk => Binary, "binary";
+    Vec<Output> => Outputs, "outputs";
+    Fingerprint => Fingerprint, "fingerprint";
+    Vec<StoredPlaylist> => StoredPlaylists, "stored_playlists";
+    Volume => Volume, "volume";
+    ReplayGainMode => ReplayGainMode, "replay_gain_mode";
+    SongId => SongId, "song_id";
+    Vec<Mount> => Mounts, "mounts";
+    Vec<Neighbor> => Neighbors, "neighbors";
+    Vec<Subsystem> => Subsystems, "subsystems";
+    Vec<(String, String)> => Raw, "raw";
 }
 
 impl From<()> for WrappedResponse {
@@ -36,7 +336,7 @@ impl From<()> for WrappedResponse {
 
 impl From<ListallinfoResponse> for WrappedResponse {
     fn from(l: ListallinfoResponse) -> Self {
-        WrappedResponse::ListAllInfo(l)
+        WrappedResponse::ListallInfo(l)
     }
 }
 
@@ -75,3 +375,205 @@ impl From<protocol::Stats> for WrappedResponse {
         WrappedResponse::Stats(s)
     }
 }
+
+impl From<Vec<(String, String)>> for WrappedResponse {
+    fn from(r: Vec<(String, String)>) -> Self {
+        WrappedResponse::Raw(r)
+    }
+}
+
+impl From<Sticker> for WrappedResponse {
+    fn from(s: Sticker) -> Self {
+        WrappedResponse::Sticker(s)
+    }
+}
+
+impl From<Vec<Sticker>> for WrappedResponse {
+    fn from(s: Vec<Sticker>) -> Self {
+        WrappedResponse::Stickers(s)
+    }
+}
+
+impl From<Vec<StickerMatch>> for WrappedResponse {
+    fn from(s: Vec<StickerMatch>) -> Self {
+        WrappedResponse::StickerMatches(s)
+    }
+}
+
+impl From<Vec<Output>> for WrappedResponse {
+    fn from(o: Vec<Output>) -> Self {
+        WrappedResponse::Outputs(o)
+    }
+}
+
+impl From<Fingerprint> for WrappedResponse {
+    fn from(f: Fingerprint) -> Self {
+        WrappedResponse::Fingerprint(f)
+    }
+}
+
+impl From<BinaryChunk> for WrappedResponse {
+    fn from(b: BinaryChunk) -> Self {
+        WrappedResponse::Binary(b)
+    }
+}
+
+impl From<Vec<StoredPlaylist>> for WrappedResponse {
+    fn from(p: Vec<StoredPlaylist>) -> Self {
+        WrappedResponse::StoredPlaylists(p)
+    }
+}
+
+impl From<Volume> for WrappedResponse {
+    fn from(v: Volume) -> Self {
+        WrappedResponse::Volume(v)
+    }
+}
+
+impl From<ReplayGainMode> for WrappedResponse {
+    fn from(r: ReplayGainMode) -> Self {
+        WrappedResponse::ReplayGainMode(r)
+    }
+}
+
+impl From<SongId> for WrappedResponse {
+    fn from(s: SongId) -> Self {
+        WrappedResponse::SongId(s)
+    }
+}
+
+impl From<Vec<Mount>> for WrappedResponse {
+    fn from(m: Vec<Mount>) -> Self {
+        WrappedResponse::Mounts(m)
+    }
+}
+
+impl From<Vec<Neighbor>> for WrappedResponse {
+    fn from(n: Vec<Neighbor>) -> Self {
+        WrappedResponse::Neighbors(n)
+    }
+}
+
+impl From<Vec<Subsystem>> for WrappedResponse {
+    fn from(s: Vec<Subsystem>) -> Self {
+        WrappedResponse::Subsystems(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::resp::respmap::RespMap;
+    use futures_lite::future::block_on;
+
+    /// Serializing, deserializing and serializing again gives the same JSON
+    fn roundtrip(resp: WrappedResponse) -> serde_json::Value {
+        let json = serde_json::to_value(&resp).unwrap();
+        let parsed: WrappedResponse = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+        assert_eq!(json["type"], parsed.variant());
+        assert!(WrappedResponse::VARIANTS.contains(&parsed.variant()));
+        json
+    }
+
+    #[test]
+    fn try_from_wrapped() {
+        let resp = WrappedResponse::from(Stats::default());
+        assert!(Status::try_from(resp).is_err());
+
+        let resp = WrappedResponse::from(Stats::default());
+        assert!(Stats::try_from(resp).is_ok());
+
+        match Vec::<Track>::try_from(WrappedResponse::Ok) {
+            Err(e @ crate::Error::ValueError { .. }) => assert_eq!(
+                e.to_string(),
+                "Invalid value: expected tracks response, got ok"
+            ),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let mut input = "directory: music\nLast-Modified: 2021-01-10T12:00:00Z\n\
+            file: music/a.flac\nArtist: A\nArtist: B\nduration: 12.5\nPos: 0\nId: 1\n\
+            playlist: favourites\nOK\n"
+            .as_bytes();
+        let listing = block_on(respmap_handlers::mixed_stream(&mut input)).unwrap();
+        let tracks = listing.files.clone();
+
+        let status = Status::from(RespMap::from_string(
+            "volume: 50\nstate: play\nsingle: oneshot\nelapsed: 1.5\nerror: broken\n".into(),
+        ));
+        let stats = Stats::from(RespMap::from_string("uptime: 10\nsongs: 3\n".into()));
+        let sticker = Sticker {
+            name: "rating".into(),
+            value: "5".into(),
+        };
+
+        let json = roundtrip(WrappedResponse::Status(status));
+        assert_eq!(json["type"], "status");
+        assert_eq!(json["data"]["state"], "play");
+        assert_eq!(json["data"]["single"], "oneshot");
+
+        let json = roundtrip(WrappedResponse::Tracks(tracks));
+        assert_eq!(json["data"][0]["artist"], serde_json::json!(["A", "B"]));
+        assert_eq!(json["data"][0]["pos"], 0);
+
+        let json = roundtrip(WrappedResponse::Subsystem(Subsystem::StoredPlaylist));
+        assert_eq!(json["data"], "stored_playlist");
+
+        roundtrip(WrappedResponse::Ok);
+        roundtrip(WrappedResponse::ListallInfo(listing));
+        roundtrip(WrappedResponse::Listall(ListallResponse {
+            files: vec!["a.flac".into()],
+            dirs: vec!["music".into()],
+            playlists: vec![],
+        }));
+        roundtrip(WrappedResponse::DatabaseVersion(DatabaseVersion(4)));
+        roundtrip(WrappedResponse::Stats(stats));
+        roundtrip(WrappedResponse::Sticker(sticker.clone()));
+        roundtrip(WrappedResponse::Stickers(vec![sticker.clone()]));
+        roundtrip(WrappedResponse::StickerMatches(vec![StickerMatch {
+            file: "a.flac".into(),
+            sticker,
+        }]));
+        roundtrip(WrappedResponse::Binary(BinaryChunk {
+            size: 3,
+            mime: Some("image/png".into()),
+            data: vec![1, 2, 3],
+        }));
+        roundtrip(WrappedResponse::Raw(vec![("Artist".into(), "A".into())]));
+        roundtrip(WrappedResponse::Fingerprint(Fingerprint("AQAA".into())));
+
+        let json = roundtrip(WrappedResponse::StoredPlaylists(vec![StoredPlaylist {
+            name: "favourites".into(),
+            last_modified: Some("2021-01-10T12:00:00Z".parse().unwrap()),
+            unknown: vec![],
+        }]));
+        assert_eq!(json["type"], "stored_playlists");
+        assert_eq!(json["data"][0]["name"], "favourites");
+
+        roundtrip(WrappedResponse::Volume(Volume(50)));
+        roundtrip(WrappedResponse::ReplayGainMode(ReplayGainMode::default()));
+        roundtrip(WrappedResponse::SongId(SongId(7)));
+        roundtrip(WrappedResponse::Mounts(vec![Mount {
+            mount: "usb".into(),
+            storage: Some("udisks://by-uuid-1234".into()),
+        }]));
+        roundtrip(WrappedResponse::Neighbors(vec![Neighbor::default()]));
+        roundtrip(WrappedResponse::Subsystems(vec![
+            Subsystem::Player,
+            Subsystem::Mixer,
+        ]));
+
+        let unknown = serde_json::json!({ "type": "from_the_future", "data": 1 });
+        assert!(matches!(
+            serde_json::from_value(unknown).unwrap(),
+            WrappedResponse::Unknown
+        ));
+
+        let invalid = serde_json::json!({ "type": "stats", "data": 1 });
+        assert!(serde_json::from_value::<WrappedResponse>(invalid).is_err());
+    }
+}
@@ -2,15 +2,22 @@ use multimap::MultiMap;
 use std::str::FromStr;
 use std::time::Duration;
 
+/// Key-value pairs of a response
+///
+/// Keys are case insensitive, since the casing of tag names varies between servers and
+/// decoder plugins, e.g. `AlbumArtist` and `ALBUMARTIST`.
 #[derive(Debug, Default)]
 pub struct RespMap {
     pub(crate) inner: MultiMap<String, String>,
+    /// Undecoded path of the file or directory, if it isn't valid UTF-8
+    pub(crate) raw_path: Option<Vec<u8>>,
 }
 
 impl RespMap {
     pub fn new() -> Self {
         Self {
             inner: MultiMap::new(),
+            raw_path: None,
         }
     }
 
@@ -19,7 +26,7 @@ impl RespMap {
     }
 
     pub fn contains_key(&self, key: &str) -> bool {
-        self.inner.contains_key(key)
+        self.inner.contains_key(&normalize(key))
     }
 
     pub fn from_string(input: String) -> Self {
@@ -28,11 +35,14 @@ impl RespMap {
         for line in input.lines() {
             if let Some((k, v)) = line.split_once(": ") {
                 log::info!("kv: {} {}", k, v);
-                map.insert(k.into(), v.into());
+                map.insert(normalize(k), v.into());
             }
         }
 
-        RespMap { inner: map }
+        RespMap {
+            inner: map,
+            raw_path: None,
+        }
     }
 
     pub fn from_iterator<'a>(input: impl Iterator<Item = &'a str>) -> Self {
@@ -41,42 +51,165 @@ impl RespMap {
         for line in input {
             if let Some((k, v)) = line.split_once(": ") {
                 log::info!("kv: {} {}", k, v);
-                map.insert(k.into(), v.into());
+                map.insert(normalize(k), v.into());
             }
         }
 
-        RespMap { inner: map }
+        RespMap {
+            inner: map,
+            raw_path: None,
+        }
     }
 
     pub fn insert(&mut self, key: &str, val: &str) {
-        self.inner.insert(key.into(), val.into());
+        self.inner.insert(normalize(key), val.into());
     }
 
+    /// Take the value of `key`, the last one if there are several
+    ///
+    /// Values left over, or that fail to parse, stay in the map and end up in
+    /// [`into_unknown`](Self::into_unknown), so that nothing in a response is lost.
     pub fn get<T: FromStr>(&mut self, key: &str) -> Option<T> {
-        self.inner
-            .remove(key)
-            .and_then(|mut v| v.pop())
-            .and_then(|v| v.parse().ok())
+        let key = normalize(key);
+        let mut values = self.inner.remove(&key)?;
+        let parsed = values.last().and_then(|v| v.parse().ok());
+
+        if parsed.is_some() {
+            values.pop();
+        }
+
+        if !values.is_empty() {
+            self.inner.insert_many(key, values);
+        }
+
+        parsed
     }
 
     pub fn get_vec(&mut self, key: &str) -> Vec<String> {
-        self.inner.remove(key).unwrap_or_default()
+        self.inner.remove(&normalize(key)).unwrap_or_default()
     }
 
     pub fn get_def<T: Default + FromStr>(&mut self, key: &str) -> T {
         self.get(key).unwrap_or_default()
     }
 
+    /// The remaining key-value pairs, ordered by key
+    ///
+    /// Conversions take the keys they know, and keep the rest with this.
+    pub fn into_unknown(self) -> Vec<(String, String)> {
+        let mut unknown: Vec<_> = self
+            .inner
+            .into_iter()
+            .flat_map(|(k, values)| values.into_iter().map(move |v| (k.clone(), v)))
+            .collect();
+
+        unknown.sort_by(|a, b| a.0.cmp(&b.0));
+        unknown
+    }
+
     pub fn as_bool(&mut self, key: &str) -> bool {
         self.get_def::<i32>(key) != 0
     }
 
+    /// Duration in seconds, None if missing or invalid
     pub fn as_duration(&mut self, key: &str) -> Option<Duration> {
-        let secs: f64 = self.get(key)?;
-        Some(Duration::from_secs_f64(secs))
+        let value: String = self.get(key)?;
+
+        match parse_duration(&value) {
+            Ok(duration) => Some(duration),
+            Err(e) => {
+                log::warn!("{}: {}", key, e);
+                self.insert(key, &value);
+                None
+            }
+        }
     }
 
     pub fn as_duration_def(&mut self, key: &str) -> Duration {
         self.as_duration(key).unwrap_or_default()
     }
 }
+
+/// Parse a duration in seconds, such as `149.029`
+///
+/// Negative, infinite and NaN values, and values too large for a `Duration`, are errors.
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, crate::Error> {
+    let invalid = || crate::Error::ValueError {
+        msg: format!("invalid duration: {}", value),
+    };
+
+    let secs: f64 = value.trim().parse().map_err(|_| invalid())?;
+    Duration::try_from_secs_f64(secs).map_err(|_| invalid())
+}
+
+/// Keys are stored in lower case
+fn normalize(key: &str) -> String {
+    key.to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn case_insensitive_keys() {
+        let mut map = RespMap::from_string("ALBUMARTIST: Abba\nTitle: Waterloo\n".into());
+
+        assert!(map.contains_key("AlbumArtist"));
+        assert_eq!(map.get::<String>("AlbumArtist").as_deref(), Some("Abba"));
+        assert_eq!(map.get::<String>("TITLE").as_deref(), Some("Waterloo"));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn invalid_and_extra_values_are_kept() {
+        let mut map = RespMap::from_string("Track: 3/12\nTitle: A\nTitle: B\nduration: x\n".into());
+
+        assert_eq!(map.get::<u32>("Track"), None);
+        assert_eq!(map.get::<String>("Title").as_deref(), Some("B"));
+        assert_eq!(map.as_duration("duration"), None);
+        assert_eq!(
+            map.into_unknown(),
+            vec![
+                ("duration".to_string(), "x".to_string()),
+                ("title".to_string(), "A".to_string()),
+                ("track".to_string(), "3/12".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(
+            parse_duration("149.029").unwrap(),
+            Duration::from_millis(149029)
+        );
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+        assert_eq!(parse_duration(" 12 ").unwrap(), Duration::from_secs(12));
+
+        for invalid in [
+            "", "-1", "-0.5", "nan", "NaN", "inf", "-inf", "1e30", "1:2", "abc",
+        ] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_duration_any_string(value in "\\PC*") {
+            let _ = parse_duration(&value);
+        }
+
+        #[test]
+        fn parse_duration_any_number(secs in proptest::num::f64::ANY) {
+            let parsed = parse_duration(&secs.to_string());
+
+            if parsed.is_ok() {
+                proptest::prop_assert!(secs.is_finite() && secs >= 0.0);
+            }
+            if (0.0..1e18).contains(&secs) {
+                proptest::prop_assert!(parsed.is_ok());
+            }
+        }
+    }
+}
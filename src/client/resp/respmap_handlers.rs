@@ -1,33 +1,57 @@
 use std::str::FromStr;
 
-use async_net::TcpStream;
-use futures_lite::{io::AsyncBufReadExt, io::BufReader, StreamExt};
-use serde::Serialize;
-
-use crate::client::resp::respmap::RespMap;
-use crate::{DatabaseVersion, Directory, Playlist, State, Stats, Status, Subsystem, Track};
+use futures_lite::io::AsyncBufRead;
+use serde::{Deserialize, Serialize};
+
+use crate::client::resp::respmap::{parse_duration, RespMap};
+use crate::client::resp::{read_line, skip_binary};
+use crate::{
+    DatabaseVersion, Directory, Error, Fingerprint, Mount, Neighbor, Output, Playlist, State,
+    Stats, Status, Sticker, StickerMatch, StoredPlaylist, Subsystem, Track,
+};
 use std::convert::TryFrom;
 
 impl From<RespMap> for Subsystem {
     fn from(mut map: RespMap) -> Self {
-        let s: String = map.get("subsystem").unwrap_or_else(|| "other".into());
+        map.get("changed").unwrap_or(Subsystem::Other)
+    }
+}
 
-        match s.as_ref() {
-            "partitions" => Subsystem::Partitions,
+impl FromStr for Subsystem {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let subsystem = match s {
+            "database" => Subsystem::Database,
             "player" => Subsystem::Player,
             "mixer" => Subsystem::Mixer,
             "options" => Subsystem::Options,
             "update" => Subsystem::Update,
-            "storedplaylist" => Subsystem::StoredPlaylist,
+            "stored_playlist" => Subsystem::StoredPlaylist,
+            "playlist" => Subsystem::Playlist,
             "output" => Subsystem::Output,
-            _ => Subsystem::Other,
-        }
+            "partition" => Subsystem::Partitions,
+            "sticker" => Subsystem::Sticker,
+            "subscription" => Subsystem::Subscription,
+            "message" => Subsystem::Message,
+            "neighbor" => Subsystem::Neighbor,
+            "mount" => Subsystem::Mount,
+            "other" => Subsystem::Other,
+            _ => return Err(crate::Error::ValueError { msg: s.into() }),
+        };
+        Ok(subsystem)
     }
 }
 
+/// Paths of the songs, directories and playlists in the database, as returned by
+/// [`MpdClient::listall`](crate::MpdClient::listall)
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct ListallResponse {
+    /// Paths of songs
     pub files: Vec<String>,
+    /// Paths of directories
     pub dirs: Vec<String>,
+    /// Paths of playlist files
     pub playlists: Vec<String>,
 }
 
@@ -51,6 +75,36 @@ impl From<RespMap> for DatabaseVersion {
     }
 }
 
+impl FromStr for Sticker {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| crate::Error::ValueError { msg: s.into() })?;
+
+        Ok(Sticker {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+}
+
+impl From<RespMap> for Sticker {
+    fn from(mut map: RespMap) -> Self {
+        map.get_def("sticker")
+    }
+}
+
+impl From<RespMap> for StickerMatch {
+    fn from(mut map: RespMap) -> Self {
+        StickerMatch {
+            file: map.get_def("file"),
+            sticker: map.get_def("sticker"),
+        }
+    }
+}
+
 impl FromStr for State {
     type Err = crate::Error;
 
@@ -65,51 +119,64 @@ impl FromStr for State {
     }
 }
 
-#[derive(Serialize, Debug)]
-/// Response from commands that returns entries with metadata and tags
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
+#[non_exhaustive]
+/// Entry of a response with songs, directories and playlists, such as `lsinfo`
 pub enum MixedResponse {
     File(Track),
     Directory(Directory),
     Playlist(Playlist),
 }
 
-pub(crate) async fn tracks(stream: &mut BufReader<TcpStream>) -> std::io::Result<Vec<Track>> {
+pub(crate) async fn tracks<R>(stream: &mut R) -> Result<Vec<Track>, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
     Ok(mixed_stream(stream).await?.files)
 }
 
 impl From<RespMap> for Directory {
     fn from(mut map: RespMap) -> Self {
-        let dir = Directory {
+        Directory {
             path: map.get_def("directory"),
+            path_raw: map.raw_path.take(),
             last_modified: map.get("Last-Modified"),
-        };
-
-        if !map.is_empty() {
-            log::warn!("Status map not empty: {:?}", map.inner);
+            unknown: map.into_unknown(),
         }
-
-        dir
     }
 }
 
 impl From<RespMap> for Playlist {
     fn from(mut map: RespMap) -> Self {
-        let playlist = Playlist {
+        Playlist {
             path: map.get_def("playlist"),
             last_modified: map.get("Last-Modified"),
-        };
-
-        if !map.is_empty() {
-            log::warn!("Status map not empty: {:?}", map.inner);
+            unknown: map.into_unknown(),
         }
+    }
+}
 
-        playlist
+impl From<RespMap> for StoredPlaylist {
+    fn from(mut map: RespMap) -> Self {
+        StoredPlaylist {
+            name: map.get_def("playlist"),
+            last_modified: map.get("Last-Modified"),
+            unknown: map.into_unknown(),
+        }
     }
 }
 
+/// Songs, directories and playlists with their metadata, as returned by
+/// [`MpdClient::listallinfo`](crate::MpdClient::listallinfo) and
+/// [`MpdClient::lsinfo`](crate::MpdClient::lsinfo)
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct ListallinfoResponse {
+    /// Songs with their tags
     pub files: Vec<Track>,
     pub dirs: Vec<Directory>,
+    /// Playlist files
     pub playlist: Vec<Playlist>,
 }
 
@@ -129,20 +196,20 @@ impl TryFrom<RespMap> for MixedResponse {
     }
 }
 
-pub async fn mixed_stream(
-    stream: &mut BufReader<TcpStream>,
-) -> std::io::Result<ListallinfoResponse> {
+pub async fn mixed_stream<R>(stream: &mut R) -> Result<ListallinfoResponse, Error>
+where
+    R: AsyncBufRead + Unpin,
+{
     let mut resvec = ListallinfoResponse {
         files: vec![],
         dirs: vec![],
         playlist: vec![],
     };
     let mut map = RespMap::new();
-    let mut lines = stream.lines();
 
-    while let Some(line) = lines.next().await {
-        let line = line?;
-        let line = line.trim();
+    while let Some(line) = read_line(stream).await? {
+        let raw = line.raw_value();
+        let line = line.text.trim();
 
         log::debug!("{}", line);
 
@@ -158,14 +225,18 @@ pub async fn mixed_stream(
             }
 
             // Add the previous record to the result vec
-            break;
+            return Ok(resvec);
         }
 
-        if !map.is_empty()
-            && (line.starts_with("directory:")
-                || line.starts_with("file:")
-                || line.starts_with("playlist:"))
-        {
+        if line.starts_with("ACK ") {
+            return Err(Error::ServerError { msg: line.into() });
+        }
+
+        // Keys are case insensitive, like in the rest of the response
+        let pair = line.split_once(": ");
+        let is_key = |name: &str| pair.is_some_and(|(k, _)| k.eq_ignore_ascii_case(name));
+
+        if !map.is_empty() && (is_key("directory") || is_key("file") || is_key("playlist")) {
             if let Ok(dtp) = MixedResponse::try_from(map) {
                 // Add the previous record to the result vec
                 match dtp {
@@ -179,35 +250,54 @@ pub async fn mixed_stream(
             map = RespMap::new();
         }
 
-        if let Some((k, v)) = line.split_once(": ") {
+        if let Some((k, v)) = pair {
+            if is_key("binary") {
+                skip_binary(stream, v).await?;
+                continue;
+            }
+
             map.insert(k, v);
+
+            if is_key("file") || is_key("directory") {
+                map.raw_path = raw.map(<[u8]>::to_vec);
+            }
         }
     }
 
-    Ok(resvec)
+    // The connection was closed before the response was complete
+    Err(Error::Disconnected)
 }
 
 impl From<RespMap> for Track {
     fn from(mut map: RespMap) -> Self {
-        let track = Track {
+        // Servers before Mpd 0.20 only send the duration in whole seconds, as `Time`
+        let time: Option<String> = map.get("Time");
+        let duration = map
+            .as_duration("duration")
+            .or_else(|| time.as_deref().and_then(|t| parse_duration(t).ok()))
+            .unwrap_or_default();
+
+        Track {
             file: map.get_def("file"),
+            file_raw: map.raw_path.take(),
             artist_sort: map.get("ArtistSort"),
             album_artist: map.get("AlbumArtist"),
             album_sort: map.get("AlbumSort"),
             album_artist_sort: map.get("AlbumArtistSort"),
             performer: map.get_vec("Performer"),
-            genre: map.get("Genre"),
+            genre: map.get_vec("Genre"),
             title: map.get("Title"),
             track: map.get("Track"),
             album: map.get("Album"),
-            artist: map.get("Artist"),
+            artist: map.get_vec("Artist"),
             pos: map.get("Pos"),
             id: map.get("Id"),
             last_modified: map.get("Last-Modified"),
+            added: map.get("Added"),
             original_date: map.get("OriginalDate"),
-            time: map.get("Time"),
+            time,
             format: map.get("Format"),
-            duration: map.as_duration_def("duration"),
+            duration,
             label: map.get("Label"),
             date: map.get("Date"),
             disc: map.get("Disc"),
@@ -218,25 +308,20 @@ impl From<RespMap> for Track {
             musicbraiz_releasetrackid: map.get("MUSICBRAINZ_RELEASETRACKID"),
             musicbraiz_workid: map.get("MUSICBRAINZ_WORKID"),
             composer: map.get_vec("Composer"),
-        };
-
-        if !map.is_empty() {
-            log::warn!("Track map not empty: {:?}", map.inner);
+            unknown: map.into_unknown(),
         }
-
-        track
     }
 }
 
 impl From<RespMap> for Status {
     fn from(mut map: RespMap) -> Self {
-        let status = Status {
+        Status {
             partition: map.get("partition"),
             volume: map.get("volume"),
             repeat: map.as_bool("repeat"),
             random: map.as_bool("random"),
             single: map.get_def("single"),
-            consume: map.as_bool("consume"),
+            consume: map.get_def("consume"),
             playlist: map.get_def("playlist"),
             playlistlength: map.get_def("playlistlength"),
             song: map.get("song"),
@@ -254,19 +339,14 @@ impl From<RespMap> for Status {
             audio: map.get("audio"),
             updating_db: map.get("updating_db"),
             error: map.get("error"),
-        };
-
-        if !map.is_empty() {
-            log::warn!("Status map not empty: {:?}", map.inner);
+            unknown: map.into_unknown(),
         }
-
-        status
     }
 }
 
 impl From<RespMap> for Stats {
     fn from(mut map: RespMap) -> Self {
-        let stats = Stats {
+        Stats {
             uptime: map.as_duration_def("uptime"),
             playtime: map.as_duration_def("playtime"),
             artists: map.get_def("artists"),
@@ -274,21 +354,190 @@ impl From<RespMap> for Stats {
             songs: map.get_def("songs"),
             db_playtime: map.as_duration_def("db_playtime"),
             db_update: map.get_def("db_update"),
-        };
+            unknown: map.into_unknown(),
+        }
+    }
+}
+
+impl From<RespMap> for Fingerprint {
+    fn from(mut map: RespMap) -> Self {
+        Fingerprint(map.get_def("chromaprint"))
+    }
+}
+
+impl From<RespMap> for Mount {
+    fn from(mut map: RespMap) -> Self {
+        Mount {
+            mount: map.get_def("mount"),
+            storage: map.get("storage"),
+        }
+    }
+}
+
+impl From<RespMap> for Neighbor {
+    fn from(mut map: RespMap) -> Self {
+        Neighbor {
+            neighbor: map.get_def("neighbor"),
+            name: map.get("name"),
+        }
+    }
+}
 
-        if !map.is_empty() {
-            log::warn!("Status map not empty: {:?}", map.inner);
+impl From<RespMap> for Output {
+    fn from(mut map: RespMap) -> Self {
+        let attributes = map
+            .get_vec("attribute")
+            .into_iter()
+            .filter_map(|attr| {
+                let (name, value) = attr.split_once('=')?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        Output {
+            id: map.get_def("outputid"),
+            name: map.get_def("outputname"),
+            plugin: map.get("plugin"),
+            enabled: map.as_bool("outputenabled"),
+            attributes,
+            unknown: map.into_unknown(),
         }
-        stats
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::client::resp::respmap::RespMap;
-    use crate::{State, Status};
+    use crate::{
+        ConsumeMode, Primary, ResponseHandler, SingleMode, SongId, SongPos, State, Status,
+        Subsystem,
+    };
     use std::time::Duration;
 
+    #[test]
+    fn parse_multi_value_tags() {
+        let mut input = "file: a.flac\nArtist: First\nArtist: Second\nGenre: Jazz\nOK\n".as_bytes();
+
+        let tracks = futures_lite::future::block_on(super::tracks(&mut input)).unwrap();
+        assert_eq!(tracks[0].artist, vec!["First", "Second"]);
+        assert_eq!(tracks[0].artist.primary(), Some("First"));
+        assert_eq!(tracks[0].genre, vec!["Jazz"]);
+    }
+
+    #[test]
+    fn parse_non_utf8_paths() {
+        let mut input =
+            &b"directory: M\xfasica\nfile: M\xfasica/a.mp3\nTitle: A\nfile: b.mp3\nOK\n"[..];
+
+        let listing = futures_lite::future::block_on(super::mixed_stream(&mut input)).unwrap();
+        assert_eq!(listing.dirs[0].path, "M\u{fffd}sica");
+        assert_eq!(listing.dirs[0].path_raw.as_deref(), Some(&b"M\xfasica"[..]));
+        assert_eq!(listing.files[0].file, "M\u{fffd}sica/a.mp3");
+        assert_eq!(
+            listing.files[0].file_raw.as_deref(),
+            Some(&b"M\xfasica/a.mp3"[..])
+        );
+        assert_eq!(listing.files[0].title.as_deref(), Some("A"));
+        assert_eq!(listing.files[1].file_raw, None);
+    }
+
+    #[test]
+    fn split_records_ignoring_case() {
+        let mut input = &b"Directory: music
+FILE: a.flac
+Title: A
+File: b.flac
+Title: B
+            Playlist: p
+OK
+"[..];
+
+        let listing = futures_lite::future::block_on(super::mixed_stream(&mut input)).unwrap();
+        assert_eq!(listing.dirs.len(), 1);
+        assert_eq!(listing.files.len(), 2);
+        assert_eq!(listing.files[1].file, "b.flac");
+        assert_eq!(listing.files[1].title.as_deref(), Some("B"));
+        assert_eq!(listing.playlist.len(), 1);
+    }
+
+    #[test]
+    fn parse_outputs() {
+        let mut input = "outputid: 0\noutputname: DAC\nplugin: alsa\noutputenabled: 1\n\
+            attribute: allowed_formats=96000:16:* dsd64:=dop\nattribute: dop=1\n\
+            outputid: 1\noutputname: Stream\noutputenabled: 0\nOK\n"
+            .as_bytes();
+
+        let outputs = futures_lite::future::block_on(crate::ListResponse::<crate::Output>::handle(
+            &mut input,
+        ))
+        .unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].plugin.as_deref(), Some("alsa"));
+        assert_eq!(outputs[0].dop(), Some(true));
+        assert_eq!(
+            outputs[0].allowed_formats(),
+            Some(vec!["96000:16:*", "dsd64:=dop"])
+        );
+        assert!(!outputs[1].enabled);
+        assert_eq!(outputs[1].dop(), None);
+    }
+
+    #[test]
+    fn parse_legacy_durations() {
+        // Response of a server before Mpd 0.20
+        let mut input = "file: a.mp3\nTime: 225\nTitle: A\nfile: b.mp3\nTime: 0\nOK\n".as_bytes();
+
+        let tracks = futures_lite::future::block_on(super::tracks(&mut input)).unwrap();
+        assert_eq!(tracks[0].duration, Duration::from_secs(225));
+        assert_eq!(tracks[0].time.as_deref(), Some("225"));
+        assert_eq!(tracks[1].duration, Duration::ZERO);
+
+        let status: Status = RespMap::from_string("elapsed: -0.5\nduration: nan\n".into()).into();
+        assert_eq!(status.elapsed, None);
+        assert_eq!(status.duration, None);
+    }
+
+    #[test]
+    fn display_from_str_roundtrip() {
+        use Subsystem::*;
+
+        for state in [State::Play, State::Stop, State::Pause] {
+            assert_eq!(state.to_string().parse::<State>().unwrap(), state);
+        }
+
+        let subsystems = [
+            Database,
+            Player,
+            Mixer,
+            Options,
+            Update,
+            StoredPlaylist,
+            Playlist,
+            Output,
+            Partitions,
+            Sticker,
+            Subscription,
+            Message,
+            Neighbor,
+            Mount,
+            Other,
+        ];
+
+        for subsystem in subsystems {
+            assert_eq!(
+                subsystem.to_string().parse::<Subsystem>().unwrap(),
+                subsystem
+            );
+        }
+    }
+
+    #[test]
+    fn parse_idle() {
+        let map = RespMap::from_string("changed: stored_playlist\n".into());
+        assert_eq!(Subsystem::from(map), Subsystem::StoredPlaylist);
+    }
+
     #[test]
     fn parse_status() {
         let input = r#"\
@@ -310,6 +559,7 @@ duration: 307.760
 audio: 44100:16:2
 nextsong: 124
 nextsongid: 125
+lastloadedplaylist: favourites
 "#;
 
         let reference = Status {
@@ -317,14 +567,14 @@ nextsongid: 125
             volume: Some(50),
             repeat: true,
             random: true,
-            single: "0".into(),
-            consume: false,
+            single: SingleMode::Off,
+            consume: ConsumeMode::Off,
             playlist: 2,
             playlistlength: 141,
-            song: Some(1),
-            songid: Some(2),
-            nextsong: Some(124),
-            nextsongid: Some(125),
+            song: Some(SongPos(1)),
+            songid: Some(SongId(2)),
+            nextsong: Some(SongPos(124)),
+            nextsongid: Some(SongId(125)),
             time: Some("149:308".into()),
             elapsed: Some(Duration::from_secs_f64(149.029)),
             duration: Some(Duration::from_secs_f64(307.76)),
@@ -336,6 +586,7 @@ nextsongid: 125
             audio: Some("44100:16:2".into()),
             updating_db: None,
             error: None,
+            unknown: vec![("lastloadedplaylist".into(), "favourites".into())],
         };
 
         let parsed = Status::from(RespMap::from_string(input.into()));
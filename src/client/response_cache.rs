@@ -0,0 +1,204 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{
+    client::{mpdclient::tag_values, resp::handlers::ResponseHandler},
+    cmd::{self, MpdCmd},
+    Error, Filter, MpdClient, Output, Stats, StoredPlaylist, Subsystem, Tag,
+};
+
+/// A cached response, with the subsystems whose changes make it stale
+struct Entry {
+    invalidated_by: Vec<Subsystem>,
+    response: Box<dyn Any + Send + Sync>,
+}
+
+/// Cache of the responses of read commands
+///
+/// Responses are kept by command line, until an idle event of a subsystem they depend on is fed
+/// to [`on_event`](ResponseCache::on_event). Useful for data that rarely changes, such as the
+/// list of artists, which is then only fetched again after the database changed.
+///
+/// Call [`clear`](ResponseCache::clear) after reconnecting, as changes made while disconnected
+/// aren't reported.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, Filter, MpdClient, ResponseCache, Tag};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let mut cache = ResponseCache::new();
+///
+///     loop {
+///         let artists = cache.list(&mut mpd, Tag::Artist, &Filter::new()).await?;
+///         println!("{} artists", artists.len());
+///
+///         cache.on_event(mpd.idle().await?);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct ResponseCache {
+    entries: HashMap<String, Entry>,
+}
+
+impl fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCache")
+            .field("entries", &self.entries.keys())
+            .finish()
+    }
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statistics of the server, fetched again after the database or the player changed
+    ///
+    /// The uptime and playtime are as of the fetch.
+    pub async fn stats(&mut self, client: &mut MpdClient) -> Result<Stats, Error> {
+        self.exec(
+            client,
+            cmd::Stats,
+            &[Subsystem::Database, Subsystem::Update, Subsystem::Player],
+        )
+        .await
+    }
+
+    /// Unique values of `tag` for songs matching `filter`, see [`MpdClient::list`]
+    pub async fn list(
+        &mut self,
+        client: &mut MpdClient,
+        tag: Tag,
+        filter: &Filter,
+    ) -> Result<Vec<String>, Error> {
+        let query = filter.to_query();
+        let list = cmd::List {
+            tag,
+            filter: query.as_deref(),
+            group: None,
+        };
+
+        let pairs = self.exec(client, list, &[Subsystem::Database]).await?;
+        Ok(tag_values(tag, pairs))
+    }
+
+    /// Stored playlists, see [`MpdClient::playlists`]
+    pub async fn playlists(
+        &mut self,
+        client: &mut MpdClient,
+    ) -> Result<Vec<StoredPlaylist>, Error> {
+        self.exec(client, cmd::ListPlaylists, &[Subsystem::StoredPlaylist])
+            .await
+    }
+
+    /// Audio outputs, see [`MpdClient::outputs`]
+    pub async fn outputs(&mut self, client: &mut MpdClient) -> Result<Vec<Output>, Error> {
+        self.exec(client, cmd::Outputs, &[Subsystem::Output]).await
+    }
+
+    /// Response of any command, kept until one of the subsystems in `invalidated_by` changes
+    pub async fn exec<C>(
+        &mut self,
+        client: &mut MpdClient,
+        cmd: C,
+        invalidated_by: &[Subsystem],
+    ) -> Result<<C::Handler as ResponseHandler>::Response, Error>
+    where
+        C: MpdCmd,
+        <C::Handler as ResponseHandler>::Response: Clone + Send + Sync + 'static,
+    {
+        let key = cmd.to_cmdline();
+
+        if let Some(response) = self.entries.get(&key).and_then(|entry| {
+            entry
+                .response
+                .downcast_ref::<<C::Handler as ResponseHandler>::Response>()
+        }) {
+            log::trace!("cached: {}", key.trim_end());
+            return Ok(response.clone());
+        }
+
+        let response = client.exec(cmd).await?;
+
+        self.entries.insert(
+            key,
+            Entry {
+                invalidated_by: invalidated_by.to_vec(),
+                response: Box::new(response.clone()),
+            },
+        );
+
+        Ok(response)
+    }
+
+    /// Handle an idle event, dropping the responses depending on `subsystem`. Returns true if
+    /// any were dropped
+    pub fn on_event(&mut self, subsystem: Subsystem) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|_, entry| !entry.invalidated_by.contains(&subsystem));
+        self.entries.len() != before
+    }
+
+    /// Drop all responses
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Number of cached responses
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ResponseCache;
+    use crate::client::mock::{run, MockServer};
+    use crate::{Filter, Subsystem, Tag};
+
+    #[test]
+    fn invalidated_by_events() {
+        let server = MockServer::new(&[
+            (&["list \"Artist\""], "Artist: A\nArtist: B\nOK\n"),
+            (&["outputs"], "outputid: 0\noutputname: alsa\nOK\n"),
+            // Fetched again after the database changed
+            (&["list \"Artist\""], "Artist: A\nOK\n"),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let mut cache = ResponseCache::new();
+            let all = Filter::new();
+
+            for _ in 0..2 {
+                let artists = cache.list(&mut client, Tag::Artist, &all).await.unwrap();
+                assert_eq!(artists, vec!["A", "B"]);
+                assert_eq!(cache.outputs(&mut client).await.unwrap().len(), 1);
+            }
+
+            assert!(!cache.on_event(Subsystem::Player));
+            assert!(cache.on_event(Subsystem::Database));
+            assert_eq!(cache.len(), 1);
+
+            let artists = cache.list(&mut client, Tag::Artist, &all).await.unwrap();
+            assert_eq!(artists, vec!["A"]);
+            cache.outputs(&mut client).await.unwrap();
+        });
+
+        server.finish();
+    }
+}
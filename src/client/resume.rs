@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::protocol::quote;
+use crate::{cmd, Error, MpdClient, SongId};
+
+/// Where playback was, saved with [`MpdClient::save_position`]
+///
+/// Serializable, for resuming audiobooks and podcasts where they were left after a restart.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SavedPosition {
+    /// Id of the song in the queue
+    pub id: SongId,
+    /// Path of the song, used when the song has another id once restored
+    pub file: String,
+    pub elapsed: Duration,
+}
+
+impl MpdClient {
+    /// The current song and its elapsed time, None if there is no current song
+    pub async fn save_position(&mut self) -> Result<Option<SavedPosition>, Error> {
+        let (status, song) = self.status_and_currentsong().await?;
+
+        Ok(song.and_then(|song| {
+            Some(SavedPosition {
+                id: song.id?,
+                file: song.file,
+                elapsed: status.elapsed.unwrap_or_default(),
+            })
+        }))
+    }
+
+    /// Play from a saved position
+    ///
+    /// The song is looked up in the queue by its path, as ids change when the queue is
+    /// rebuilt, preferring the saved id when the song is in the queue more than once. Returns
+    /// false if the song isn't in the queue.
+    pub async fn restore_position(&mut self, position: &SavedPosition) -> Result<bool, Error> {
+        let query = format!("(file == {})", quote(&position.file));
+        let songs = self.exec(cmd::PlaylistFind(&query)).await?;
+
+        let id = songs
+            .iter()
+            .filter_map(|song| song.id)
+            .find(|id| *id == position.id)
+            .or_else(|| songs.iter().find_map(|song| song.id));
+
+        match id {
+            Some(id) => {
+                self.seek_id(id, position.elapsed).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SavedPosition;
+    use crate::client::mock::{run, MockServer};
+    use crate::SongId;
+    use std::time::Duration;
+
+    const STATUS_AND_CURRENTSONG: &[&str] = &[
+        "command_list_ok_begin",
+        "status",
+        "currentsong",
+        "command_list_end",
+    ];
+
+    const PLAYLISTFIND: &[&str] = &[r#"playlistfind "(file == \"book.m4b\")""#];
+
+    fn position() -> SavedPosition {
+        SavedPosition {
+            id: SongId(7),
+            file: "book.m4b".into(),
+            elapsed: Duration::from_millis(12_500),
+        }
+    }
+
+    #[test]
+    fn save_position() {
+        let server = MockServer::new(&[
+            (
+                STATUS_AND_CURRENTSONG,
+                "state: pause\nsongid: 7\nelapsed: 12.500\nlist_OK\n\
+                 file: book.m4b\nPos: 0\nId: 7\nlist_OK\nOK\n",
+            ),
+            (
+                STATUS_AND_CURRENTSONG,
+                "state: stop\nlist_OK\nlist_OK\nOK\n",
+            ),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            assert_eq!(client.save_position().await.unwrap(), Some(position()));
+            assert_eq!(client.save_position().await.unwrap(), None);
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn restore_position() {
+        let server = MockServer::new(&[
+            // The song is twice in the queue, the saved id is preferred
+            (
+                PLAYLISTFIND,
+                "file: book.m4b\nPos: 0\nId: 4\nfile: book.m4b\nPos: 1\nId: 7\nOK\n",
+            ),
+            (&[r#"seekid "7" "12.500""#], "OK\n"),
+            // The queue was rebuilt, and the song has another id
+            (PLAYLISTFIND, "file: book.m4b\nPos: 0\nId: 9\nOK\n"),
+            (&[r#"seekid "9" "12.500""#], "OK\n"),
+            // The song was removed from the queue
+            (PLAYLISTFIND, "OK\n"),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            assert!(client.restore_position(&position()).await.unwrap());
+            assert!(client.restore_position(&position()).await.unwrap());
+            assert!(!client.restore_position(&position()).await.unwrap());
+        });
+
+        server.finish();
+    }
+}
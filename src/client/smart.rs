@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+
+use super::library::album_key;
+use crate::{cmd, AckCode, Error, Filter, MpdClient, Sort, Track};
+
+/// Songs fetched per `search`, and added per command list, by default
+const BATCH_SIZE: u32 = 500;
+
+/// Playlist generated from rules
+///
+/// The songs matching a filter, in the given order, optionally limited in number and to one
+/// song per album. The playlist is materialized into the queue or a stored playlist, and can be
+/// materialized again later to pick up changes of the database.
+///
+/// Without `one_per_album` the server does all the work, with `searchadd` and `searchaddpl`.
+/// With it, the songs are fetched `batch_size` at a time with the `window` of `search` until
+/// the limit is reached, and added in command lists of `batch_size` songs.
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::{Error, Filter, MpdClient, SmartPlaylist, Sort, SortKey, Tag, ToFilterExpr};
+///
+/// #[async_std::main]
+/// async fn main() -> Result<(), Error> {
+///     let mut mpd = MpdClient::new();
+///     mpd.connect("localhost:6600").await?;
+///
+///     let filter = Filter::new().and(Tag::Genre.equals("Jazz"));
+///     let playlist = SmartPlaylist::new(&filter)
+///         .sort(Sort::by(SortKey::Added).descending())
+///         .limit(50)
+///         .one_per_album();
+///
+///     playlist.save(&mut mpd, "New jazz").await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SmartPlaylist {
+    query: Option<String>,
+    sort: Option<Sort>,
+    limit: Option<u32>,
+    one_per_album: bool,
+    batch_size: u32,
+}
+
+impl SmartPlaylist {
+    /// Playlist of the songs matching `filter`, ignoring case
+    pub fn new(filter: &Filter) -> Self {
+        Self {
+            query: filter.to_query(),
+            sort: None,
+            limit: None,
+            one_per_album: false,
+            batch_size: BATCH_SIZE,
+        }
+    }
+
+    /// Sort the songs by a tag, in ascending order, or in the given [`Sort`] order
+    pub fn sort<S: Into<Sort>>(mut self, sort: S) -> Self {
+        self.sort = Some(sort.into());
+        self
+    }
+
+    /// Take at most `limit` songs
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Take only the first song of every album. Songs without an album tag are all taken
+    pub fn one_per_album(mut self) -> Self {
+        self.one_per_album = true;
+        self
+    }
+
+    /// Number of songs fetched per `search` and added per command list, 500 by default
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// The filter of the playlist, which the server requires for all its searches
+    fn query(&self) -> Result<&str, Error> {
+        self.query.as_deref().ok_or_else(|| Error::InvalidArgument {
+            msg: "smart playlist with an empty filter".into(),
+        })
+    }
+
+    /// The songs of the playlist
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the filter is empty.
+    pub async fn tracks(&self, client: &mut MpdClient) -> Result<Vec<Track>, Error> {
+        let query = self.query()?;
+
+        if !self.one_per_album {
+            return client
+                .exec(cmd::Search {
+                    sort: self.sort,
+                    window: self.limit.map(|limit| (0..limit).into()),
+                    ..cmd::Search::new(Some(query))
+                })
+                .await;
+        }
+
+        let mut dedupe = AlbumDedupe::new(self.limit);
+        let mut start: u32 = 0;
+
+        loop {
+            let end = start.saturating_add(self.batch_size);
+            let batch = client
+                .exec(cmd::Search {
+                    sort: self.sort,
+                    ..cmd::Search::new(Some(query)).window(start..end)
+                })
+                .await?;
+
+            let last = batch.len() < self.batch_size as usize;
+            if dedupe.extend(batch) || last || end == u32::MAX {
+                return Ok(dedupe.tracks);
+            }
+
+            start = end;
+        }
+    }
+
+    /// Add the songs of the playlist to the end of the queue
+    ///
+    /// Fails with [`Error::InvalidArgument`] if the filter is empty.
+    pub async fn add_to_queue(&self, client: &mut MpdClient) -> Result<(), Error> {
+        if !self.one_per_album {
+            client
+                .exec(cmd::SearchAdd {
+                    filter: self.query()?,
+                    sort: self.sort,
+                    window: self.limit.map(|limit| (0..limit).into()),
+                })
+                .await?;
+            return Ok(());
+        }
+
+        let tracks = self.tracks(client).await?;
+        for batch in tracks.chunks(self.batch_size as usize) {
+            let adds: Vec<_> = batch.iter().map(|t| cmd::QueueAdd(&t.file)).collect();
+            client.exec(cmd::CommandList(adds)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the stored playlist `name` with the songs of the playlist, creating it if it
+    /// doesn't exist
+    ///
+    /// Fails with [`Error::InvalidArgument`], leaving the stored playlist unchanged, if the filter is
+    /// empty.
+    pub async fn save(&self, client: &mut MpdClient, name: &str) -> Result<(), Error> {
+        let query = self.query()?;
+
+        // Fetched before deleting the playlist, so that it is kept if the search fails
+        let tracks = if self.one_per_album {
+            Some(self.tracks(client).await?)
+        } else {
+            None
+        };
+
+        match client.exec(cmd::Rm(name)).await {
+            Err(e) if e.ack().map(|ack| ack.code) == Some(AckCode::NoExist) => (),
+            res => res?,
+        }
+
+        match tracks {
+            None => {
+                client
+                    .exec(cmd::SearchAddPl {
+                        name,
+                        filter: query,
+                        sort: self.sort,
+                        window: self.limit.map(|limit| (0..limit).into()),
+                    })
+                    .await?;
+            }
+            Some(tracks) => {
+                for batch in tracks.chunks(self.batch_size as usize) {
+                    let adds: Vec<_> = batch
+                        .iter()
+                        .map(|t| cmd::PlaylistAdd { name, uri: &t.file })
+                        .collect();
+                    client.exec(cmd::CommandList(adds)).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Tracks with at most one per album, up to a limit
+struct AlbumDedupe {
+    albums: HashSet<(String, String)>,
+    tracks: Vec<Track>,
+    limit: Option<u32>,
+}
+
+impl AlbumDedupe {
+    fn new(limit: Option<u32>) -> Self {
+        Self {
+            albums: HashSet::new(),
+            tracks: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Take the tracks of albums not seen before. Returns true when the limit is reached
+    fn extend(&mut self, tracks: Vec<Track>) -> bool {
+        for track in tracks {
+            if self.is_full() {
+                break;
+            }
+
+            if track.album.is_none() || self.albums.insert(album_key(&track)) {
+                self.tracks.push(track);
+            }
+        }
+
+        self.is_full()
+    }
+
+    fn is_full(&self) -> bool {
+        self.limit
+            .is_some_and(|limit| self.tracks.len() >= limit as usize)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AlbumDedupe;
+    use crate::client::mock::{run, MockServer};
+    use crate::{ErrorKind, Filter, SmartPlaylist, Track};
+
+    fn track(file: &str, album: Option<&str>) -> Track {
+        let builder = Track::builder(file).artist("Artist");
+        match album {
+            Some(album) => builder.album(album).build(),
+            None => builder.build(),
+        }
+    }
+
+    fn files(dedupe: &AlbumDedupe) -> Vec<&str> {
+        dedupe.tracks.iter().map(|t| t.file.as_str()).collect()
+    }
+
+    #[test]
+    fn one_per_album() {
+        let mut dedupe = AlbumDedupe::new(Some(4));
+
+        assert!(!dedupe.extend(vec![
+            track("a1", Some("A")),
+            track("a2", Some("A")),
+            track("x", None),
+        ]));
+        assert_eq!(files(&dedupe), vec!["a1", "x"]);
+
+        // Albums are remembered across batches
+        assert!(dedupe.extend(vec![
+            track("a3", Some("A")),
+            track("y", None),
+            track("b1", Some("B")),
+            track("c1", Some("C")),
+        ]));
+        assert_eq!(files(&dedupe), vec!["a1", "x", "y", "b1"]);
+    }
+
+    #[test]
+    fn empty_filter() {
+        let server = MockServer::new(&[]);
+        let playlist = SmartPlaylist::new(&Filter::new()).limit(10);
+
+        run(async {
+            let mut client = server.client().await;
+
+            let err = playlist.add_to_queue(&mut client).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Usage);
+            // The stored playlist isn't deleted
+            let err = playlist.save(&mut client, "p").await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Usage);
+        });
+
+        server.finish();
+    }
+}
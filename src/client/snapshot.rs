@@ -0,0 +1,191 @@
+use crate::{
+    client::mpdclient::is_no_exist, cmd, Error, MpdClient, ServerSnapshot, SongPos, State,
+};
+
+/// Songs added to the queue per command list by `restore_queue`
+const QUEUE_BATCH: usize = 256;
+
+impl MpdClient {
+    /// The status, statistics, queue, stored playlists and outputs of the server
+    ///
+    /// Fetched in one command list, so that no other client can change the state in between.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Error, MpdClient};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mut mpd = MpdClient::new();
+    ///     mpd.connect("localhost:6600").await?;
+    ///
+    ///     let snapshot = mpd.snapshot().await?;
+    ///     println!("{}", serde_json::to_string_pretty(&snapshot).unwrap());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn snapshot(&mut self) -> Result<ServerSnapshot, Error> {
+        let (status, stats, queue, playlists, outputs) = self
+            .exec(cmd::CommandList((
+                cmd::Status,
+                cmd::Stats,
+                cmd::PlaylistInfo,
+                cmd::ListPlaylists,
+                cmd::Outputs,
+            )))
+            .await?;
+
+        Ok(ServerSnapshot {
+            status,
+            stats,
+            queue,
+            playlists,
+            outputs,
+        })
+    }
+
+    /// Replace the queue with the queue of `snapshot`, such as a snapshot of another server
+    ///
+    /// The song playing or paused in the snapshot is played or paused again at the same time.
+    /// Songs the server doesn't have are left out, and returned.
+    pub async fn restore_queue(&mut self, snapshot: &ServerSnapshot) -> Result<Vec<String>, Error> {
+        self.queue_clear().await?;
+
+        let queue = &snapshot.queue;
+        let current = snapshot.status.song.map(|pos| pos.0 as usize);
+        let mut current_pos = None;
+        let mut added = 0;
+        let mut missing = Vec::new();
+        let mut next = 0;
+
+        while next < queue.len() {
+            let batch = &queue[next..queue.len().min(next + QUEUE_BATCH)];
+            let adds: Vec<_> = batch.iter().map(|t| cmd::QueueAdd(&t.file)).collect();
+
+            // The server stops at the missing song, after adding the songs before it
+            let failed = match self.exec(cmd::CommandList(adds)).await {
+                Ok(_) => None,
+                Err(e) if is_no_exist(&e) => e.ack().map(|ack| ack.list_index as usize),
+                Err(e) => return Err(e),
+            };
+            let done = failed.map_or(batch.len(), |index| index + 1);
+
+            for (index, track) in batch[..done].iter().enumerate() {
+                if failed == Some(index) {
+                    log::warn!("Song missing on the server: {}", track.file);
+                    missing.push(track.file.clone());
+                    continue;
+                }
+
+                if current == Some(next + index) {
+                    current_pos = Some(SongPos(added));
+                }
+                added += 1;
+            }
+
+            next += done;
+        }
+
+        if let (State::Play | State::Pause, Some(pos)) = (snapshot.status.state, current_pos) {
+            let time = snapshot.status.elapsed.unwrap_or_default();
+            self.exec(cmd::Seek { pos, time }).await?;
+
+            if snapshot.status.state == State::Pause {
+                self.pause().await?;
+            }
+        }
+
+        Ok(missing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::mock::{run, MockServer};
+    use crate::{ServerSnapshot, SongPos, State, Status, Track};
+    use std::time::Duration;
+
+    #[test]
+    fn snapshot() {
+        let server = MockServer::new(&[(
+            &[
+                "command_list_ok_begin",
+                "status",
+                "stats",
+                "playlistinfo",
+                "listplaylists",
+                "outputs",
+                "command_list_end",
+            ],
+            "state: play\nlist_OK\nsongs: 2\nlist_OK\nfile: a.flac\nfile: b.flac\nlist_OK\n\
+             playlist: p\nlist_OK\noutputid: 0\noutputname: alsa\nlist_OK\nOK\n",
+        )]);
+
+        let snapshot = run(async {
+            let mut client = server.client().await;
+            client.snapshot().await.unwrap()
+        });
+        server.finish();
+
+        assert_eq!(snapshot.status.state, State::Play);
+        assert_eq!(snapshot.stats.songs, 2);
+        assert_eq!(snapshot.queue.len(), 2);
+        assert_eq!(snapshot.playlists[0].name, "p");
+        assert_eq!(snapshot.outputs[0].name, "alsa");
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: ServerSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.queue[1].file, "b.flac");
+    }
+
+    #[test]
+    fn restore_queue() {
+        let server = MockServer::new(&[
+            (&["clear"], "OK\n"),
+            (
+                &[
+                    "command_list_ok_begin",
+                    "add \"a.flac\"",
+                    "add \"b.flac\"",
+                    "add \"c.flac\"",
+                    "command_list_end",
+                ],
+                "list_OK\nACK [50@1] {add} No such song\n",
+            ),
+            (
+                &[
+                    "command_list_ok_begin",
+                    "add \"c.flac\"",
+                    "command_list_end",
+                ],
+                "list_OK\nOK\n",
+            ),
+            // c.flac is the second song of the new queue
+            (&["seek \"1\" \"12.500\""], "OK\n"),
+            (&["pause \"1\""], "OK\n"),
+        ]);
+
+        let snapshot = ServerSnapshot {
+            status: Status {
+                state: State::Pause,
+                song: Some(SongPos(2)),
+                elapsed: Some(Duration::from_millis(12_500)),
+                ..Status::default()
+            },
+            queue: ["a.flac", "b.flac", "c.flac"]
+                .iter()
+                .map(|file| Track::builder(*file).build())
+                .collect(),
+            ..ServerSnapshot::default()
+        };
+
+        let missing = run(async {
+            let mut client = server.client().await;
+            client.restore_queue(&snapshot).await.unwrap()
+        });
+        server.finish();
+
+        assert_eq!(missing, vec!["b.flac"]);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{Error, MpdClient, SingleMode, SongId, State, Subsystem, Version};
+
+/// Playback options changed by [`MpdClient::stop_after_current`], to be restored once the
+/// current song ended
+///
+/// Servers older than Mpd 0.21 don't have the oneshot single mode, so single mode is turned on
+/// instead, and repeat off. They stay so until restored with [`wait_and_restore`] or
+/// [`restore`]. Nothing needs restoring on newer servers.
+///
+/// [`wait_and_restore`]: Self::wait_and_restore
+/// [`restore`]: Self::restore
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[must_use]
+pub struct StopAfterCurrent {
+    saved: Option<Saved>,
+}
+
+/// The options before they were changed, and the song playing then
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Saved {
+    repeat: bool,
+    single: SingleMode,
+    songid: Option<SongId>,
+}
+
+impl StopAfterCurrent {
+    /// Repeat or single mode were changed, and need to be restored
+    pub fn needs_restore(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Wait until the player stops or plays another song, and restore the options
+    ///
+    /// This can take as long as the song. The options are restored also when waiting fails,
+    /// and the error of waiting is returned. If the future is dropped before it completes, the
+    /// options can still be restored with [`restore`](Self::restore).
+    pub async fn wait_and_restore(&self, client: &mut MpdClient) -> Result<(), Error> {
+        let saved = match &self.saved {
+            Some(saved) => saved,
+            None => return Ok(()),
+        };
+
+        let waited = wait_for_song_end(client, saved.songid).await;
+        let restored = self.restore(client).await;
+        waited.and(restored)
+    }
+
+    /// Restore the options right away
+    pub async fn restore(&self, client: &mut MpdClient) -> Result<(), Error> {
+        if let Some(saved) = &self.saved {
+            if saved.single != SingleMode::On {
+                client.single(saved.single).await?;
+            }
+            if saved.repeat {
+                client.repeat(true).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+async fn wait_for_song_end(client: &mut MpdClient, songid: Option<SongId>) -> Result<(), Error> {
+    loop {
+        client.idle_until(&[Subsystem::Player], None).await?;
+
+        let status = client.status().await?;
+        if status.state == State::Stop || status.songid != songid {
+            return Ok(());
+        }
+    }
+}
+
+impl MpdClient {
+    /// Stop playback when the current song ends
+    ///
+    /// Uses the oneshot single mode where supported. Older servers get single mode turned on
+    /// instead, and repeat turned off, since single mode repeats the song if repeat is enabled.
+    /// The returned [`StopAfterCurrent`] restores them once the song ended.
+    pub async fn stop_after_current(&mut self) -> Result<StopAfterCurrent, Error> {
+        let done = StopAfterCurrent { saved: None };
+
+        if self.server_version() >= Some(Version::new(0, 21, 0)) {
+            self.single(SingleMode::Oneshot).await?;
+            return Ok(done);
+        }
+
+        let status = self.status().await?;
+        if status.state == State::Stop || (!status.repeat && status.single == SingleMode::On) {
+            return Ok(done);
+        }
+
+        let stop = StopAfterCurrent {
+            saved: Some(Saved {
+                repeat: status.repeat,
+                single: status.single,
+                songid: status.songid,
+            }),
+        };
+
+        let changed = async {
+            if status.repeat {
+                self.repeat(false).await?;
+            }
+            if status.single != SingleMode::On {
+                self.single(SingleMode::On).await?;
+            }
+            Ok(())
+        }
+        .await;
+
+        match changed {
+            Ok(()) => Ok(stop),
+            Err(e) => {
+                // Undo the options that were changed, keeping the error that caused it
+                let _ = stop.restore(self).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::client::mock::{run, MockServer};
+    use crate::cmd;
+
+    #[test]
+    fn oneshot() {
+        let server = MockServer::new(&[(&["single \"oneshot\""], "OK\n")]);
+
+        run(async {
+            let mut client = server.client().await;
+            let stop = client.stop_after_current().await.unwrap();
+            assert!(!stop.needs_restore());
+            stop.wait_and_restore(&mut client).await.unwrap();
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn old_server() {
+        let server = MockServer::with_version(
+            "0.20.0",
+            &[
+                (
+                    &["status"],
+                    "repeat: 1\nsingle: 0\nstate: play\nsongid: 3\nOK\n",
+                ),
+                (&["repeat \"0\""], "OK\n"),
+                (&["single \"1\""], "OK\n"),
+                // The caller is free to send other commands
+                (&["ping"], "OK\n"),
+                (&["idle \"player\""], "changed: player\nOK\n"),
+                // Paused, the song hasn't ended yet
+                (
+                    &["status"],
+                    "repeat: 0\nsingle: 1\nstate: pause\nsongid: 3\nOK\n",
+                ),
+                (&["idle \"player\""], "changed: player\nOK\n"),
+                (&["status"], "repeat: 0\nsingle: 1\nstate: stop\nOK\n"),
+                (&["single \"0\""], "OK\n"),
+                (&["repeat \"1\""], "OK\n"),
+            ],
+        );
+
+        run(async {
+            let mut client = server.client().await;
+            let stop = client.stop_after_current().await.unwrap();
+            assert!(stop.needs_restore());
+
+            client.exec(cmd::Ping).await.unwrap();
+            stop.wait_and_restore(&mut client).await.unwrap();
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn restored_when_waiting_fails() {
+        let server = MockServer::with_version(
+            "0.20.0",
+            &[
+                (
+                    &["status"],
+                    "repeat: 1\nsingle: 0\nstate: play\nsongid: 3\nOK\n",
+                ),
+                (&["repeat \"0\""], "OK\n"),
+                (&["single \"1\""], "OK\n"),
+                (
+                    &["idle \"player\""],
+                    "ACK [4@0] {idle} you don't have permission\n",
+                ),
+                (&["single \"0\""], "OK\n"),
+                (&["repeat \"1\""], "OK\n"),
+            ],
+        );
+
+        run(async {
+            let mut client = server.client().await;
+            let stop = client.stop_after_current().await.unwrap();
+
+            let err = stop.wait_and_restore(&mut client).await.unwrap_err();
+            assert!(err.ack().is_some());
+        });
+
+        server.finish();
+    }
+
+    #[test]
+    fn restored_when_changing_fails() {
+        let server = MockServer::with_version(
+            "0.20.0",
+            &[
+                (
+                    &["status"],
+                    "repeat: 1\nsingle: 0\nstate: play\nsongid: 3\nOK\n",
+                ),
+                (&["repeat \"0\""], "OK\n"),
+                (
+                    &["single \"1\""],
+                    "ACK [4@0] {single} you don't have permission\n",
+                ),
+                (&["single \"0\""], "OK\n"),
+                (&["repeat \"1\""], "OK\n"),
+            ],
+        );
+
+        run(async {
+            let mut client = server.client().await;
+            assert!(client.stop_after_current().await.is_err());
+        });
+
+        server.finish();
+    }
+}
@@ -0,0 +1,177 @@
+use futures_lite::{stream, Stream};
+use std::collections::VecDeque;
+
+use crate::{cmd, AckCode, Error, Mount, MpdClient, Neighbor, Subsystem};
+
+/// Change of the storage of the server, reported by [`MpdClient::storage_changes`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageEvent {
+    /// Storage was mounted in the music directory
+    Mounted(Mount),
+    /// Storage was unmounted
+    Unmounted(Mount),
+    /// Storage appeared on the network
+    NeighborFound(Neighbor),
+    /// Storage disappeared from the network
+    NeighborLost(Neighbor),
+}
+
+/// Events for the entries of `new` not in `old`, and of `old` not in `new`
+fn diff<T: PartialEq + Clone>(
+    old: &[T],
+    new: &[T],
+    added: fn(T) -> StorageEvent,
+    removed: fn(T) -> StorageEvent,
+) -> Vec<StorageEvent> {
+    let gone = old
+        .iter()
+        .filter(|e| !new.contains(e))
+        .cloned()
+        .map(removed);
+    let appeared = new.iter().filter(|e| !old.contains(e)).cloned().map(added);
+    gone.chain(appeared).collect()
+}
+
+struct StorageState<'a> {
+    client: &'a mut MpdClient,
+    /// Mounts and neighbors last listed, None before the first listing
+    known: Option<(Vec<Mount>, Vec<Neighbor>)>,
+    ready: VecDeque<StorageEvent>,
+    done: bool,
+}
+
+impl StorageState<'_> {
+    async fn next_event(&mut self) -> Result<StorageEvent, Error> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Ok(event);
+            }
+
+            let (mounts, neighbors) = match &self.known {
+                Some(_) => {
+                    let changed = self
+                        .client
+                        .idle_until(&[Subsystem::Mount, Subsystem::Neighbor], None)
+                        .await?;
+
+                    if changed.is_empty() {
+                        continue;
+                    }
+
+                    (self.client.mounts().await?, self.client.neighbors().await?)
+                }
+                None => (self.client.mounts().await?, self.client.neighbors().await?),
+            };
+
+            let (old_mounts, old_neighbors) = self.known.take().unwrap_or_default();
+            self.ready.extend(diff(
+                &old_mounts,
+                &mounts,
+                StorageEvent::Mounted,
+                StorageEvent::Unmounted,
+            ));
+            self.ready.extend(diff(
+                &old_neighbors,
+                &neighbors,
+                StorageEvent::NeighborFound,
+                StorageEvent::NeighborLost,
+            ));
+            self.known = Some((mounts, neighbors));
+        }
+    }
+}
+
+impl MpdClient {
+    /// Storage mounted in the music directory. Requires Mpd 0.19
+    pub async fn mounts(&mut self) -> Result<Vec<Mount>, Error> {
+        self.exec(cmd::ListMounts).await
+    }
+
+    /// Storage found on the network, empty if the server has no neighbor plugin. Requires
+    /// Mpd 0.19
+    pub async fn neighbors(&mut self) -> Result<Vec<Neighbor>, Error> {
+        match self.exec(cmd::ListNeighbors).await {
+            Ok(neighbors) => Ok(neighbors),
+            // Servers without neighbor plugins don't know the command
+            Err(e) if e.ack().map(|ack| ack.code) == Some(AckCode::Unknown) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stream of changes of the mounted and neighboring storage
+    ///
+    /// Starts with the current storage, as `Mounted` and `NeighborFound` events, followed by
+    /// the changes as the server reports them. The stream ends after the first error.
+    pub fn storage_changes(&mut self) -> impl Stream<Item = Result<StorageEvent, Error>> + '_ {
+        let state = StorageState {
+            client: self,
+            known: None,
+            ready: VecDeque::new(),
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let event = state.next_event().await;
+            state.done = event.is_err();
+            Some((event, state))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff, StorageEvent};
+    use crate::client::mock::{run, MockServer};
+    use crate::{AckCode, Mount};
+
+    #[test]
+    fn mount_changes() {
+        let mount = |path: &str| Mount {
+            mount: path.into(),
+            storage: Some(format!("nfs://nas/{}", path)),
+        };
+
+        let events = diff(
+            &[mount(""), mount("nas")],
+            &[mount(""), mount("usb")],
+            StorageEvent::Mounted,
+            StorageEvent::Unmounted,
+        );
+
+        assert_eq!(
+            events,
+            vec![
+                StorageEvent::Unmounted(mount("nas")),
+                StorageEvent::Mounted(mount("usb"))
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbors_unknown() {
+        let server = MockServer::new(&[
+            (
+                &["listneighbors"],
+                "ACK [5@0] {} unknown command \"listneighbors\"\n",
+            ),
+            (
+                &["listneighbors"],
+                "ACK [4@0] {listneighbors} you don't have permission for \"listneighbors\"\n",
+            ),
+        ]);
+
+        run(async {
+            let mut client = server.client().await;
+
+            assert!(client.neighbors().await.unwrap().is_empty());
+            let err = client.neighbors().await.unwrap_err();
+            assert_eq!(err.ack().unwrap().code, AckCode::Permission);
+        });
+
+        server.finish();
+    }
+}
@@ -0,0 +1,240 @@
+use futures_lite::io::{AsyncBufRead, AsyncRead, BufReader};
+use futures_lite::ready;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use crate::client::fetch::ProgressCounter;
+use crate::cmd::{self, MpdCmd};
+
+/// Longest line passed to a wire tap, in bytes. Longer lines are cut
+const MAX_LINE: usize = 1024;
+
+/// Direction of a protocol line passed to a wire tap
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum WireDirection {
+    /// Command line sent to the server
+    Sent,
+    /// Response line received from the server
+    Received,
+}
+
+pub(crate) type WireTapFn = Arc<dyn Fn(WireDirection, &str) + Send + Sync>;
+
+/// Passes the protocol lines of a connection to a callback
+///
+/// Passwords are replaced with `***`, lines longer than [`MAX_LINE`] are cut, and binary data
+/// is replaced with a note of its size.
+pub(crate) struct WireTap {
+    callback: WireTapFn,
+    /// Received part of the current line, at most `MAX_LINE` bytes
+    line: Vec<u8>,
+    /// Bytes of the current line left out
+    cut: usize,
+    /// Bytes of binary data left to skip, including the newline after it
+    binary: usize,
+}
+
+impl WireTap {
+    pub(crate) fn new(callback: WireTapFn) -> Self {
+        Self {
+            callback,
+            line: Vec::new(),
+            cut: 0,
+            binary: 0,
+        }
+    }
+
+    /// Pass the lines of a command, or command list, that is sent
+    pub(crate) fn sent(&self, lines: &str) {
+        for line in lines.lines() {
+            let redacted;
+            let line = match line.strip_prefix(cmd::Password::CMD) {
+                Some(rest) if rest.starts_with(' ') => {
+                    redacted = format!("{} \"***\"", cmd::Password::CMD);
+                    &redacted
+                }
+                _ => line,
+            };
+
+            match line.char_indices().nth(MAX_LINE) {
+                Some((end, _)) => self.emit(WireDirection::Sent, &line[..end], line.len() - end),
+                None => self.emit(WireDirection::Sent, line, 0),
+            }
+        }
+    }
+
+    /// Pass the complete lines of received data, keeping the last line until it is complete
+    fn received(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            if self.binary > 0 {
+                let skip = self.binary.min(data.len());
+                self.binary -= skip;
+                data = &data[skip..];
+                continue;
+            }
+
+            let (part, complete) = match data.iter().position(|&b| b == b'\n') {
+                Some(end) => (&data[..end], Some(end + 1)),
+                None => (data, None),
+            };
+
+            let room = MAX_LINE.saturating_sub(self.line.len()).min(part.len());
+            self.line.extend_from_slice(&part[..room]);
+            self.cut += part.len() - room;
+
+            match complete {
+                Some(end) => {
+                    self.finish_line();
+                    data = &data[end..];
+                }
+                None => break,
+            }
+        }
+    }
+
+    fn finish_line(&mut self) {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.emit(WireDirection::Received, &line, self.cut);
+
+        if let Some(len) = line.strip_prefix("binary: ") {
+            if let Ok(len) = len.trim().parse::<usize>() {
+                let note = format!("<{} bytes of binary data>", len);
+                self.emit(WireDirection::Received, &note, 0);
+                self.binary = len + 1;
+            }
+        }
+
+        self.line.clear();
+        self.cut = 0;
+    }
+
+    fn emit(&self, direction: WireDirection, line: &str, cut: usize) {
+        if cut > 0 {
+            (self.callback)(direction, &format!("{}... ({} more bytes)", line, cut));
+        } else {
+            (self.callback)(direction, line);
+        }
+    }
+}
+
+/// Reader of responses, passing what is read to the wire tap and progress counter of the
+/// client if it has them
+pub(crate) struct TapReader<'a, R> {
+    pub(crate) inner: &'a mut BufReader<R>,
+    pub(crate) tap: Option<&'a mut WireTap>,
+    pub(crate) progress: Option<&'a mut ProgressCounter>,
+    /// Bytes read through the reader
+    pub(crate) bytes: usize,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for TapReader<'_, R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let n = ready!(Pin::new(&mut *this.inner).poll_read(cx, buf))?;
+
+        if let Some(tap) = this.tap.as_deref_mut() {
+            tap.received(&buf[..n]);
+        }
+        if let Some(progress) = this.progress.as_deref_mut() {
+            progress.received(&buf[..n]);
+        }
+        this.bytes += n;
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for TapReader<'_, R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        Pin::new(&mut *self.get_mut().inner).poll_fill_buf(cx)
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+
+        if let Some(tap) = this.tap.as_deref_mut() {
+            tap.received(&this.inner.buffer()[..amt]);
+        }
+        if let Some(progress) = this.progress.as_deref_mut() {
+            progress.received(&this.inner.buffer()[..amt]);
+        }
+        this.bytes += amt;
+
+        Pin::new(&mut *this.inner).consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TapReader, WireDirection, WireTap, MAX_LINE};
+    use crate::{BinaryResponse, ResponseHandler};
+    use futures_lite::future::block_on;
+    use futures_lite::io::BufReader;
+    use std::sync::{Arc, Mutex};
+
+    /// Tap keeping the lines passed to it, prefixed with `>` when sent and `<` when received
+    fn tap() -> (WireTap, Arc<Mutex<Vec<String>>>) {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let seen = lines.clone();
+        let tap = WireTap::new(Arc::new(move |direction, line: &str| {
+            let prefix = match direction {
+                WireDirection::Sent => '>',
+                WireDirection::Received => '<',
+            };
+            seen.lock().unwrap().push(format!("{} {}", prefix, line))
+        }));
+        (tap, lines)
+    }
+
+    #[test]
+    fn sent_lines() {
+        let (tap, lines) = tap();
+        tap.sent("command_list_ok_begin\npassword \"hunter2\"\nstatus\ncommand_list_end\n");
+        tap.sent(&format!("add \"{}\"\n", "a".repeat(2 * MAX_LINE)));
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(
+            lines[..4],
+            [
+                "> command_list_ok_begin",
+                "> password \"***\"",
+                "> status",
+                "> command_list_end"
+            ]
+        );
+        assert!(lines[4].ends_with("aaa... (1030 more bytes)"));
+    }
+
+    #[test]
+    fn received_lines() {
+        let (mut tap, lines) = tap();
+        let resp = b"size: 8\ntype: image/png\nbinary: 8\nOK\nabcd\n\nOK\n";
+        let mut reader = BufReader::with_capacity(5, &resp[..]);
+
+        let chunk = block_on(BinaryResponse::handle(&mut TapReader {
+            inner: &mut reader,
+            tap: Some(&mut tap),
+            progress: None,
+            bytes: 0,
+        }))
+        .unwrap();
+        assert_eq!(chunk.data, b"OK\nabcd\n");
+
+        assert_eq!(
+            *lines.lock().unwrap(),
+            vec![
+                "< size: 8",
+                "< type: image/png",
+                "< binary: 8",
+                "< <8 bytes of binary data>",
+                "< OK"
+            ]
+        );
+    }
+}
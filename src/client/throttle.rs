@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use crate::{client::backend::sleep, MpdClient};
+
+/// Limit of the rate commands are sent at, set with [`MpdClientBuilder::rate_limit`]
+///
+/// A token bucket: every command takes a token, and tokens are added at a steady rate up to
+/// the size of the bucket, which is the number of commands that can be sent in a burst. Commands
+/// sent without a token left wait for one, instead of failing. A command list takes one token.
+///
+/// # Example
+/// ```
+/// use async_mpd::{MpdClient, RateLimit};
+///
+/// // At most 20 commands a second, with bursts of up to 50 commands
+/// let mpd = MpdClient::builder()
+///     .rate_limit(RateLimit::per_second(20).burst(50))
+///     .build();
+/// ```
+///
+/// [`MpdClientBuilder::rate_limit`]: crate::MpdClientBuilder::rate_limit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    per_second: u32,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// `commands` commands a second, in bursts of up to `commands` commands
+    pub fn per_second(commands: u32) -> Self {
+        let commands = commands.max(1);
+
+        Self {
+            per_second: commands,
+            burst: commands,
+        }
+    }
+
+    /// Commands that can be sent at once, after sending none for a while
+    pub fn burst(mut self, commands: u32) -> Self {
+        self.burst = commands.max(1);
+        self
+    }
+}
+
+/// Tokens left of a [`RateLimit`]
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    /// Negative when commands are waiting for tokens
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            limit,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Take a token at `now`, returning how long to wait for it
+    fn take(&mut self, now: Instant) -> Duration {
+        let rate = self.limit.per_second as f64;
+        let elapsed = now.saturating_duration_since(self.refilled);
+
+        self.refilled = now;
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * rate).min(self.limit.burst as f64);
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+}
+
+impl MpdClient {
+    /// Wait until the rate limit allows sending a command
+    pub(crate) async fn throttle(&mut self) {
+        let delay = match &mut self.throttle {
+            Some(bucket) => bucket.take(Instant::now()),
+            None => return,
+        };
+
+        if !delay.is_zero() {
+            log::debug!("Throttled for {} ms", delay.as_millis());
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RateLimit, TokenBucket};
+    use std::time::Duration;
+
+    #[test]
+    fn token_bucket() {
+        let mut bucket = TokenBucket::new(RateLimit::per_second(10).burst(2));
+        let start = bucket.refilled;
+        let ms = |ms| start + Duration::from_millis(ms);
+
+        // The burst goes through, the commands after it queue up
+        assert_eq!(bucket.take(start), Duration::ZERO);
+        assert_eq!(bucket.take(start), Duration::ZERO);
+        assert_eq!(bucket.take(start), Duration::from_millis(100));
+        assert_eq!(bucket.take(start), Duration::from_millis(200));
+
+        // Waiting refills the bucket, up to the burst
+        assert_eq!(bucket.take(ms(300)), Duration::ZERO);
+        assert_eq!(bucket.take(ms(10_000)), Duration::ZERO);
+        assert_eq!(bucket.take(ms(10_000)), Duration::ZERO);
+        assert_eq!(bucket.take(ms(10_000)), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::client::backend::{self, TcpStream};
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use socket2::TcpKeepalive;
+use std::io;
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Options of the connection, set with [`MpdClientBuilder`](crate::MpdClientBuilder)
+#[derive(Clone, Debug)]
+pub(crate) struct SocketOptions {
+    /// Send commands right away instead of waiting for more data to send
+    pub(crate) nodelay: bool,
+    /// Idle time before the OS starts probing a TCP connection, no probes if None
+    pub(crate) keepalive: Option<Duration>,
+    /// Size of the receive buffer of TCP sockets, the OS default if None
+    pub(crate) recv_buffer_size: Option<usize>,
+    /// Capacity of the buffer responses are read through
+    pub(crate) read_buffer_size: usize,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: None,
+            recv_buffer_size: None,
+            read_buffer_size: 8 * 1024,
+        }
+    }
+}
+
+impl SocketOptions {
+    /// Set the options of a connected TCP socket
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = backend::sock_ref(stream);
+
+        socket.set_tcp_nodelay(self.nodelay)?;
+
+        if let Some(time) = self.keepalive {
+            socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+        }
+
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Byte stream to a server provided by the user of the crate
+pub(crate) trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// Connection to the server
+pub(crate) enum Transport {
+    Tcp(TcpStream),
+    /// Stream given to [`MpdClient::connect_stream`](crate::MpdClient::connect_stream)
+    Stream(Box<dyn Io>),
+    #[cfg(unix)]
+    Unix(crate::client::backend::UnixStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<futures_rustls::client::TlsStream<TcpStream>>),
+}
+
+impl Transport {
+    /// Secure `stream` with TLS, verifying that the server certificate is valid for `domain`
+    #[cfg(feature = "tls")]
+    pub(crate) async fn tls(stream: TcpStream, domain: &str) -> io::Result<Transport> {
+        use futures_rustls::{
+            pki_types::ServerName,
+            rustls::{crypto::ring, ClientConfig, RootCertStore},
+            TlsConnector,
+        };
+        use std::convert::TryFrom;
+        use std::sync::Arc;
+
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let name = ServerName::try_from(domain.to_string())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(name, stream)
+            .await?;
+
+        Ok(Transport::Tls(Box::new(stream)))
+    }
+}
+
+impl Transport {
+    /// Streams that [`shutdown`](Self::shutdown) leaves alone, which are closed when dropped
+    /// or with `AsyncWriteExt::close`
+    pub(crate) fn closes_on_drop(&self) -> bool {
+        matches!(self, Transport::Stream(_)) || cfg!(feature = "backend-tokio")
+    }
+
+    /// Shut down both directions of the connection. Streams given by the user are closed when
+    /// dropped
+    pub(crate) fn shutdown(&self) -> io::Result<()> {
+        match self {
+            Transport::Tcp(s) => s.shutdown(Shutdown::Both),
+            Transport::Stream(_) => Ok(()),
+            #[cfg(unix)]
+            Transport::Unix(s) => s.shutdown(Shutdown::Both),
+            #[cfg(feature = "tls")]
+            Transport::Tls(s) => s.get_ref().0.shutdown(Shutdown::Both),
+        }
+    }
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            Transport::Stream(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_read(cx, buf),
+            #[cfg(feature = "tls")]
+            Transport::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            Transport::Stream(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_write(cx, buf),
+            #[cfg(feature = "tls")]
+            Transport::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_flush(cx),
+            Transport::Stream(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_flush(cx),
+            #[cfg(feature = "tls")]
+            Transport::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(s) => Pin::new(s).poll_close(cx),
+            Transport::Stream(s) => Pin::new(s).poll_close(cx),
+            #[cfg(unix)]
+            Transport::Unix(s) => Pin::new(s).poll_close(cx),
+            #[cfg(feature = "tls")]
+            Transport::Tls(s) => Pin::new(s).poll_close(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{cmd, ConnectionState, MpdClient, ServerAddress};
+    use futures_lite::future::block_on;
+    use futures_lite::io::{AsyncRead, AsyncWrite, Cursor};
+    use std::io;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    /// Stream replying with canned responses, and keeping what is written to it
+    struct Bridge {
+        responses: Cursor<&'static [u8]>,
+        sent: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl AsyncRead for Bridge {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            Pin::new(&mut self.responses).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Bridge {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.sent.lock().unwrap().extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn connect_stream() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let bridge = Bridge {
+            responses: Cursor::new(b"OK MPD 0.23.5\nvolume: 40\nstate: play\nOK\n"),
+            sent: sent.clone(),
+        };
+
+        let mut client = MpdClient::new();
+        block_on(client.connect_stream(bridge, "bridge")).unwrap();
+
+        let status = block_on(client.exec(cmd::Status)).unwrap();
+        assert_eq!(status.volume, Some(40));
+        assert_eq!(&*sent.lock().unwrap(), b"status\n");
+
+        match client.connection_state() {
+            ConnectionState::Connected { server, .. } => {
+                assert_eq!(server, ServerAddress::Stream("bridge".into()))
+            }
+            state => panic!("{:?}", state),
+        }
+
+        block_on(client.close()).unwrap();
+        assert!(block_on(client.reconnect()).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use crate::{
+    client::resp::respmap_handlers::ListallinfoResponse, Directory, Error, MpdClient, Playlist,
+    Track,
+};
+
+/// Directory in a [`FileTree`]
+#[derive(Clone, Debug, Default)]
+pub struct DirNode {
+    pub directory: Directory,
+    /// Paths of the subdirectories
+    pub subdirs: Vec<String>,
+    pub files: Vec<Track>,
+    pub playlists: Vec<Playlist>,
+    /// Set when the contents of the directory have been fetched
+    pub loaded: bool,
+}
+
+impl DirNode {
+    /// Number of subdirectories, songs and playlists in the directory, None if its contents
+    /// haven't been fetched
+    ///
+    /// Every directory of a tree built from `listallinfo` has a count, while a tree loaded a
+    /// directory at a time has one for the loaded directories only.
+    pub fn entry_count(&self) -> Option<usize> {
+        self.loaded
+            .then(|| self.subdirs.len() + self.files.len() + self.playlists.len())
+    }
+}
+
+/// Directory tree of the music database
+///
+/// The tree can be built in one pass from a `listallinfo` response, or a directory at a time
+/// with [`FileTree::load`], which uses `lsinfo`.
+#[derive(Clone, Debug)]
+pub struct FileTree {
+    dirs: HashMap<String, DirNode>,
+}
+
+impl Default for FileTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileTree {
+    /// Tree with an empty root directory, which has the path ""
+    pub fn new() -> Self {
+        let mut dirs = HashMap::new();
+        dirs.insert(String::new(), DirNode::default());
+        Self { dirs }
+    }
+
+    /// Build the tree from the response of a recursive listallinfo of the root directory
+    pub fn from_listallinfo(resp: ListallinfoResponse) -> Self {
+        let mut tree = Self::new();
+        tree.insert(resp);
+
+        for dir in tree.dirs.values_mut() {
+            dir.loaded = true;
+        }
+
+        tree
+    }
+
+    /// Add the contents of the directory `path`, as returned by lsinfo
+    pub fn insert_listing(&mut self, path: &str, resp: ListallinfoResponse) {
+        self.insert(resp);
+        self.dir_mut(path).loaded = true;
+    }
+
+    /// Directory at `path`
+    pub fn get(&self, path: &str) -> Option<&DirNode> {
+        self.dirs.get(path.trim_matches('/'))
+    }
+
+    /// The root directory
+    pub fn root(&self) -> &DirNode {
+        &self.dirs[""]
+    }
+
+    /// Subdirectories of the directory at `path`
+    pub fn children<'a>(&'a self, path: &str) -> impl Iterator<Item = &'a DirNode> + 'a {
+        self.get(path)
+            .into_iter()
+            .flat_map(|dir| dir.subdirs.iter())
+            .filter_map(move |p| self.dirs.get(p))
+    }
+
+    /// Parent directory of the directory at `path`
+    pub fn parent(&self, path: &str) -> Option<&DirNode> {
+        let path = path.trim_matches('/');
+
+        if path.is_empty() {
+            None
+        } else {
+            self.get(parent_path(path))
+        }
+    }
+
+    /// Fetch the contents of the directory `path` with lsinfo, unless already loaded
+    pub async fn load(&mut self, client: &mut MpdClient, path: &str) -> Result<&DirNode, Error> {
+        let path = path.trim_matches('/');
+
+        if !self.get(path).is_some_and(|dir| dir.loaded) {
+            let arg = if path.is_empty() { None } else { Some(path) };
+            let resp = client.lsinfo(arg).await?;
+            self.insert_listing(path, resp);
+        }
+
+        Ok(&self.dirs[path])
+    }
+
+    fn insert(&mut self, resp: ListallinfoResponse) {
+        for directory in resp.dirs {
+            let path = directory.path.clone();
+            self.dir_mut(&path).directory = directory;
+        }
+
+        for track in resp.files {
+            let parent = parent_path(&track.file).to_string();
+            self.dir_mut(&parent).files.push(track);
+        }
+
+        for playlist in resp.playlist {
+            let parent = parent_path(&playlist.path).to_string();
+            self.dir_mut(&parent).playlists.push(playlist);
+        }
+    }
+
+    /// Get the directory at `path`, creating it and its parents as needed
+    fn dir_mut(&mut self, path: &str) -> &mut DirNode {
+        if !self.dirs.contains_key(path) {
+            let parent = parent_path(path).to_string();
+            self.dir_mut(&parent).subdirs.push(path.to_string());
+
+            let dir = DirNode {
+                directory: Directory {
+                    path: path.to_string(),
+                    ..Directory::default()
+                },
+                ..DirNode::default()
+            };
+            self.dirs.insert(path.to_string(), dir);
+        }
+
+        self.dirs.get_mut(path).unwrap()
+    }
+}
+
+fn parent_path(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(parent, _)| parent)
+}
+
+#[cfg(test)]
+mod test {
+    use super::FileTree;
+    use crate::client::resp::respmap_handlers::ListallinfoResponse;
+    use crate::{Directory, Track};
+
+    fn dir(path: &str) -> Directory {
+        Directory {
+            path: path.into(),
+            ..Directory::default()
+        }
+    }
+
+    #[test]
+    fn tree_from_listallinfo() {
+        let resp = ListallinfoResponse {
+            files: vec![
+                Track::builder("a/b/1.flac").build(),
+                Track::builder("a/2.flac").build(),
+                Track::builder("3.flac").build(),
+            ],
+            dirs: vec![dir("a"), dir("a/b")],
+            playlist: vec![],
+        };
+
+        let tree = FileTree::from_listallinfo(resp);
+
+        assert_eq!(tree.root().files.len(), 1);
+        assert_eq!(tree.root().subdirs, vec!["a"]);
+
+        let children: Vec<_> = tree.children("a").map(|d| &d.directory.path).collect();
+        assert_eq!(children, vec!["a/b"]);
+        assert_eq!(tree.get("a/b").unwrap().files[0].file, "a/b/1.flac");
+        assert_eq!(tree.parent("a/b").unwrap().directory.path, "a");
+        assert!(tree.get("a/b").unwrap().loaded);
+        assert_eq!(tree.get("a").unwrap().entry_count(), Some(2));
+        assert_eq!(tree.root().entry_count(), Some(2));
+    }
+
+    #[test]
+    fn tree_incremental() {
+        let mut tree = FileTree::new();
+
+        tree.insert_listing(
+            "",
+            ListallinfoResponse {
+                dirs: vec![dir("a")],
+                ..ListallinfoResponse::default()
+            },
+        );
+
+        assert!(tree.root().loaded);
+        assert!(!tree.get("a").unwrap().loaded);
+        assert_eq!(tree.get("a").unwrap().entry_count(), None);
+
+        tree.insert_listing(
+            "a",
+            ListallinfoResponse {
+                files: vec![Track::builder("a/1.flac").build()],
+                ..ListallinfoResponse::default()
+            },
+        );
+
+        assert!(tree.get("a").unwrap().loaded);
+        assert_eq!(tree.get("a").unwrap().files.len(), 1);
+        assert_eq!(tree.get("a").unwrap().entry_count(), Some(1));
+        assert_eq!(tree.root().subdirs, vec!["a"]);
+    }
+}
@@ -0,0 +1,198 @@
+#[cfg(unix)]
+use std::path::PathBuf;
+
+use crate::{Error, MpdClient, SecretString};
+
+/// Port used when a connection string doesn't have one
+pub(crate) const DEFAULT_PORT: u16 = 6600;
+
+/// Where to connect, according to a connection string
+#[derive(Debug, PartialEq)]
+pub(crate) enum UrlAddress {
+    /// Host and port
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// Parsed connection string
+#[derive(Debug, PartialEq)]
+pub(crate) struct ConnectionUrl {
+    pub(crate) address: UrlAddress,
+    pub(crate) password: Option<SecretString>,
+    /// Domain to verify the certificate against, when connecting with TLS
+    pub(crate) tls_domain: Option<String>,
+}
+
+impl ConnectionUrl {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidArgument {
+            msg: format!("{}: {}", msg, url),
+        };
+
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| invalid("missing scheme"))?;
+
+        // The password is everything before the last `@` of the authority
+        let authority_end = rest.find('/').unwrap_or(rest.len());
+        let (password, rest) = match rest[..authority_end].rfind('@') {
+            Some(at) => (Some(SecretString::from(&rest[..at])), &rest[at + 1..]),
+            None => (None, rest),
+        };
+
+        match scheme {
+            "mpd" | "mpds" => {
+                let host = rest.trim_end_matches('/');
+                if host.is_empty() {
+                    return Err(invalid("missing host"));
+                }
+
+                // An IPv6 address is in brackets, a colon after them starts the port
+                let port_colon = host
+                    .rfind(':')
+                    .filter(|colon| host.rfind(']').is_none_or(|bracket| *colon > bracket));
+
+                let (domain, address) = match port_colon {
+                    Some(colon) => (&host[..colon], host.to_string()),
+                    None => (host, format!("{}:{}", host, DEFAULT_PORT)),
+                };
+
+                let tls_domain = if scheme == "mpds" {
+                    Some(
+                        domain
+                            .trim_start_matches('[')
+                            .trim_end_matches(']')
+                            .to_string(),
+                    )
+                } else {
+                    None
+                };
+
+                Ok(ConnectionUrl {
+                    address: UrlAddress::Tcp(address),
+                    password,
+                    tls_domain,
+                })
+            }
+            #[cfg(unix)]
+            "mpd+unix" => {
+                if !rest.starts_with('/') {
+                    return Err(invalid("socket path must be absolute"));
+                }
+
+                Ok(ConnectionUrl {
+                    address: UrlAddress::Unix(PathBuf::from(rest)),
+                    password,
+                    tls_domain: None,
+                })
+            }
+            _ => Err(invalid("unsupported scheme")),
+        }
+    }
+}
+
+impl MpdClient {
+    /// Connect using a connection string
+    ///
+    /// Supported forms are
+    /// - `mpd://[password@]host[:port]`
+    /// - `mpds://[password@]host[:port]`, with TLS. Requires the `tls` feature
+    /// - `mpd+unix://[password@]/path/to/socket`
+    ///
+    /// The port defaults to 6600. A password in the string is used for later reconnects as well.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Error, MpdClient};
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mut mpd = MpdClient::new();
+    ///     mpd.connect_url("mpd://secret@localhost:6600").await?;
+    ///
+    ///     println!("{:?}", mpd.status().await?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn connect_url(&mut self, url: &str) -> Result<String, Error> {
+        let url = ConnectionUrl::parse(url)?;
+        self.connect_to(url).await
+    }
+
+    pub(crate) async fn connect_to(&mut self, url: ConnectionUrl) -> Result<String, Error> {
+        if url.password.is_some() {
+            self.password = url.password;
+        }
+
+        #[cfg(feature = "tls")]
+        {
+            self.tls_domain = url.tls_domain;
+        }
+        #[cfg(not(feature = "tls"))]
+        if url.tls_domain.is_some() {
+            return Err(Error::InvalidArgument {
+                msg: "mpds:// requires the tls feature".into(),
+            });
+        }
+
+        match url.address {
+            UrlAddress::Tcp(addr) => self.connect(addr.as_str()).await,
+            #[cfg(unix)]
+            UrlAddress::Unix(path) => self.connect_unix(path).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn parse_url() {
+        assert_eq!(
+            ConnectionUrl::parse("mpd://localhost").unwrap(),
+            ConnectionUrl {
+                address: UrlAddress::Tcp("localhost:6600".into()),
+                password: None,
+                tls_domain: None,
+            }
+        );
+        assert_eq!(
+            ConnectionUrl::parse("mpds://p@ss@example.com:6601/").unwrap(),
+            ConnectionUrl {
+                address: UrlAddress::Tcp("example.com:6601".into()),
+                password: Some("p@ss".into()),
+                tls_domain: Some("example.com".into()),
+            }
+        );
+        assert_eq!(
+            ConnectionUrl::parse("mpd://[::1]").unwrap().address,
+            UrlAddress::Tcp("[::1]:6600".into())
+        );
+        assert_eq!(
+            ConnectionUrl::parse("mpd://[::1]:6601").unwrap().address,
+            UrlAddress::Tcp("[::1]:6601".into())
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            ConnectionUrl::parse("mpd+unix:///run/mpd/socket").unwrap(),
+            ConnectionUrl {
+                address: UrlAddress::Unix("/run/mpd/socket".into()),
+                password: None,
+                tls_domain: None,
+            }
+        );
+
+        assert!(ConnectionUrl::parse("localhost:6600").is_err());
+
+        let err = ConnectionUrl::parse("http://localhost").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Usage);
+        assert_eq!(
+            err.to_string(),
+            "Invalid argument: unsupported scheme: http://localhost"
+        );
+        assert!(ConnectionUrl::parse("mpd://").is_err());
+    }
+}
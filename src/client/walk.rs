@@ -0,0 +1,79 @@
+use futures_lite::{stream, Stream};
+
+use crate::{Directory, Error, ListallinfoResponse, MpdClient};
+
+struct WalkState<'a> {
+    client: &'a mut MpdClient,
+    /// Directories left to list, the next one last
+    pending: Vec<Directory>,
+    done: bool,
+}
+
+impl MpdClient {
+    /// Stream of the directories below `path`, each with its contents
+    ///
+    /// Directories are listed one at a time with `lsinfo`, as the stream is advanced, depth
+    /// first starting with `path` itself. Unlike a recursive `listallinfo`, neither the server
+    /// nor the client has to hold the whole library at once. The stream ends after the first
+    /// error.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use async_mpd::{Error, MpdClient};
+    /// use futures_lite::StreamExt;
+    ///
+    /// #[async_std::main]
+    /// async fn main() -> Result<(), Error> {
+    ///     let mut mpd = MpdClient::new();
+    ///     mpd.connect("localhost:6600").await?;
+    ///
+    ///     let mut dirs = Box::pin(mpd.walk(""));
+    ///
+    ///     while let Some(entry) = dirs.next().await {
+    ///         let (dir, contents) = entry?;
+    ///         println!("{}: {} songs", dir.path, contents.files.len());
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn walk(
+        &mut self,
+        path: &str,
+    ) -> impl Stream<Item = Result<(Directory, ListallinfoResponse), Error>> + '_ {
+        let root = Directory {
+            path: path.trim_matches('/').to_string(),
+            ..Directory::default()
+        };
+
+        let state = WalkState {
+            client: self,
+            pending: vec![root],
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+
+            let dir = state.pending.pop()?;
+            let arg = if dir.path.is_empty() {
+                None
+            } else {
+                Some(dir.path.as_str())
+            };
+
+            match state.client.lsinfo(arg).await {
+                Ok(contents) => {
+                    state.pending.extend(contents.dirs.iter().rev().cloned());
+                    Some((Ok((dir, contents)), state))
+                }
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(e), state))
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::{Duration, Instant};
+
+use crate::{client::backend::sleep_until, Error, MpdClient, Stats, Status};
+
+/// Changes of the server statistics between two polls of a [`StatsWatcher`]
+#[derive(Clone, Debug)]
+pub struct StatsDelta {
+    pub stats: Stats,
+    pub status: Status,
+    /// Change of the number of songs in the database
+    pub songs: i64,
+    /// Change of the number of artists in the database
+    pub artists: i64,
+    /// Change of the number of albums in the database
+    pub albums: i64,
+    /// Change of the total playtime of the database, in seconds
+    pub db_playtime: f64,
+    /// Time spent playing since the previous poll
+    pub played: Duration,
+    /// The database was updated since the previous poll
+    pub db_updated: bool,
+    /// The server was restarted since the previous poll, seen by its uptime going down
+    pub restarted: bool,
+    /// When the poll was answered
+    pub received_at: Instant,
+}
+
+impl StatsDelta {
+    fn new(prev: Option<&Stats>, stats: Stats, status: Status, received_at: Instant) -> Self {
+        let prev = match prev {
+            Some(prev) => prev,
+            None => {
+                return StatsDelta {
+                    stats,
+                    status,
+                    songs: 0,
+                    artists: 0,
+                    albums: 0,
+                    db_playtime: 0.0,
+                    played: Duration::ZERO,
+                    db_updated: false,
+                    restarted: false,
+                    received_at,
+                }
+            }
+        };
+
+        let restarted = stats.uptime < prev.uptime;
+        let played = if restarted {
+            stats.playtime
+        } else {
+            stats.playtime.saturating_sub(prev.playtime)
+        };
+
+        StatsDelta {
+            songs: i64::from(stats.songs) - i64::from(prev.songs),
+            artists: i64::from(stats.artists) - i64::from(prev.artists),
+            albums: i64::from(stats.albums) - i64::from(prev.albums),
+            db_playtime: stats.db_playtime.as_secs_f64() - prev.db_playtime.as_secs_f64(),
+            played,
+            db_updated: stats.db_update != prev.db_update,
+            restarted,
+            received_at,
+            stats,
+            status,
+        }
+    }
+}
+
+/// Periodic poller of `stats` and `status`
+///
+/// Each poll is reported as the changes since the previous one. The first poll has no previous
+/// one to compare with, and reports no changes.
+#[derive(Clone, Debug)]
+pub struct StatsWatcher {
+    interval: Duration,
+    next_poll: Option<Instant>,
+    last: Option<Stats>,
+}
+
+impl StatsWatcher {
+    /// Poll every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_poll: None,
+            last: None,
+        }
+    }
+
+    /// Wait until the next poll is due, and poll
+    pub async fn next(&mut self, client: &mut MpdClient) -> Result<StatsDelta, Error> {
+        if let Some(deadline) = self.next_poll {
+            sleep_until(deadline).await;
+        }
+
+        self.poll(client).await
+    }
+
+    /// Poll now
+    pub async fn poll(&mut self, client: &mut MpdClient) -> Result<StatsDelta, Error> {
+        let stats = client.stats().await?;
+        let status = client.status().await?;
+        let now = Instant::now();
+        self.next_poll = Some(now + self.interval);
+
+        let delta = StatsDelta::new(self.last.as_ref(), stats, status, now);
+        self.last = Some(delta.stats.clone());
+        Ok(delta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(uptime: u64, playtime: u64, songs: u32) -> Stats {
+        Stats {
+            uptime: Duration::from_secs(uptime),
+            playtime: Duration::from_secs(playtime),
+            songs,
+            ..Stats::default()
+        }
+    }
+
+    #[test]
+    fn deltas() {
+        let now = Instant::now();
+        let first = StatsDelta::new(None, stats(100, 50, 10), Status::default(), now);
+        assert_eq!(first.songs, 0);
+        assert!(!first.restarted);
+
+        let prev = stats(100, 50, 10);
+        let delta = StatsDelta::new(Some(&prev), stats(160, 80, 12), Status::default(), now);
+        assert_eq!(delta.songs, 2);
+        assert_eq!(delta.played, Duration::from_secs(30));
+        assert!(!delta.restarted);
+
+        let delta = StatsDelta::new(Some(&prev), stats(20, 5, 8), Status::default(), now);
+        assert_eq!(delta.songs, -2);
+        assert_eq!(delta.played, Duration::from_secs(5));
+        assert!(delta.restarted);
+    }
+}
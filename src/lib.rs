@@ -1,9 +1,35 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 #[cfg(feature = "client")]
-mod client;
-mod protocol;
+pub mod client;
+pub mod protocol;
 
 #[cfg(feature = "client")]
 pub use client::*;
+pub use protocol::filter;
 pub use protocol::*;
+
+/// The types and traits needed by most users of the crate
+///
+/// # Example
+/// ```no_run
+/// use async_mpd::prelude::*;
+///
+/// # async fn example(mpd: &mut MpdClient) -> Result<(), Error> {
+/// let tracks = mpd.find(&Filter::with(Tag::Album.equals("Kind of Blue"))).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub mod prelude {
+    #[cfg(feature = "client")]
+    pub use crate::client::{
+        Error, ListallResponse, ListallinfoResponse, MpdClient, MpdClientBuilder,
+    };
+    pub use crate::protocol::{
+        Directory, Filter, FilterExpr, Playlist, Primary, SongId, SongPos, State, Stats, Status,
+        StoredPlaylist, Subsystem, Tag, ToFilterExpr, Track,
+    };
+}
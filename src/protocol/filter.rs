@@ -0,0 +1,319 @@
+//! Search filters, for the commands that find songs by their tags
+
+use crate::{Primary, Tag, Track};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+pub trait ToFilterExpr {
+    /// Tag equals
+    fn equals<T: ToString>(self, s: T) -> FilterExpr;
+
+    /// Tag contains
+    fn contains<T: ToString>(self, s: T) -> FilterExpr;
+}
+
+impl ToFilterExpr for Tag {
+    fn equals<T: ToString>(self, s: T) -> FilterExpr {
+        FilterExpr::Equals(self, s.to_string())
+    }
+
+    fn contains<T: ToString>(self, s: T) -> FilterExpr {
+        FilterExpr::Contains(self, s.to_string())
+    }
+}
+
+/// Filter expression used by search function
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum FilterExpr {
+    Equals(Tag, String),
+    Contains(Tag, String),
+    /// Songs added to the database since the given time. Requires Mpd 0.24
+    AddedSince(DateTime<Utc>),
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Songs without `tag`, such as songs without an album artist
+    ///
+    /// Mpd matches a missing tag with the empty value, so this is `(tag == "")`. For
+    /// [`Tag::Any`] it matches songs without any tags.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::{Filter, FilterExpr, Tag};
+    ///
+    /// let filter = Filter::with(FilterExpr::absent(Tag::AlbumArtist));
+    /// assert_eq!(filter.to_query().unwrap(), r#"((AlbumArtist == ""))"#);
+    /// ```
+    pub fn absent(tag: Tag) -> Self {
+        FilterExpr::Equals(tag, String::new())
+    }
+
+    /// Songs with `tag`, with any value. The opposite of [`FilterExpr::absent`]
+    pub fn present(tag: Tag) -> Self {
+        FilterExpr::Not(Box::new(FilterExpr::absent(tag)))
+    }
+
+    pub fn to_query(&self) -> String {
+        match self {
+            FilterExpr::Equals(tag, s) => format!("({} == {})", tag, quote(s)),
+            FilterExpr::Contains(tag, s) => format!("({} contains {})", tag, quote(s)),
+            FilterExpr::AddedSince(time) => format!(
+                "(added-since {})",
+                quote(&time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            ),
+            FilterExpr::Not(exp) => format!("(!{})", exp.to_query()),
+        }
+    }
+}
+
+/// Abstraction over search filter
+///
+/// Serializable, for saved searches that are stored and run again later.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    exprs: Vec<FilterExpr>,
+}
+
+impl Filter {
+    pub fn new() -> Self {
+        Self { exprs: Vec::new() }
+    }
+
+    pub fn with(filter: FilterExpr) -> Self {
+        Self {
+            exprs: vec![filter],
+        }
+    }
+
+    pub fn and(mut self, other: FilterExpr) -> Filter {
+        self.exprs.push(other);
+        self
+    }
+
+    /// Songs not matching `other`, such as songs without the word "live" in any tag with
+    /// `Tag::Any.contains("live")`
+    ///
+    /// A new filter matches all songs, so this also excludes songs from a filter without other
+    /// expressions.
+    pub fn and_not(mut self, other: FilterExpr) -> Self {
+        self.exprs.push(FilterExpr::Not(Box::new(other)));
+        self
+    }
+
+    /// The filter has no expressions, and matches all songs
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Filter for the text typed into a search box
+    ///
+    /// The query is split into words, where double quotes group words into one, and every word
+    /// has to match. Words of the form `tag:value`, with the name of a [`Tag`] in any case,
+    /// match songs with the tag containing the value, other words match songs with any tag
+    /// containing the word.
+    ///
+    /// # Example
+    /// ```
+    /// use async_mpd::Filter;
+    ///
+    /// let filter = Filter::parse_user_query(r#"artist:beatles "white album" "#);
+    /// assert_eq!(
+    ///     filter.to_query().unwrap(),
+    ///     r#"((Artist contains "beatles") AND (any contains "white album"))"#
+    /// );
+    /// ```
+    pub fn parse_user_query(query: &str) -> Self {
+        let exprs = query_words(query)
+            .into_iter()
+            .map(|word| match word.split_once(':') {
+                Some((tag, value)) if !value.is_empty() => match tag.parse::<Tag>() {
+                    Ok(tag) => tag.contains(value),
+                    Err(_) => Tag::Any.contains(word),
+                },
+                _ => Tag::Any.contains(word),
+            })
+            .collect();
+
+        Self { exprs }
+    }
+
+    /// Songs of the same album as `track`, None if it has no album tag
+    ///
+    /// The album is matched together with the album artist, or the artist if the track has no
+    /// album artist, so that albums with the same name by different artists aren't mixed up.
+    pub fn same_album(track: &Track) -> Option<Self> {
+        let filter = Filter::with(Tag::Album.equals(track.album.as_ref()?));
+
+        let filter = match (&track.album_artist, track.artist.primary()) {
+            (Some(album_artist), _) => filter.and(Tag::AlbumArtist.equals(album_artist)),
+            (None, Some(artist)) => filter.and(Tag::Artist.equals(artist)),
+            (None, None) => filter,
+        };
+
+        Some(filter)
+    }
+
+    /// Songs by the same artist as `track`, None if it has neither an artist nor an album
+    /// artist tag
+    pub fn same_artist(track: &Track) -> Option<Self> {
+        match (track.artist.primary(), &track.album_artist) {
+            (Some(artist), _) => Some(Filter::with(Tag::Artist.equals(artist))),
+            (None, Some(album_artist)) => Some(Filter::with(Tag::AlbumArtist.equals(album_artist))),
+            (None, None) => None,
+        }
+    }
+
+    pub fn to_query(&self) -> Option<String> {
+        if self.exprs.is_empty() {
+            return None;
+        }
+
+        let joined = self
+            .exprs
+            .iter()
+            .map(|filter| filter.to_query())
+            .collect::<Vec<_>>()
+            .join(" AND ");
+
+        Some(format!("({})", joined))
+    }
+}
+
+/// Words of a search box query, with double quoted parts kept together
+fn query_words(query: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(core::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Quotes `arg` as a single argument of a command line, escaping backslashes and double quotes
+///
+/// # Example
+/// ```
+/// assert_eq!(async_mpd::quote(r#"say "hi""#), r#""say \"hi\"""#);
+/// ```
+pub fn quote(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+
+    for c in arg.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_album_and_artist() {
+        let track = Track::builder("a.flac")
+            .artist("Artist")
+            .album("Album")
+            .build();
+
+        assert_eq!(
+            Filter::same_album(&track).unwrap().to_query().unwrap(),
+            r#"((Album == "Album") AND (Artist == "Artist"))"#
+        );
+        assert_eq!(
+            Filter::same_artist(&track).unwrap().to_query().unwrap(),
+            r#"((Artist == "Artist"))"#
+        );
+
+        let added = "2024-01-02T03:04:05Z".parse().unwrap();
+        assert_eq!(
+            Filter::with(FilterExpr::AddedSince(added))
+                .to_query()
+                .unwrap(),
+            r#"((added-since "2024-01-02T03:04:05Z"))"#
+        );
+
+        let untagged = Track::builder("b.flac").build();
+        assert!(Filter::same_album(&untagged).is_none());
+        assert!(Filter::same_artist(&untagged).is_none());
+    }
+
+    #[test]
+    fn absent_tags() {
+        assert_eq!(
+            Filter::with(Tag::Genre.equals("Jazz"))
+                .and(FilterExpr::absent(Tag::AlbumArtist))
+                .and(FilterExpr::present(Tag::Date))
+                .to_query()
+                .unwrap(),
+            r#"((Genre == "Jazz") AND (AlbumArtist == "") AND (!(Date == "")))"#
+        );
+        assert_eq!(
+            Filter::new()
+                .and_not(Tag::Any.contains("live"))
+                .to_query()
+                .unwrap(),
+            r#"((!(any contains "live")))"#
+        );
+    }
+
+    #[test]
+    fn user_query() {
+        let query = |q: &str| Filter::parse_user_query(q).to_query().unwrap_or_default();
+
+        assert_eq!(
+            query("artist:beatles white album"),
+            r#"((Artist contains "beatles") AND (any contains "white") AND (any contains "album"))"#
+        );
+        assert_eq!(
+            query(r#"  AlbumArtist:"The Beatles"  title:"let it" "#),
+            r#"((AlbumArtist contains "The Beatles") AND (Title contains "let it"))"#
+        );
+        // Unknown tags and empty values are searched for as they are
+        assert_eq!(
+            query("http://radio mood: genre:"),
+            r#"((any contains "http://radio") AND (any contains "mood:") AND (any contains "genre:"))"#
+        );
+        assert_eq!(query("   "), "");
+        assert_eq!(query(r#" "" "#), "");
+    }
+
+    #[test]
+    fn saved_filter() {
+        assert!(Filter::new().is_empty());
+
+        let filter = Filter::with(Tag::Genre.equals("Jazz")).and_not(Tag::Artist.contains("Kenny"));
+        assert!(!filter.is_empty());
+
+        let saved = serde_json::to_string(&filter).unwrap();
+        let restored: Filter = serde_json::from_str(&saved).unwrap();
+        assert_eq!(restored, filter.clone());
+        assert_eq!(restored.to_query(), filter.to_query());
+    }
+}
@@ -1,22 +1,132 @@
+//! Types of the Mpd protocol, such as songs, the status of the player, and search filters
+//!
+//! The types only need `alloc`, and are available without the `client` feature.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use chrono::{DateTime, Utc};
+use core::convert::Infallible;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::num::ParseIntError;
+use core::ops::Range;
+use core::str::FromStr;
+use core::time::Duration;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+pub mod filter;
+
+pub use filter::*;
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Song id, unique for a song in the queue and stable when the queue is modified
+pub struct SongId(pub u32);
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Position of a song in the queue
+pub struct SongPos(pub u32);
+
+impl FromStr for SongId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(SongId)
+    }
+}
+
+impl FromStr for SongPos {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(SongPos)
+    }
+}
+
+impl fmt::Display for SongId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
 
-#[derive(Deserialize, Serialize, Debug, Default)]
-/// Playlist on the server
+impl fmt::Display for SongPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+/// Where to insert songs in the queue
+pub enum Position {
+    /// At a position in the queue
+    At(SongPos),
+    /// The given number of songs after the current song, `AfterCurrent(0)` being right after
+    /// it. Requires Mpd 0.23
+    AfterCurrent(u32),
+    /// The given number of songs before the current song, `BeforeCurrent(0)` being right
+    /// before it. Requires Mpd 0.23
+    BeforeCurrent(u32),
+}
+
+impl From<SongPos> for Position {
+    fn from(pos: SongPos) -> Self {
+        Position::At(pos)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::At(pos) => pos.fmt(f),
+            Position::AfterCurrent(n) => write!(f, "+{}", n),
+            Position::BeforeCurrent(n) => write!(f, "-{}", n),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+/// Playlist file in the music directory, as listed by `lsinfo` and `listallinfo`
+///
+/// Addressed by its path, unlike a [`StoredPlaylist`], which is addressed by its name.
 pub struct Playlist {
+    /// Path in the music directory
     pub path: String,
     pub last_modified: Option<DateTime<Utc>>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+/// Playlist stored by the server, as returned by `listplaylists`
+///
+/// Addressed by its name in commands such as `load` and `listplaylistinfo`.
+pub struct StoredPlaylist {
+    pub name: String,
+    pub last_modified: Option<DateTime<Utc>>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Default)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 /// Directory on the server
 pub struct Directory {
+    /// Path of the directory, decoded lossily if it isn't valid UTF-8
     pub path: String,
+    /// The undecoded path, if it isn't valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path_raw: Option<Vec<u8>>,
     pub last_modified: Option<DateTime<Utc>>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
 /// Mpd status response
 pub struct Status {
     /// Name of current partition
@@ -25,16 +135,15 @@ pub struct Status {
     pub volume: Option<u8>,
     pub repeat: bool,
     pub random: bool,
-    /// 0, 1 or Oneshot
-    pub single: String,
-    pub consume: bool,
+    pub single: SingleMode,
+    pub consume: ConsumeMode,
     /// Playlist version number
     pub playlist: u32,
     pub playlistlength: u32,
-    pub song: Option<u32>,
-    pub songid: Option<u32>,
-    pub nextsong: Option<u32>,
-    pub nextsongid: Option<u32>,
+    pub song: Option<SongPos>,
+    pub songid: Option<SongId>,
+    pub nextsong: Option<SongPos>,
+    pub nextsongid: Option<SongId>,
     // TODO: mpd returns this as "291:336" for 291.336 seconds.
     // It’s almost usually just a few ms ahead of elapsed,
     // so I’m not sure if we need this at all.
@@ -54,20 +163,286 @@ pub struct Status {
     pub xfade: Option<u32>,
     pub audio: Option<String>,
     pub updating_db: Option<u32>,
-    pub error: Option<String>,
+    pub error: Option<PlayerError>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
+}
+
+impl Status {
+    /// What changed between two statuses of the same server
+    pub fn diff(old: &Status, new: &Status) -> StatusDelta {
+        StatusDelta {
+            state: old.state != new.state,
+            song: old.songid != new.songid || old.song != new.song,
+            next_song: old.nextsongid != new.nextsongid || old.nextsong != new.nextsong,
+            volume: old.volume != new.volume,
+            options: old.repeat != new.repeat
+                || old.random != new.random
+                || old.single != new.single
+                || old.consume != new.consume
+                || old.xfade != new.xfade
+                || old.mixrampdb != new.mixrampdb
+                || old.mixrampdelay != new.mixrampdelay,
+            queue: old.playlist != new.playlist,
+            update: old.updating_db != new.updating_db,
+            error: old.error != new.error,
+            partition: old.partition != new.partition,
+            audio: old.audio != new.audio,
+        }
+    }
+}
+
+/// Aspects of the player that changed between two statuses, see [`Status::diff`]
+///
+/// The elapsed time and bitrate change all the time while playing, and aren't included.
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusDelta {
+    /// Playing, paused or stopped
+    pub state: bool,
+    /// The current song, or its position in the queue
+    pub song: bool,
+    /// The next song, or its position in the queue
+    pub next_song: bool,
+    pub volume: bool,
+    /// Repeat, random, single, consume, crossfade or MixRamp
+    pub options: bool,
+    /// The queue was modified
+    pub queue: bool,
+    /// A database update started or finished
+    pub update: bool,
+    /// The player error
+    pub error: bool,
+    pub partition: bool,
+    /// Audio format of the current song
+    pub audio: bool,
+}
+
+impl StatusDelta {
+    /// Nothing changed
+    pub fn is_empty(&self) -> bool {
+        *self == StatusDelta::default()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+/// Error reported by the player, such as a decoder error. Cleared with `clearerror`
+pub struct PlayerError(pub String);
+
+impl FromStr for PlayerError {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(PlayerError(s.to_string()))
+    }
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+/// Single mode. When on, playback stops after the current song, or repeats it if repeat is
+/// enabled
+pub enum SingleMode {
+    #[default]
+    Off,
+    On,
+    /// Stop after the current song, and then turn single mode off. Since Mpd 0.21
+    Oneshot,
+}
+
+impl FromStr for SingleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(SingleMode::Off),
+            "1" => Ok(SingleMode::On),
+            "oneshot" => Ok(SingleMode::Oneshot),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SingleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SingleMode::Off => "0",
+            SingleMode::On => "1",
+            SingleMode::Oneshot => "oneshot",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+/// Consume mode. When on, songs are removed from the queue after they were played
+pub enum ConsumeMode {
+    #[default]
+    Off,
+    On,
+    /// Remove the current song after it was played, and then turn consume mode off. Since
+    /// Mpd 0.24
+    Oneshot,
+}
+
+impl From<bool> for ConsumeMode {
+    fn from(on: bool) -> Self {
+        if on {
+            ConsumeMode::On
+        } else {
+            ConsumeMode::Off
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq)]
+impl From<ConsumeMode> for bool {
+    /// Whether songs are consumed, in either the on or the oneshot mode
+    fn from(mode: ConsumeMode) -> Self {
+        mode != ConsumeMode::Off
+    }
+}
+
+impl FromStr for ConsumeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(ConsumeMode::Off),
+            "1" => Ok(ConsumeMode::On),
+            "oneshot" => Ok(ConsumeMode::Oneshot),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ConsumeMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ConsumeMode::Off => "0",
+            ConsumeMode::On => "1",
+            ConsumeMode::Oneshot => "oneshot",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Protocol version of the server
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseIntError;
+
+    /// Parses a version, with or without the `OK MPD` prefix of the connection greeting
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches("OK MPD ");
+        let mut parts = s.splitn(3, '.');
+
+        let major = parts.next().unwrap_or_default().parse()?;
+        let minor = parts.next().unwrap_or("0").parse()?;
+        let patch = parts.next().unwrap_or("0").parse()?;
+
+        Ok(Version::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, PartialEq)]
+/// Playback progress of the current song, extrapolated from a status
+pub struct Progress {
+    /// Elapsed time when the status was fetched
+    pub elapsed: Duration,
+    pub duration: Option<Duration>,
+    pub state: State,
+    /// When the status was fetched
+    pub fetched: Instant,
+}
+
+#[cfg(feature = "std")]
+impl Progress {
+    /// Progress from a status fetched at `fetched`
+    pub fn new(status: &Status, fetched: Instant) -> Self {
+        Self {
+            elapsed: status.elapsed.unwrap_or_default(),
+            duration: status.duration,
+            state: status.state,
+            fetched,
+        }
+    }
+
+    /// Elapsed time at `instant`. Only advances while playing, and never passes the duration
+    pub fn at(&self, instant: Instant) -> Duration {
+        if self.state != State::Play {
+            return self.elapsed;
+        }
+
+        let elapsed = self.elapsed + instant.saturating_duration_since(self.fetched);
+
+        match self.duration {
+            Some(duration) => elapsed.min(duration),
+            None => elapsed,
+        }
+    }
+
+    /// Elapsed part of the song at `instant`, between 0.0 and 1.0
+    pub fn fraction_at(&self, instant: Instant) -> Option<f64> {
+        let duration = self.duration.filter(|d| !d.is_zero())?;
+        Some(self.at(instant).as_secs_f64() / duration.as_secs_f64())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<&Status> for Progress {
+    /// Progress from a status that was just fetched
+    fn from(status: &Status) -> Self {
+        Progress::new(status, Instant::now())
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
 /// Player status
 pub enum State {
     Play,
+    #[default]
     Stop,
     Pause,
 }
 
-impl Default for State {
-    fn default() -> Self {
-        State::Stop
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            State::Play => "play",
+            State::Stop => "stop",
+            State::Pause => "pause",
+        };
+        f.write_str(s)
     }
 }
 
@@ -81,28 +456,293 @@ pub struct Stats {
     pub songs: u32,
     pub db_playtime: Duration,
     pub db_update: i32,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Storage mounted in the music directory, as returned by `listmounts`
+pub struct Mount {
+    /// Path of the mount in the music directory, empty for the music directory itself
+    pub mount: String,
+    /// Uri of the storage, left out by the server for storage without one
+    pub storage: Option<String>,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+/// State of a server at one point in time, as returned by `MpdClient::snapshot`
+///
+/// Serializable, to attach to bug reports, keep as a backup, or use as test data.
+pub struct ServerSnapshot {
+    pub status: Status,
+    pub stats: Stats,
+    /// Songs in the queue
+    pub queue: Vec<Track>,
+    /// Stored playlists
+    pub playlists: Vec<StoredPlaylist>,
+    pub outputs: Vec<Output>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Storage found on the network by a neighbor plugin, as returned by `listneighbors`
+pub struct Neighbor {
+    /// Uri of the storage, which can be mounted
+    pub neighbor: String,
+    /// Display name of the storage
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Audio output, as returned by `outputs`
+pub struct Output {
+    pub id: u32,
+    pub name: String,
+    /// Name of the output plugin, e.g. `alsa`. Reported by Mpd 0.21 and later
+    pub plugin: Option<String>,
+    pub enabled: bool,
+    /// Runtime attributes of the output plugin, by name
+    ///
+    /// Known attributes have typed accessors, such as [`Output::dop`].
+    pub attributes: BTreeMap<String, String>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
+}
+
+impl Output {
+    /// Name of the ALSA attribute for DSD over PCM
+    pub const DOP: &'static str = "dop";
+    /// Name of the ALSA attribute restricting the audio formats
+    pub const ALLOWED_FORMATS: &'static str = "allowed_formats";
+
+    /// Whether DSD over PCM is enabled, None if the output doesn't support it
+    pub fn dop(&self) -> Option<bool> {
+        self.attributes.get(Self::DOP).map(|v| v == "1")
+    }
+
+    /// Audio formats the output may use, such as `96000:16:*` or `dsd64:=dop`, None if the
+    /// output doesn't support restricting them
+    ///
+    /// An empty list allows all formats.
+    pub fn allowed_formats(&self) -> Option<Vec<&str>> {
+        self.attributes
+            .get(Self::ALLOWED_FORMATS)
+            .map(|v| v.split_whitespace().collect())
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+/// Chromaprint fingerprint of a song, as returned by `getfingerprint`
+pub struct Fingerprint(pub String);
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct DatabaseVersion(pub u32);
 
+impl FromStr for DatabaseVersion {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(DatabaseVersion)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Volume in percent, as returned by `getvol`
+pub struct Volume(pub u8);
+
+impl FromStr for Volume {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Volume)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+/// Replay gain mode, selecting the gain applied to songs with replay gain tags
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+    /// Album gain when playing albums in order, track gain otherwise
+    Auto,
+}
+
+impl FromStr for ReplayGainMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ReplayGainMode::Off),
+            "track" => Ok(ReplayGainMode::Track),
+            "album" => Ok(ReplayGainMode::Album),
+            "auto" => Ok(ReplayGainMode::Auto),
+            _ => Err(s.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for ReplayGainMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ReplayGainMode::Off => "off",
+            ReplayGainMode::Track => "track",
+            ReplayGainMode::Album => "album",
+            ReplayGainMode::Auto => "auto",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// Range of results to return, from `start` up to but not including `end`
+pub struct Window {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl From<Range<u32>> for Window {
+    fn from(r: Range<u32>) -> Self {
+        Window {
+            start: r.start,
+            end: r.end,
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.start, self.end)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// What to sort songs by
+pub enum SortKey {
+    Tag(Tag),
+    /// Time the file was last modified
+    LastModified,
+    /// Time the song was added to the database. Since Mpd 0.24
+    Added,
+    /// Priority of the song in the queue
+    Prio,
+}
+
+impl From<Tag> for SortKey {
+    fn from(tag: Tag) -> Self {
+        SortKey::Tag(tag)
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortKey::Tag(tag) => tag.fmt(f),
+            SortKey::LastModified => f.write_str("Last-Modified"),
+            SortKey::Added => f.write_str("added"),
+            SortKey::Prio => f.write_str("prio"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+/// Sort order of songs, for the commands with a `sort` argument
+///
+/// Displayed in the protocol syntax, with `-` in front for descending order.
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl Sort {
+    /// Sort by `key` in ascending order
+    pub fn by<K: Into<SortKey>>(key: K) -> Self {
+        Self {
+            key: key.into(),
+            descending: false,
+        }
+    }
+
+    /// The same order, descending
+    pub fn descending(self) -> Self {
+        Self {
+            descending: true,
+            ..self
+        }
+    }
+}
+
+impl<K: Into<SortKey>> From<K> for Sort {
+    fn from(key: K) -> Self {
+        Sort::by(key)
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            f.write_str("-")?;
+        }
+        self.key.fmt(f)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Sticker, a name and value attached to a song
+pub struct Sticker {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+/// Song with a matching sticker, as returned by sticker find
+pub struct StickerMatch {
+    pub file: String,
+    pub sticker: Sticker,
+}
+
+/// Access to the first value of a tag that can have multiple values, such as
+/// [`Track::artist`]
+pub trait Primary {
+    /// The first value, if any
+    fn primary(&self) -> Option<&str>;
+}
+
+impl Primary for [String] {
+    fn primary(&self) -> Option<&str> {
+        self.first().map(String::as_str)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, Default)]
 /// Track
 pub struct Track {
+    /// Path of the song, decoded lossily if it isn't valid UTF-8
     pub file: String,
+    /// The undecoded path, if it isn't valid UTF-8
+    ///
+    /// Servers with music in legacy encodings can send such paths. The lossy `file` doesn't
+    /// name the song on the server, so commands can't refer to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_raw: Option<Vec<u8>>,
     pub artist_sort: Option<String>,
     pub album_artist: Option<String>,
     pub album_sort: Option<String>,
     pub album_artist_sort: Option<String>,
     pub performer: Vec<String>,
-    pub genre: Option<String>,
+    pub genre: Vec<String>,
     pub title: Option<String>,
     pub track: Option<u32>,
     pub album: Option<String>,
-    pub artist: Option<String>,
-    pub pos: Option<u32>,
-    pub id: Option<u32>,
+    pub artist: Vec<String>,
+    pub pos: Option<SongPos>,
+    pub id: Option<SongId>,
     pub last_modified: Option<DateTime<Utc>>,
+    /// When the song was added to the database. Since Mpd 0.24
+    pub added: Option<DateTime<Utc>>,
     pub original_date: Option<String>,
     pub time: Option<String>,
     pub format: Option<String>,
@@ -117,9 +757,123 @@ pub struct Track {
     pub musicbraiz_releasetrackid: Option<String>,
     pub musicbraiz_workid: Option<String>,
     pub composer: Vec<String>,
+    /// Fields of the response not known by this crate, with keys in lower case
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown: Vec<(String, String)>,
+}
+
+/// Tracks are considered equal if they refer to the same file and queue id
+impl PartialEq for Track {
+    fn eq(&self, other: &Self) -> bool {
+        self.file == other.file && self.id == other.id
+    }
+}
+
+impl Eq for Track {}
+
+impl Hash for Track {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.file.hash(state);
+        self.id.hash(state);
+    }
+}
+
+impl Track {
+    /// Create a builder for a Track with the given file
+    pub fn builder<S: Into<String>>(file: S) -> TrackBuilder {
+        TrackBuilder::new(file)
+    }
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Builder for Tracks, mostly useful for tests and fixtures
+#[derive(Clone, Debug, Default)]
+pub struct TrackBuilder {
+    track: Track,
+}
+
+impl TrackBuilder {
+    pub fn new<S: Into<String>>(file: S) -> Self {
+        Self {
+            track: Track {
+                file: file.into(),
+                ..Track::default()
+            },
+        }
+    }
+
+    /// Add an artist, for songs with more than one artist call this once for each
+    pub fn artist<S: Into<String>>(mut self, artist: S) -> Self {
+        self.track.artist.push(artist.into());
+        self
+    }
+
+    pub fn album_artist<S: Into<String>>(mut self, album_artist: S) -> Self {
+        self.track.album_artist = Some(album_artist.into());
+        self
+    }
+
+    pub fn album<S: Into<String>>(mut self, album: S) -> Self {
+        self.track.album = Some(album.into());
+        self
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Self {
+        self.track.title = Some(title.into());
+        self
+    }
+
+    /// Add a genre, for songs with more than one genre call this once for each
+    pub fn genre<S: Into<String>>(mut self, genre: S) -> Self {
+        self.track.genre.push(genre.into());
+        self
+    }
+
+    pub fn date<S: Into<String>>(mut self, date: S) -> Self {
+        self.track.date = Some(date.into());
+        self
+    }
+
+    pub fn track(mut self, track: u32) -> Self {
+        self.track.track = Some(track);
+        self
+    }
+
+    pub fn disc(mut self, disc: u32) -> Self {
+        self.track.disc = Some(disc);
+        self
+    }
+
+    pub fn pos(mut self, pos: SongPos) -> Self {
+        self.track.pos = Some(pos);
+        self
+    }
+
+    pub fn id(mut self, id: SongId) -> Self {
+        self.track.id = Some(id);
+        self
+    }
+
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.track.duration = duration;
+        self
+    }
+
+    pub fn last_modified(mut self, last_modified: DateTime<Utc>) -> Self {
+        self.track.last_modified = Some(last_modified);
+        self
+    }
+
+    pub fn added(mut self, added: DateTime<Utc>) -> Self {
+        self.track.added = Some(added);
+        self
+    }
+
+    pub fn build(self) -> Track {
+        self.track
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 /// Track tags
 pub enum Tag {
     Artist,
@@ -150,7 +904,88 @@ pub enum Tag {
     Any,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+impl fmt::Display for Tag {
+    /// The name of the tag in the protocol
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Tag::Artist => "Artist",
+            Tag::ArtistSort => "ArtistSort",
+            Tag::Album => "Album",
+            Tag::AlbumSort => "AlbumSort",
+            Tag::AlbumArtist => "AlbumArtist",
+            Tag::AlbumSortOrder => "AlbumArtistSort",
+            Tag::Title => "Title",
+            Tag::Track => "Track",
+            Tag::Name => "Name",
+            Tag::Genre => "Genre",
+            Tag::Date => "Date",
+            Tag::Composer => "Composer",
+            Tag::Performer => "Performer",
+            Tag::Conductor => "Conductor",
+            Tag::Work => "Work",
+            Tag::Grouping => "Grouping",
+            Tag::Comment => "Comment",
+            Tag::Disc => "Disc",
+            Tag::Label => "Label",
+            Tag::MusicbrainzArtistId => "MUSICBRAINZ_ARTISTID",
+            Tag::MusicbrainzAlbumId => "MUSICBRAINZ_ALBUMID",
+            Tag::MusicbrainzAlbumArtistId => "MUSICBRAINZ_ALBUMARTISTID",
+            Tag::MusicbrainzTrackId => "MUSICBRAINZ_TRACKID",
+            Tag::MusicbrainzReleaseTrackId => "MUSICBRAINZ_RELEASETRACKID",
+            Tag::MusicbrainzWorkId => "MUSICBRAINZ_WORKID",
+            Tag::Any => "any",
+        };
+        f.write_str(s)
+    }
+}
+
+impl Tag {
+    /// All tags
+    pub const ALL: [Tag; 26] = [
+        Tag::Artist,
+        Tag::ArtistSort,
+        Tag::Album,
+        Tag::AlbumSort,
+        Tag::AlbumArtist,
+        Tag::AlbumSortOrder,
+        Tag::Title,
+        Tag::Track,
+        Tag::Name,
+        Tag::Genre,
+        Tag::Date,
+        Tag::Composer,
+        Tag::Performer,
+        Tag::Conductor,
+        Tag::Work,
+        Tag::Grouping,
+        Tag::Comment,
+        Tag::Disc,
+        Tag::Label,
+        Tag::MusicbrainzArtistId,
+        Tag::MusicbrainzAlbumId,
+        Tag::MusicbrainzAlbumArtistId,
+        Tag::MusicbrainzTrackId,
+        Tag::MusicbrainzReleaseTrackId,
+        Tag::MusicbrainzWorkId,
+        Tag::Any,
+    ];
+}
+
+impl FromStr for Tag {
+    type Err = String;
+
+    /// The tag with the protocol name `s`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tag::ALL
+            .iter()
+            .copied()
+            .find(|tag| tag.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| s.to_string())
+    }
+}
+
+#[derive(Deserialize, Serialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
 /// Subsystem
 pub enum Subsystem {
     Database,
@@ -165,6 +1000,121 @@ pub enum Subsystem {
     Sticker,
     Subscription,
     Message,
+    Neighbor,
+    Mount,
 
     Other,
 }
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Subsystem::Database => "database",
+            Subsystem::Player => "player",
+            Subsystem::Mixer => "mixer",
+            Subsystem::Options => "options",
+            Subsystem::Update => "update",
+            Subsystem::StoredPlaylist => "stored_playlist",
+            Subsystem::Playlist => "playlist",
+            Subsystem::Output => "output",
+            Subsystem::Partitions => "partition",
+            Subsystem::Sticker => "sticker",
+            Subsystem::Subscription => "subscription",
+            Subsystem::Message => "message",
+            Subsystem::Neighbor => "neighbor",
+            Subsystem::Mount => "mount",
+            Subsystem::Other => "other",
+        };
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SingleMode, SongId, SongPos, State, Status, StatusDelta, Track, Version};
+    use std::collections::HashSet;
+
+    #[test]
+    fn version() {
+        assert_eq!("OK MPD 0.23.5".parse(), Ok(Version::new(0, 23, 5)));
+        assert_eq!("0.21".parse(), Ok(Version::new(0, 21, 0)));
+        assert!(Version::new(0, 20, 22) < Version::new(0, 21, 0));
+        assert!("OK MPD".parse::<Version>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn progress() {
+        use super::{Progress, State};
+        use std::time::{Duration, Instant};
+
+        let fetched = Instant::now();
+        let mut progress = Progress {
+            elapsed: Duration::from_secs(10),
+            duration: Some(Duration::from_secs(20)),
+            state: State::Play,
+            fetched,
+        };
+
+        let later = fetched + Duration::from_secs(5);
+        assert_eq!(progress.at(later), Duration::from_secs(15));
+        assert_eq!(progress.fraction_at(later), Some(0.75));
+        assert_eq!(
+            progress.at(fetched + Duration::from_secs(60)),
+            Duration::from_secs(20)
+        );
+
+        progress.state = State::Pause;
+        assert_eq!(progress.at(later), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn status_diff() {
+        let old = Status {
+            volume: Some(40),
+            song: Some(SongPos(1)),
+            songid: Some(SongId(7)),
+            ..Status::default()
+        };
+        assert!(Status::diff(&old, &old.clone()).is_empty());
+
+        let new = Status {
+            state: State::Play,
+            volume: Some(50),
+            single: SingleMode::Oneshot,
+            elapsed: Some(core::time::Duration::from_secs(3)),
+            ..old.clone()
+        };
+        assert_eq!(
+            Status::diff(&old, &new),
+            StatusDelta {
+                state: true,
+                volume: true,
+                options: true,
+                ..StatusDelta::default()
+            }
+        );
+
+        // The current song moved in the queue
+        let moved = Status {
+            song: Some(SongPos(0)),
+            playlist: 1,
+            ..old.clone()
+        };
+        let delta = Status::diff(&old, &moved);
+        assert!(delta.song && delta.queue && !delta.state);
+    }
+
+    #[test]
+    fn track_identity() {
+        let a = Track::builder("a.flac").id(SongId(1)).title("A").build();
+        let b = Track::builder("a.flac").id(SongId(1)).title("B").build();
+        let c = Track::builder("a.flac").id(SongId(2)).build();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let set: HashSet<Track> = vec![a, b, c].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -0,0 +1,180 @@
+//! Parsing of responses of Mpd 0.21 to 0.24, checked in under `tests/fixtures`
+//!
+//! Every value in a response has to end up in the parsed type, either in a field of its own or
+//! in `unknown`, which is checked by looking for it in the JSON serialization of the type.
+
+use async_mpd::{
+    ConsumeMode, KeyValueResponse, MixedResponseResponse, ResponseHandler, SingleMode, Stats,
+    Status, Tracks,
+};
+use futures_lite::future::block_on;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+const VERSIONS: [&str; 4] = ["mpd-0.21", "mpd-0.22", "mpd-0.23", "mpd-0.24"];
+
+fn fixture(version: &str, name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(version)
+        .join(name);
+
+    fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Key-value pairs of the response, split into records where one of `starts` is seen
+fn records(resp: &str, starts: &[&str]) -> Vec<Vec<(String, String)>> {
+    let mut records: Vec<Vec<(String, String)>> = Vec::new();
+
+    for line in resp.lines().take_while(|line| *line != "OK") {
+        let (key, value) = line.split_once(": ").expect(line);
+
+        if records.is_empty() || starts.contains(&key) {
+            records.push(Vec::new());
+        }
+
+        records.last_mut().unwrap().push((key.into(), value.into()));
+    }
+
+    records
+}
+
+/// `value` parsed as a `T`, serialized
+fn serialized<T: FromStr + Serialize>(value: &str) -> Option<Value> {
+    value
+        .parse::<T>()
+        .ok()
+        .map(|v| serde_json::to_value(v).unwrap())
+}
+
+/// The value of `key` is found somewhere in `json`
+fn contains(json: &Value, key: &str, value: &str) -> bool {
+    match json {
+        Value::String(s) => {
+            s == value
+                || (key == "single" && serialized::<SingleMode>(value) == Some(json.clone()))
+                || (key == "consume" && serialized::<ConsumeMode>(value) == Some(json.clone()))
+        }
+        Value::Number(n) => n.to_string() == value || value.parse::<f64>().ok() == n.as_f64(),
+        Value::Bool(b) => value == if *b { "1" } else { "0" },
+        Value::Object(o) if o.contains_key("secs") && o.contains_key("nanos") => {
+            let secs = o["secs"].as_f64().unwrap() + o["nanos"].as_f64().unwrap() / 1e9;
+            value.parse::<f64>().is_ok_and(|v| (v - secs).abs() < 1e-6)
+        }
+        Value::Object(o) => o.values().any(|v| contains(v, key, value)),
+        Value::Array(a) => a.iter().any(|v| contains(v, key, value)),
+        Value::Null => false,
+    }
+}
+
+fn assert_lossless(version: &str, record: &[(String, String)], json: &Value) {
+    for (key, value) in record {
+        assert!(
+            contains(json, key, value),
+            "{}: `{}: {}` lost in {}",
+            version,
+            key,
+            value,
+            json
+        );
+    }
+}
+
+#[test]
+fn status() {
+    for version in VERSIONS {
+        let resp = fixture(version, "status.txt");
+        let status = block_on(KeyValueResponse::<Status>::handle(&mut resp.as_bytes())).unwrap();
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_lossless(version, &records(&resp, &[])[0], &json);
+    }
+
+    let resp = fixture("mpd-0.24", "status.txt");
+    let status = block_on(KeyValueResponse::<Status>::handle(&mut resp.as_bytes())).unwrap();
+    assert_eq!(status.consume, ConsumeMode::Oneshot);
+}
+
+#[test]
+fn stats() {
+    for version in VERSIONS {
+        let resp = fixture(version, "stats.txt");
+        let stats = block_on(KeyValueResponse::<Stats>::handle(&mut resp.as_bytes())).unwrap();
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_lossless(version, &records(&resp, &[])[0], &json);
+        assert!(stats.unknown.is_empty(), "{}: {:?}", version, stats.unknown);
+    }
+}
+
+#[test]
+fn search() {
+    for version in VERSIONS {
+        let resp = fixture(version, "search.txt");
+        let tracks = block_on(Tracks::handle(&mut resp.as_bytes())).unwrap();
+        let records = records(&resp, &["file"]);
+
+        assert_eq!(tracks.len(), records.len(), "{}", version);
+
+        for (track, record) in tracks.iter().zip(&records) {
+            let json = serde_json::to_value(track).unwrap();
+            assert_lossless(version, record, &json);
+        }
+    }
+}
+
+#[test]
+fn lsinfo() {
+    for version in VERSIONS {
+        let resp = fixture(version, "lsinfo.txt");
+        let listing = block_on(MixedResponseResponse::handle(&mut resp.as_bytes())).unwrap();
+        let json = serde_json::to_value(&listing).unwrap();
+
+        let (mut files, mut dirs, mut playlists) = (0, 0, 0);
+
+        for record in records(&resp, &["file", "directory", "playlist"]) {
+            let entry = match record[0].0.as_str() {
+                "file" => &json["files"][post_inc(&mut files)],
+                "directory" => &json["dirs"][post_inc(&mut dirs)],
+                "playlist" => &json["playlist"][post_inc(&mut playlists)],
+                other => panic!("{}: unexpected record {}", version, other),
+            };
+
+            assert_lossless(version, &record, entry);
+        }
+
+        assert_eq!(listing.files.len(), files, "{}", version);
+        assert_eq!(listing.dirs.len(), dirs, "{}", version);
+        assert_eq!(listing.playlist.len(), playlists, "{}", version);
+    }
+}
+
+fn post_inc(i: &mut usize) -> usize {
+    *i += 1;
+    *i - 1
+}
+
+#[test]
+fn exotic_tags() {
+    let resp = fixture("mpd-0.24", "search.txt");
+    let track = &block_on(Tracks::handle(&mut resp.as_bytes())).unwrap()[0];
+
+    assert_eq!(track.composer, vec!["First Composer", "Second Composer"]);
+    assert_eq!(track.added, Some("2024-01-02T03:04:05Z".parse().unwrap()));
+    assert!(track
+        .unknown
+        .contains(&("ensemble".to_string(), "The Ensemble".to_string())));
+
+    let resp = fixture("mpd-0.21", "search.txt");
+    let tracks = block_on(Tracks::handle(&mut resp.as_bytes())).unwrap();
+
+    assert_eq!(tracks[1].artist, vec!["Singer One", "Singer Two"]);
+    // Track numbers with the number of tracks aren't parsed, but kept
+    assert_eq!(tracks[1].track, None);
+    assert!(tracks[1]
+        .unknown
+        .contains(&("track".to_string(), "3/12".to_string())));
+}